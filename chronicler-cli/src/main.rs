@@ -1,4 +1,5 @@
 #![deny(warnings)]
+#![allow(clippy::collapsible_if)]
 
 mod history;
 mod navigator;
@@ -11,13 +11,13 @@ use std::{
     io::Write,
     os::unix::prelude::OsStrExt,
     path::{Path, PathBuf},
-    sync::{Arc, LazyLock, RwLock},
+    sync::{Arc, LazyLock, OnceLock, RwLock},
 };
 use sweep::{
     Haystack, Positions, Sweep, SweepEvent, SweepOptions,
     common::{AbortJoinHandle, LockExt},
     surf_n_term::{
-        Glyph,
+        Glyph, TerminalWaker,
         view::{Either, View},
     },
 };
@@ -28,12 +28,14 @@ pub enum NavigatorItem {
     History(HistoryEntry),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct NavigatorContext {
     pub cwd: Arc<str>,
     pub home_dir: Arc<str>,
     users_cache: Arc<RwLock<HashMap<u32, Option<uzers::User>>>>,
     groups_cache: Arc<RwLock<HashMap<u32, Option<uzers::Group>>>>,
+    /// Waker of the sweep terminal, set once sweep is created
+    pub waker: Arc<OnceLock<TerminalWaker>>,
 }
 
 impl NavigatorContext {
@@ -172,8 +174,10 @@ impl Navigator {
                 .into(),
             users_cache: Default::default(),
             groups_cache: Default::default(),
+            waker: Default::default(),
         };
-        let sweep = Sweep::new(ctx, options)?;
+        let sweep = Sweep::new(ctx.clone(), options)?;
+        let _ = ctx.waker.set(sweep.waker());
         sweep
             .scorer_by_name(None, Some("substr".to_owned()))
             .await?;
@@ -283,7 +287,14 @@ impl Navigator {
                         mode.enter(self).await?;
                     }
                 }
-                SweepEvent::Window { .. } => {}
+                SweepEvent::Window { .. }
+                | SweepEvent::QueryChanged { .. }
+                | SweepEvent::CursorChanged { .. }
+                | SweepEvent::MarksChanged { .. }
+                | SweepEvent::ScorerChanged { .. }
+                | SweepEvent::KeepOrderChanged { .. }
+                | SweepEvent::ThemeChanged { .. }
+                | SweepEvent::LayoutChanged { .. } => {}
             }
         }
         Ok(Vec::new())
@@ -18,7 +18,7 @@ use std::{
     task::Poll,
 };
 use sweep::{
-    Haystack, HaystackBasicPreview, HaystackDefaultView,
+    FilePreview, Haystack, HaystackBasicPreview, HaystackDefaultView,
     surf_n_term::{
         CellWrite, Face, FaceAttrs,
         view::{Flex, Justify, Text},
@@ -106,7 +106,7 @@ impl Haystack for PathItem {
     type Context = NavigatorContext;
     type View = Flex<'static>;
     type Preview = HaystackBasicPreview<Text>;
-    type PreviewLarge = ();
+    type PreviewLarge = FilePreview;
 
     fn haystack_scope<S>(&self, ctx: &Self::Context, mut scope: S)
     where
@@ -197,6 +197,19 @@ impl Haystack for PathItem {
         }
         Some(HaystackBasicPreview::new(text, None))
     }
+
+    fn preview_large(
+        &self,
+        ctx: &Self::Context,
+        _positions: sweep::Positions<&[u8]>,
+        theme: &sweep::Theme,
+    ) -> Option<Self::PreviewLarge> {
+        if !self.metadata.as_ref()?.is_file() {
+            return None;
+        }
+        let waker = ctx.waker.get()?.clone();
+        FilePreview::new(&self.path, None, theme, waker).ok()
+    }
 }
 
 /// Walk directory returning a stream of [PathItem] in the breadth first order
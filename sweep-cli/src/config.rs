@@ -0,0 +1,181 @@
+use anyhow::{Context, Error};
+use serde::{Deserialize, Deserializer, de};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use sweep::{
+    FieldSelector, KeyBinding, PreviewLayout, ProcessOptions, Theme, WindowLayout,
+    common::config_dir,
+};
+
+/// User configuration loaded from `$XDG_CONFIG_HOME/sweep/config.toml`
+///
+/// ```toml
+/// prompt = "FILES"
+/// theme = "dark,accent=#8ec07c"
+/// layout = "float,h=15"
+/// preview_window = "right,50%"
+/// preview_options = "debounce=100,prefetch"
+/// preview_file = "0"
+/// scorer = "substr"
+/// history = "/home/user/.local/share/sweep/history"
+/// vi = true
+///
+/// [bind]
+/// "ctrl+j" = "list.item.next"
+/// "ctrl+k" = "list.item.prev"
+/// "ctrl+x ctrl+k" = "input.delete.end"
+/// "alt+m" = ["sweep.mark.current", "list.item.next"]
+/// ```
+///
+/// Options specified on the command line take precedence over the ones
+/// from the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub prompt: Option<String>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub theme: Option<Theme>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub layout: Option<WindowLayout>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub preview_window: Option<PreviewLayout>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub preview_options: Option<ProcessOptions>,
+    #[serde(deserialize_with = "from_str_opt")]
+    pub preview_file: Option<FieldSelector>,
+    pub scorer: Option<String>,
+    pub history: Option<PathBuf>,
+    pub vi: bool,
+    #[serde(deserialize_with = "bindings")]
+    pub bind: Vec<KeyBinding>,
+}
+
+impl Config {
+    /// Default path of the configuration file
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("config.toml"))
+    }
+
+    /// Load configuration from the path, or from the default path if it is not
+    /// specified. Missing default configuration file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, is_default) = match path {
+            Some(path) => (path.to_owned(), false),
+            None => match Self::default_path() {
+                Some(path) => (path, true),
+                None => return Ok(Self::default()),
+            },
+        };
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(error) if is_default && error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(error) => {
+                return Err(Error::from(error)
+                    .context(format!("failed to read config: {}", path.display())));
+            }
+        };
+        Self::from_str(&data).with_context(|| format!("invalid config: {}", path.display()))
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(data)?)
+    }
+}
+
+fn from_str_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// Binding is either a single action (or comma separated actions) or a list of actions
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingTags {
+    Single(String),
+    Chain(Vec<String>),
+}
+
+fn bindings<'de, D>(deserializer: D) -> Result<Vec<KeyBinding>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bindings: BTreeMap<String, BindingTags> = BTreeMap::deserialize(deserializer)?;
+    bindings
+        .into_iter()
+        .map(|(chord, tags)| match tags {
+            BindingTags::Single(tag) => format!("{chord}:{tag}").parse(),
+            BindingTags::Chain(tags) => format!("{chord}:{}", tags.join(",")).parse(),
+        })
+        .collect::<Result<_, Error>>()
+        .map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse() -> Result<(), Error> {
+        let config: Config = r#"
+            prompt = "FILES"
+            layout = "full,h=-30%"
+            preview_window = "top,40%"
+            preview_file = "0"
+            scorer = "substr"
+            vi = true
+
+            [bind]
+            "ctrl+j" = "list.item.next"
+            "ctrl+x ctrl+k" = "input.delete.end"
+            "alt+m" = ["sweep.mark.current", "list.item.next"]
+            "normal:G" = ["list.home"]
+        "#
+        .parse()?;
+        assert_eq!(config.prompt.as_deref(), Some("FILES"));
+        assert!(matches!(config.layout, Some(WindowLayout::Full { .. })));
+        assert_eq!(
+            config.preview_window.map(|layout| layout.to_string()),
+            Some("top,40%".to_owned())
+        );
+        assert!(config.preview_file.is_some());
+        assert_eq!(config.scorer.as_deref(), Some("substr"));
+        assert!(config.vi);
+        assert_eq!(
+            config.bind,
+            vec![
+                "alt+m:sweep.mark.current,list.item.next".parse::<KeyBinding>()?,
+                "ctrl+j:list.item.next".parse()?,
+                "ctrl+x ctrl+k:input.delete.end".parse()?,
+                "normal:G:list.home".parse()?,
+            ]
+        );
+
+        assert!("unknown = 1".parse::<Config>().is_err());
+        assert!(
+            "[bind]\n\"ctrl+unknown\" = \"sweep.select\""
+                .parse::<Config>()
+                .is_err()
+        );
+        assert!(
+            "[bind]\n\"ctrl+q\" = [\"sweep.qiut\"]"
+                .parse::<Config>()
+                .is_err()
+        );
+        Ok(())
+    }
+}
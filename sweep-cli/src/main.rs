@@ -1,24 +1,34 @@
 #![deny(warnings)]
 #![allow(clippy::type_complexity)]
 
+mod config;
+
 use anyhow::{Context, Error};
 use argh::FromArgs;
+use config::Config;
 use futures::TryStreamExt;
 use std::{
     fs::File,
     io::Write,
     os::unix::{io::FromRawFd, net::UnixStream as StdUnixStream},
+    path::PathBuf,
     pin::Pin,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use surf_n_term::Glyph;
 use sweep::{
-    ALL_SCORER_BUILDERS, Candidate, CandidateContext, FieldSelector, ProcessCommandBuilder, Sweep,
-    SweepEvent, SweepOptions, Theme, WindowId, WindowLayout, WindowLayoutSize,
+    ALL_SCORER_BUILDERS, Candidate, CandidateContext, FieldSelector, KeyBinding, MarksPolicy,
+    PreviewLayout, ProcessCommandBuilder, ProcessOptions, Sweep, SweepEvent, SweepOptions, Theme,
+    WindowId, WindowLayout, WindowLayoutSize, WindowOrientation,
     common::{VecDeserializeSeed, json_from_slice_seed},
+    rpc::RpcFraming,
     scorer_by_name,
 };
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    signal::unix::{SignalKind, signal},
+};
 use tracing_subscriber::fmt::format::FmtSpan;
 
 #[cfg(not(target_os = "macos"))]
@@ -101,15 +111,43 @@ async fn main() -> Result<(), Error> {
         }
     };
 
-    let theme = args.theme;
+    let config = Config::load(args.config.as_deref())?;
+
+    // theme priority: command line, environment variable, config file, NO_COLOR
+    let theme = args
+        .theme
+        .or_else(|| {
+            std::env::var("SWEEP_THEME")
+                .is_ok_and(|theme| !theme.is_empty())
+                .then(Theme::from_env)
+        })
+        .or(config.theme)
+        .unwrap_or_else(|| {
+            if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                Theme::no_color()
+            } else {
+                Theme::light()
+            }
+        });
     let candidate_context = CandidateContext::new();
     candidate_context.update_named_colors(&theme);
     let mut scorers = ALL_SCORER_BUILDERS.clone();
-    scorer_by_name(&mut scorers, Some(args.scorer.as_str()));
+    let scorer = args.scorer.or(config.scorer);
+    let scorer = scorer.as_deref().unwrap_or("fuzzy");
+    if scorer_by_name(&mut scorers, Some(scorer)).is_none() {
+        return Err(anyhow::anyhow!("unknown scorer type: {}", scorer));
+    }
+    // bindings from the command line are applied last to override config
+    let mut bindings = config.bind;
+    bindings.extend(args.bind);
+    let preview_file = args.preview_file.or(config.preview_file);
     let sweep: Sweep<Candidate> = Sweep::new(
         candidate_context.clone(),
         SweepOptions {
-            prompt: args.prompt.clone(),
+            prompt: args
+                .prompt
+                .or(config.prompt)
+                .unwrap_or_else(|| "INPUT".to_owned()),
             prompt_icon: Some(args.prompt_icon),
             theme,
             keep_order: args.keep_order,
@@ -117,10 +155,19 @@ async fn main() -> Result<(), Error> {
             title: args.title,
             window_uid: args.window_uid.clone(),
             scorers,
-            layout: args.layout.unwrap_or_else(|| {
-                if args.preview_builder.is_some() {
+            bindings,
+            history: args.history.or(config.history),
+            vi_mode: args.vi || config.vi,
+            preview_layout: args.preview_window.or(config.preview_window),
+            marks_policy: MarksPolicy {
+                limit: args.marks_limit,
+                ..MarksPolicy::default()
+            },
+            layout: args.layout.or(config.layout).unwrap_or_else(|| {
+                if args.preview_builder.is_some() || preview_file.is_some() {
                     WindowLayout::Full {
                         height: WindowLayoutSize::Fraction(-0.3),
+                        orientation: WindowOrientation::default(),
                     }
                 } else {
                     WindowLayout::default()
@@ -129,19 +176,38 @@ async fn main() -> Result<(), Error> {
         },
     )?;
     if let Some(preview_builder) = args.preview_builder {
-        candidate_context.preview_set(preview_builder, sweep.waker());
+        let mut preview_options = args
+            .preview_options
+            .or(config.preview_options)
+            .unwrap_or_default();
+        if let Some(debounce) = args.preview_debounce {
+            preview_options.debounce = Duration::from_millis(debounce);
+        }
+        candidate_context.preview_set_with_options(preview_builder, preview_options, sweep.waker());
     }
+    if let Some(preview_file) = preview_file {
+        candidate_context.preview_file_set(preview_file, sweep.waker());
+    }
+
+    // theme files are reloaded on SIGUSR1
+    let mut theme_reload = signal(SignalKind::user_defined1())?;
 
     if args.rpc {
-        sweep
-            .serve_seed(
-                candidate_context.clone(),
-                Some(Arc::new(candidate_context.clone())),
-                input,
-                output,
-                |peer| Candidate::setup(peer, sweep.waker(), candidate_context),
-            )
-            .await?;
+        let serve = sweep.serve_seed_framed(
+            candidate_context.clone(),
+            Some(Arc::new(candidate_context.clone())),
+            input,
+            output,
+            args.rpc_framing,
+            |peer| Candidate::setup_sweep(peer, sweep.clone(), candidate_context),
+        );
+        tokio::pin!(serve);
+        loop {
+            tokio::select! {
+                result = &mut serve => break result?,
+                _ = theme_reload.recv() => sweep.theme_reload(),
+            }
+        }
     } else {
         let uid = args.window_uid.unwrap_or_else(|| "default".into());
         sweep.window_switch(uid, false).await?;
@@ -167,7 +233,17 @@ async fn main() -> Result<(), Error> {
                 Ok::<_, Error>(())
             });
         };
-        while let Some(event) = sweep.next_event().await {
+        loop {
+            let event = tokio::select! {
+                event = sweep.next_event() => event,
+                _ = theme_reload.recv() => {
+                    sweep.theme_reload();
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
             if let SweepEvent::Select { items, .. } = event {
                 if items.is_empty() && !args.no_match_use_input {
                     continue;
@@ -225,9 +301,9 @@ impl Write for Log {
 /// Sweep is a command line fuzzy finder
 #[derive(FromArgs)]
 pub struct Args {
-    /// prompt string
-    #[argh(option, short = 'p', default = "\"INPUT\".to_string()")]
-    pub prompt: String,
+    /// prompt string (default: INPUT)
+    #[argh(option, short = 'p')]
+    pub prompt: Option<String>,
 
     /// prompt icon
     #[argh(option, default = "sweep::PROMPT_DEFAULT_ICON.clone()")]
@@ -237,9 +313,10 @@ pub struct Args {
     #[argh(option, default = "String::new()")]
     pub query: String,
 
-    /// theme `(light|dark),accent=<color>,fg=<color>,bg=<color>`
-    #[argh(option, default = "Theme::from_env()")]
-    pub theme: Theme,
+    /// theme file `@path` (reloaded on SIGUSR1) or theme attributes
+    /// `(light|dark|nocolor),accent=<color>,fg=<color>,bg=<color>,border=<color>`
+    #[argh(option)]
+    pub theme: Option<Theme>,
 
     /// filed selectors (i.e `1,3..-1`)
     #[argh(option, long = "nth")]
@@ -253,14 +330,24 @@ pub struct Args {
     #[argh(switch, long = "keep-order")]
     pub keep_order: bool,
 
-    /// default scorer to rank items
-    #[argh(option, from_str_fn(scorer_arg), default = "\"fuzzy\".to_string()")]
-    pub scorer: String,
+    /// maximum number of items that can be marked
+    #[argh(option)]
+    pub marks_limit: Option<usize>,
+
+    /// default scorer to rank items (default: fuzzy)
+    #[argh(option, from_str_fn(scorer_arg))]
+    pub scorer: Option<String>,
 
     /// switch to remote-procedure-call mode
     #[argh(switch)]
     pub rpc: bool,
 
+    /// framing of RPC messages `newline`, `content-length` (LSP style) or
+    /// `auto` detected from the first message, `ready` is only sent after
+    /// the first client message in this case (default: newline)
+    #[argh(option, default = "RpcFraming::Newline")]
+    pub rpc_framing: RpcFraming,
+
     /// path to the TTY (default: /dev/tty)
     #[argh(option, long = "tty", default = "\"/dev/tty\".to_string()")]
     pub tty_path: String,
@@ -306,10 +393,50 @@ pub struct Args {
     #[argh(option, long = "preview")]
     pub preview_builder: Option<ProcessCommandBuilder>,
 
-    /// layout mode specified as `name(,attr=value)*`
+    /// preview file referenced by fields as `path(:line)`, text files are syntax
+    /// highlighted and centered on the line (i.e `0` or `0..2`)
+    #[argh(option)]
+    pub preview_file: Option<FieldSelector>,
+
+    /// preview placement `(right|bottom|left|top|hidden)(,size)` (i.e `top,40%`),
+    /// not used with `float` layout, which does not show the large preview
+    #[argh(option)]
+    pub preview_window: Option<PreviewLayout>,
+
+    /// preview process options `attr=value(,attr=value)*`, where attr is one of
+    /// `cache`, `debounce` (ms), `jobs` or `prefetch` (i.e `debounce=100,prefetch`)
+    #[argh(option)]
+    pub preview_options: Option<ProcessOptions>,
+
+    /// delay in milliseconds before the preview command is executed, so it is not
+    /// executed for every item while scrolling (not delayed by default)
+    #[argh(option)]
+    pub preview_debounce: Option<u64>,
+
+    /// layout mode specified as `name(,attr=value)*`, where name is `float` or
+    /// `full`, prompt is placed with `prompt=top|bottom` and list direction with
+    /// `list=down|up` (i.e `float,h=15,reverse` for prompt at the bottom)
     #[argh(option)]
     pub layout: Option<WindowLayout>,
 
+    /// bind chord to an action `chord:action` (i.e `ctrl+j:list.item.next`),
+    /// `normal:` prefix binds chord in the vi normal mode (i.e `normal:G:list.end`),
+    /// user tags reported with the `bind` event are prefixed with `@`
+    #[argh(option)]
+    pub bind: Vec<KeyBinding>,
+
+    /// file used to store history of submitted queries
+    #[argh(option)]
+    pub history: Option<PathBuf>,
+
+    /// enable vi-style modal key bindings (`esc` enters normal mode)
+    #[argh(switch)]
+    pub vi: bool,
+
+    /// path to the config file (default: $XDG_CONFIG_HOME/sweep/config.toml)
+    #[argh(option)]
+    pub config: Option<PathBuf>,
+
     /// show sweep version and quit
     #[argh(switch)]
     pub version: bool,
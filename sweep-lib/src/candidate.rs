@@ -1,8 +1,8 @@
 use crate::{
-    Haystack, HaystackBasicPreview, Positions, Process, ProcessCommandArg, ProcessCommandBuilder,
-    Theme,
+    FilePreview, Haystack, HaystackBasicPreview, HaystackViewOptions, Positions, PreviewEnv,
+    Process, ProcessCommandArg, ProcessCommandBuilder, ProcessOptions, Sweep, Theme, WindowId,
     common::{LockExt, VecDeserializeSeed, json_from_slice_seed},
-    rpc::{RpcParams, RpcPeer},
+    rpc::{RpcMethodDesc, RpcParams, RpcPeer},
     widgets::ProcessOutput,
 };
 use anyhow::Error;
@@ -15,23 +15,39 @@ use serde::{
 use serde_json::Value;
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt,
+    ops::Range,
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, Weak},
+    time::Duration,
 };
 use surf_n_term::{
-    CellWrite, Face, FaceDeserializer, Glyph, KeyChord, RGBA, Size, TerminalWaker,
+    Cell, CellWrite, Face, FaceDeserializer, Glyph, KeyChord, Position, RGBA, Size,
+    TerminalSurface, TerminalSurfaceExt, TerminalWaker,
     glyph::GlyphDeserializer,
     rasterize::SVG_COLORS,
     view::{
-        Align, ArcView, Axis, Container, Flex, IntoView, Justify, Margins, Text, View, ViewCache,
-        ViewDeserializer,
+        Align, ArcView, Axis, BoxConstraint, Container, Flex, Justify, Layout, Margins, Text, View,
+        ViewCache, ViewContext, ViewDeserializer, ViewLayout, ViewMutLayout,
     },
 };
-use tokio::io::{AsyncBufReadExt, AsyncRead};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead},
+    sync::mpsc,
+    time::Instant,
+};
+
+/// Maximum number of cached renderings of the lazy items
+const LAZY_CACHE_SIZE: usize = 1024;
+/// Maximum time to wait for the peer to render lazy items
+const LAZY_RENDER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before failed lazy item is requested again, doubled on each failure
+const LAZY_RENDER_BACKOFF: Duration = Duration::from_millis(100);
+/// Number of failed attempts after which registered text of lazy item is shown
+const LAZY_RENDER_ATTEMPTS: u32 = 5;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 struct CandidateInner {
     /// Searchable fields shown on left
     target: Vec<Field<'static>>,
@@ -49,6 +65,11 @@ struct CandidateInner {
     preview_haystack_position: usize,
     /// Hotkey associated with this item
     hotkey: Option<KeyChord>,
+    /// Stable key used to update or remove the item
+    key: Option<String>,
+    /// Identifier of the lazy item, its rendering is requested from the
+    /// peer with `items_render` when it is shown
+    lazy: Option<i64>,
     /// Extra fields extracted from candidate object during parsing, this
     /// can be useful when candidate has some additional data associated with it
     extra: HashMap<String, Value>,
@@ -70,6 +91,7 @@ impl Candidate {
     ///  - `right_face`: Default face for right fields
     ///  - `preview`: Fields to be shown on preview [Haystack::preview]
     ///  - `preview_flex`: Preview view flex value
+    ///  - `hotkey`: Key that selects this item in hotkey mode
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         target: Vec<Field<'static>>,
@@ -95,10 +117,47 @@ impl Candidate {
                 preview_flex: preview_flex.max(0.0),
                 preview_haystack_position,
                 hotkey,
+                key: None,
+                lazy: None,
             }),
         }
     }
 
+    /// Set stable key used to update or remove the item
+    pub fn with_key(mut self, key: Option<String>) -> Self {
+        Arc::make_mut(&mut self.inner).key = key;
+        self
+    }
+
+    /// Create lazy candidate, only the searchable text is known and the rest
+    /// is rendered by the peer on demand
+    pub fn lazy(text: String, id: i64) -> Self {
+        let mut candidate = Self::new(
+            vec![Field::from(text)],
+            None,
+            Vec::new(),
+            0,
+            None,
+            Vec::new(),
+            0.0,
+            None,
+        );
+        if let Some(inner) = Arc::get_mut(&mut candidate.inner) {
+            inner.lazy = Some(id);
+        }
+        candidate
+    }
+
+    /// Identifier of the lazy candidate
+    pub fn lazy_id(&self) -> Option<i64> {
+        self.inner.lazy
+    }
+
+    // rendering of the lazy candidate if it is available
+    fn lazy_rendered(&self, ctx: &CandidateContext) -> Option<Candidate> {
+        ctx.lazy_get(self)
+    }
+
     /// Extra data passed with candidate
     pub fn extra(&self) -> &HashMap<String, Value> {
         &self.inner.extra
@@ -197,65 +256,131 @@ impl Candidate {
     /// Initialize RpcPeer
     pub fn setup(peer: RpcPeer, waker: TerminalWaker, ctx: CandidateContext) {
         // register field
-        peer.register("field_register", {
-            let ctx = ctx.clone();
-            let view_cache: Arc<dyn ViewCache> = Arc::new(ctx.clone());
-            let waker = waker.clone();
-            move |mut params: RpcParams| {
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "field_register",
+                "Register field that can be used as the base for the other field",
+            )
+            .param("field", "Field")
+            .param_opt("id", "int")
+            .result("int"),
+            {
                 let ctx = ctx.clone();
+                let view_cache: Arc<dyn ViewCache> = Arc::new(ctx.clone());
                 let waker = waker.clone();
-                let view_cache = view_cache.clone();
-                async move {
-                    let field: Field = {
-                        let ctx_inner = ctx.inner.read().expect("lock poisoned");
-                        let field_seed = FieldDeserializer {
-                            colors: &ctx_inner.named_colors,
-                            view_cache: Some(view_cache),
+                move |mut params: RpcParams| {
+                    let ctx = ctx.clone();
+                    let waker = waker.clone();
+                    let view_cache = view_cache.clone();
+                    async move {
+                        let field: Field = {
+                            let ctx_inner = ctx.inner.read().expect("lock poisoned");
+                            let field_seed = FieldDeserializer {
+                                colors: &ctx_inner.named_colors,
+                                view_cache: Some(view_cache),
+                            };
+                            let field = params.take_seed(field_seed, 0, "field")?;
+                            if ctx_inner.no_color {
+                                field.strip_colors()
+                            } else {
+                                field
+                            }
                         };
-                        params.take_seed(field_seed, 0, "field")?
-                    };
-                    let ref_id_opt: Option<i64> = params.take_opt(1, "id")?;
-                    let ref_id = ctx.inner.with_mut(move |inner| {
-                        let ref_id = ref_id_opt.unwrap_or(inner.field_refs.len() as i64);
-                        inner.field_refs.insert(FieldRef(ref_id), field);
-                        ref_id
-                    });
-                    let _ = waker.wake();
-                    Ok(ref_id)
+                        let ref_id_opt: Option<i64> = params.take_opt(1, "id")?;
+                        let ref_id = ctx.inner.with_mut(move |inner| {
+                            let ref_id = ref_id_opt.unwrap_or(inner.field_refs.len() as i64);
+                            inner.field_refs.insert(FieldRef(ref_id), field);
+                            ref_id
+                        });
+                        let _ = waker.wake();
+                        Ok(ref_id)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // view register
-        peer.register("view_register", {
-            let ctx = ctx.clone();
-            let view_cache: Arc<dyn ViewCache> = Arc::new(ctx.clone());
-            let waker = waker.clone();
-            move |mut params: RpcParams| {
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "view_register",
+                "Register view that can be referenced by other views and fields",
+            )
+            .param("view", "View")
+            .param_opt("ref", "int")
+            .result("int"),
+            {
                 let ctx = ctx.clone();
-                let view_cache = view_cache.clone();
+                let view_cache: Arc<dyn ViewCache> = Arc::new(ctx.clone());
                 let waker = waker.clone();
-                async move {
-                    let view = {
-                        let ctx_inner = ctx.inner.read().expect("lock poisoned");
-                        let seed =
-                            ViewDeserializer::new(Some(&ctx_inner.named_colors), Some(view_cache));
-                        params.take_seed(&seed, 0, "view")?
-                    };
-                    let ref_id_opt: Option<i64> = params.take_opt(1, "ref")?;
-                    let ref_id = ctx.inner.with_mut(move |inner| {
-                        let ref_id = ref_id_opt.unwrap_or(inner.view_cache.len() as i64);
-                        inner.view_cache.insert(ref_id, view);
-                        ref_id
-                    });
-                    let _ = waker.wake();
-                    Ok(ref_id)
+                move |mut params: RpcParams| {
+                    let ctx = ctx.clone();
+                    let view_cache = view_cache.clone();
+                    let waker = waker.clone();
+                    async move {
+                        let view = {
+                            let ctx_inner = ctx.inner.read().expect("lock poisoned");
+                            let seed = ViewDeserializer::new(
+                                Some(&ctx_inner.named_colors),
+                                Some(view_cache),
+                            );
+                            params.take_seed(&seed, 0, "view")?
+                        };
+                        let ref_id_opt: Option<i64> = params.take_opt(1, "ref")?;
+                        let ref_id = ctx.inner.with_mut(move |inner| {
+                            let ref_id = ref_id_opt.unwrap_or(inner.view_cache.len() as i64);
+                            inner.view_cache.insert(ref_id, view);
+                            ref_id
+                        });
+                        let _ = waker.wake();
+                        Ok(ref_id)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         ctx.peer_set(peer);
     }
+
+    /// Initialize RpcPeer with [Candidate::setup] and methods that need access
+    /// to the [Sweep] instance (lazy items)
+    pub fn setup_sweep(peer: RpcPeer, sweep: Sweep<Candidate>, ctx: CandidateContext) {
+        let waker = sweep.waker();
+        Self::setup(peer.clone(), waker.clone(), ctx.clone());
+
+        // items extend lazy
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "items_extend_lazy",
+                "Extend list with items rendered on demand with `items_render`, searchable \
+                text of the rendered item must be equal to the registered string. \
+                Returns identifier of the first item, the rest are consecutive",
+            )
+            .param_opt("uid", "WindowId")
+            .param("items", "[String]")
+            .result("int"),
+            {
+                let ctx = ctx.clone();
+                move |mut params: RpcParams| {
+                    let ctx = ctx.clone();
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid: Option<WindowId> = params.take_opt(0, "uid")?;
+                        let items: Vec<String> = params.take(1, "items")?;
+                        let id = ctx.lazy_reserve(items.len());
+                        sweep.items_extend(
+                            uid,
+                            (id..)
+                                .zip(items)
+                                .map(|(id, item)| Candidate::lazy(item, id)),
+                        );
+                        Ok(id)
+                    }
+                }
+            },
+        );
+
+        ctx.lazy_render_start(peer, waker);
+    }
 }
 
 impl PartialEq for Candidate {
@@ -284,6 +409,8 @@ impl Serialize for Candidate {
             && inner.target[0].active
             && inner.right.is_empty()
             && inner.preview.is_empty()
+            && inner.key.is_none()
+            && inner.lazy.is_none()
         {
             self.to_string().serialize(serializer)
         } else {
@@ -309,6 +436,12 @@ impl Serialize for Candidate {
             if inner.preview_flex != 0.0 {
                 map.serialize_entry("preview_flex", &inner.preview_flex)?;
             }
+            if let Some(key) = &inner.key {
+                map.serialize_entry("key", key)?;
+            }
+            if let Some(lazy) = inner.lazy {
+                map.serialize_entry("lazy", &lazy)?;
+            }
             map.end()
         }
     }
@@ -368,7 +501,7 @@ impl Haystack for Candidate {
     type Context = CandidateContext;
     type View = Flex<'static>;
     type Preview = HaystackBasicPreview<FieldsView>;
-    type PreviewLarge = ProcessOutput;
+    type PreviewLarge = either::Either<ProcessOutput, FilePreview>;
 
     fn haystack_scope<S>(&self, _ctx: &Self::Context, scope: S)
     where
@@ -381,19 +514,45 @@ impl Haystack for Candidate {
         self.inner.hotkey.clone()
     }
 
+    fn key(&self) -> Option<&str> {
+        self.inner.key.as_deref()
+    }
+
     fn view(&self, ctx: &Self::Context, positions: Positions<&[u8]>, theme: &Theme) -> Self::View {
+        self.view_with_options(ctx, positions, theme, HaystackViewOptions::default())
+    }
+
+    fn view_with_options(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        options: HaystackViewOptions,
+    ) -> Self::View {
+        if let Some(rendered) = self.lazy_rendered(ctx) {
+            return rendered.view_with_options(ctx, positions, theme, options);
+        }
+
         // left side
         let mut positions_offset = 0;
-        let left = fields_view(
+        let mut highlight = None;
+        let left = fields_view_highlight(
             self.target(),
             positions.clone(),
             &mut positions_offset,
+            &mut highlight,
             ctx,
             theme.list_text,
             theme.list_highlight,
             theme.list_inactive,
             Axis::Horizontal,
-        );
+        )
+        .map_right(|text| TruncatedText {
+            text,
+            highlight,
+            options,
+            ellipsis: Cell::new_char(theme.list_inactive, '…'),
+        });
 
         // right side
         let right = fields_view(
@@ -432,6 +591,9 @@ impl Haystack for Candidate {
         positions: Positions<&[u8]>,
         theme: &Theme,
     ) -> Option<Self::Preview> {
+        if let Some(rendered) = self.lazy_rendered(ctx) {
+            return rendered.preview(ctx, positions, theme);
+        }
         if self.inner.preview.is_empty() {
             return None;
         }
@@ -453,12 +615,35 @@ impl Haystack for Candidate {
     }
 
     fn preview_large(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+    ) -> Option<Self::PreviewLarge> {
+        self.preview_large_with_env(ctx, positions, theme, &PreviewEnv::default())
+    }
+
+    fn preview_large_with_env(
         &self,
         ctx: &Self::Context,
         _positions: Positions<&[u8]>,
-        _theme: &Theme,
+        theme: &Theme,
+        env: &PreviewEnv,
     ) -> Option<Self::PreviewLarge> {
-        ctx.preview_get(self)
+        // unrendered lazy item has no preview yet, `None` is not cached
+        match self.lazy_rendered(ctx) {
+            Some(rendered) => ctx.preview_get(&rendered, theme, env),
+            None if self.lazy_id().is_some() => None,
+            None => ctx.preview_get(self, theme, env),
+        }
+    }
+
+    fn preview_prefetch(&self, ctx: &Self::Context, env: &PreviewEnv) {
+        match self.lazy_rendered(ctx) {
+            Some(rendered) => ctx.preview_prefetch(&rendered, env),
+            None if self.lazy_id().is_some() => {}
+            None => ctx.preview_prefetch(self, env),
+        }
     }
 }
 
@@ -483,6 +668,33 @@ pub fn fields_view(
     face_highlight: Face,
     face_inactive: Face,
     flex_axis: Axis,
+) -> FieldsView {
+    fields_view_highlight(
+        fields,
+        positions,
+        positions_offset,
+        &mut None,
+        candidate_context,
+        face_default,
+        face_highlight,
+        face_inactive,
+        flex_axis,
+    )
+}
+
+/// Same as [fields_view] but also records range of highlighted cells, which
+/// is only meaningful if resulting view is [Text]
+#[allow(clippy::too_many_arguments)]
+fn fields_view_highlight(
+    fields: &[Field<'_>],
+    positions: Positions<&[u8]>,
+    positions_offset: &mut usize,
+    highlight: &mut Option<Range<usize>>,
+    candidate_context: &CandidateContext,
+    face_default: Face,
+    face_highlight: Face,
+    face_inactive: Face,
+    flex_axis: Axis,
 ) -> FieldsView {
     let mut flex = Flex::new(flex_axis);
     let mut has_views = false;
@@ -498,6 +710,11 @@ pub fn fields_view(
             let face_default = face_default.overlay(&field_face);
             for c in field.text.chars() {
                 if positions.get(*positions_offset) {
+                    let index = text.len();
+                    match highlight {
+                        Some(highlight) => highlight.end = index + 1,
+                        None => *highlight = Some(index..index + 1),
+                    }
                     text.set_face(face_highlight);
                     text.put_char(c);
                 } else {
@@ -537,13 +754,166 @@ pub fn fields_view(
     }
 }
 
+/// Number of cells kept visible after the highlighted range of truncated text
+const TRUNCATE_HIGHLIGHT_CONTEXT: usize = 8;
+
+/// Single line text truncated to the available width
+///
+/// Left side of the text is elided with an ellipsis so the beginning of the
+/// highlighted range stays visible, text can also be scrolled horizontally.
+/// If wrapping is enabled text is shown as is.
+struct TruncatedText {
+    text: Text,
+    highlight: Option<Range<usize>>,
+    options: HaystackViewOptions,
+    ellipsis: Cell,
+}
+
+impl TruncatedText {
+    /// Index of the first visible cell when rendered with the given width
+    fn start(&self, ctx: &ViewContext, width: usize) -> usize {
+        let widths: Vec<usize> = self
+            .text
+            .cells()
+            .iter()
+            .map(|cell| {
+                let mut size = Size::empty();
+                let mut cursor = Position::origin();
+                cell.layout(ctx, usize::MAX, false, &mut size, &mut cursor);
+                cursor.col
+            })
+            .collect();
+        let start = match &self.highlight {
+            Some(highlight) => {
+                let end = (highlight.end + TRUNCATE_HIGHLIGHT_CONTEXT).min(widths.len());
+                if widths[..end].iter().sum::<usize>() <= width {
+                    0
+                } else {
+                    // fit as much as possible before the end, leaving space
+                    // for the ellipsis, but keep start of the highlight visible
+                    let mut start = end;
+                    let mut used = 0;
+                    while start > 0 && used + widths[start - 1] < width {
+                        start -= 1;
+                        used += widths[start];
+                    }
+                    start.min(highlight.start)
+                }
+            }
+            None => 0,
+        };
+        (start + self.options.offset).min(widths.len())
+    }
+}
+
+impl View for TruncatedText {
+    fn render(
+        &self,
+        ctx: &ViewContext,
+        surf: TerminalSurface<'_>,
+        layout: ViewLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        if self.options.wrap {
+            return self.text.render(ctx, surf, layout);
+        }
+        let start = self.start(ctx, layout.size().width);
+        let mut surf = layout.apply_to(surf);
+        let mut writer = surf.writer(ctx).with_wraps(false);
+        if start > 0 {
+            writer.put_cell(self.ellipsis.clone());
+        }
+        for cell in &self.text.cells()[start..] {
+            if !writer.put_cell(cell.clone()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn layout(
+        &self,
+        ctx: &ViewContext,
+        ct: BoxConstraint,
+        mut layout: ViewMutLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        if self.options.wrap {
+            return self.text.layout(ctx, ct, layout);
+        }
+        let start = self.start(ctx, ct.max().width);
+        let mut size = Size::empty();
+        let mut cursor = Position::origin();
+        if start > 0 {
+            self.ellipsis
+                .layout(ctx, usize::MAX, false, &mut size, &mut cursor);
+        }
+        for cell in &self.text.cells()[start..] {
+            cell.layout(ctx, usize::MAX, false, &mut size, &mut cursor);
+        }
+        self.options
+            .overflow
+            .update(size.width.saturating_sub(ct.max().width));
+        // truncated text takes all available width, so render would observe
+        // the same width and calculate the same start
+        let width = if start > 0 {
+            ct.max().width
+        } else {
+            size.width
+        };
+        *layout = Layout::new().with_size(ct.clamp(Size::new(size.height.min(1), width)));
+        Ok(())
+    }
+}
+
 struct CandidateContextInner {
     field_refs: HashMap<FieldRef, Field<'static>>,
     view_cache: HashMap<i64, ArcView<'static>>,
     named_colors: Arc<HashMap<String, RGBA>>,
+    // strip colors from the faces of the fields (`NO_COLOR`)
+    no_color: bool,
     peer: Option<RpcPeer>,
     preview_process: Option<Process>,
-    preview_output: Option<(Candidate, ProcessOutput)>,
+    preview_file: Option<(FieldSelector, TerminalWaker)>,
+    // next identifier of the lazy item
+    lazy_next: i64,
+    // rendered lazy items
+    lazy_cache: LazyCache,
+    // lazy items requested from the peer and not rendered yet,
+    // with the searchable text they were registered with
+    lazy_pending: HashMap<i64, String>,
+    // channel to request rendering of the lazy items
+    lazy_render: Option<mpsc::UnboundedSender<i64>>,
+}
+
+/// Rendered lazy items, oldest items are evicted first
+struct LazyCache {
+    capacity: usize,
+    entries: HashMap<i64, Candidate>,
+    order: VecDeque<i64>,
+}
+
+impl LazyCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, id: i64) -> Option<Candidate> {
+        self.entries.get(&id).cloned()
+    }
+
+    fn insert(&mut self, id: i64, candidate: Candidate) {
+        if self.entries.insert(id, candidate).is_none() {
+            self.order.push_back(id);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(id) = self.order.pop_front() {
+                self.entries.remove(&id);
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -557,9 +927,14 @@ impl CandidateContext {
             field_refs: HashMap::new(),
             view_cache: HashMap::new(),
             named_colors: Arc::new(SVG_COLORS.clone()),
+            no_color: false,
             peer: None,
             preview_process: None,
-            preview_output: None,
+            preview_file: None,
+            lazy_next: 0,
+            lazy_cache: LazyCache::new(LAZY_CACHE_SIZE),
+            lazy_pending: HashMap::new(),
+            lazy_render: None,
         };
         Self {
             inner: Arc::new(RwLock::new(inner)),
@@ -587,10 +962,24 @@ impl CandidateContext {
     }
 
     /// Update stored named colors from [Theme]
+    ///
+    /// For [Theme::no_color] accent color is not exposed, and colors of the explicit
+    /// faces of the fields are stripped, only attributes are kept.
     pub fn update_named_colors(&self, theme: &Theme) {
-        let named_colors = theme.named_colors.clone();
-        self.inner
-            .with_mut(|inner| inner.named_colors = named_colors)
+        let named_colors = if theme.no_color {
+            // names are still resolvable, but accent is replaced with foreground
+            let mut named_colors = HashMap::clone(&theme.named_colors);
+            for name in ["accent", "base"] {
+                named_colors.insert(name.to_owned(), theme.fg);
+            }
+            Arc::new(named_colors)
+        } else {
+            theme.named_colors.clone()
+        };
+        self.inner.with_mut(|inner| {
+            inner.named_colors = named_colors;
+            inner.no_color = theme.no_color;
+        })
     }
 
     /// Set rpc peer
@@ -600,28 +989,266 @@ impl CandidateContext {
 
     /// Set preview command builder
     pub fn preview_set(&self, builder: ProcessCommandBuilder, waker: TerminalWaker) {
+        self.preview_set_with_options(builder, ProcessOptions::default(), waker)
+    }
+
+    /// Set preview command builder with custom process options
+    pub fn preview_set_with_options(
+        &self,
+        builder: ProcessCommandBuilder,
+        options: ProcessOptions,
+        waker: TerminalWaker,
+    ) {
         self.inner.with_mut(|inner| {
             inner
                 .preview_process
-                .replace(Process::new(Some(builder), waker))
+                .replace(Process::new_with_options(Some(builder), options, waker))
         });
     }
 
-    pub(crate) fn preview_get(&self, candidate: &Candidate) -> Option<ProcessOutput> {
-        self.inner.with_mut(|inner| match &inner.preview_output {
-            Some((candidate_prev, output)) if candidate == candidate_prev => Some(output.clone()),
-            _ => {
-                let Some(proc) = &inner.preview_process else {
-                    return None;
-                };
-                proc.spawn(candidate.target());
-                let output = proc.into_view();
-                inner.preview_output = Some((candidate.clone(), output.clone()));
-                Some(output)
+    /// Set selector of the fields containing `path(:line)` of a file to preview,
+    /// used when preview command is not set
+    pub fn preview_file_set(&self, selector: FieldSelector, waker: TerminalWaker) {
+        self.inner
+            .with_mut(|inner| inner.preview_file.replace((selector, waker)));
+    }
+
+    pub(crate) fn preview_get(
+        &self,
+        candidate: &Candidate,
+        theme: &Theme,
+        env: &PreviewEnv,
+    ) -> Option<either::Either<ProcessOutput, FilePreview>> {
+        self.inner.with(|inner| {
+            if let Some(proc) = &inner.preview_process {
+                return Some(either::Either::Left(
+                    proc.spawn_with_env(candidate.target(), env),
+                ));
+            }
+            let (selector, waker) = inner.preview_file.as_ref()?;
+            let target = candidate.target();
+            // fields include trailing delimiter, so they are joined as is
+            let location = selector
+                .matches_iter(target.len())
+                .map(|index| target[index].text.as_ref())
+                .collect::<String>();
+            let location = location.trim();
+            if location.is_empty() {
+                return None;
+            }
+            match FilePreview::from_location(location, theme, waker.clone()) {
+                Ok(preview) => Some(either::Either::Right(preview)),
+                Err(error) => {
+                    tracing::debug!(?error, "[preview_get] file preview failed");
+                    None
+                }
+            }
+        })
+    }
+
+    pub(crate) fn preview_prefetch(&self, candidate: &Candidate, env: &PreviewEnv) {
+        self.inner.with(|inner| {
+            if let Some(proc) = &inner.preview_process
+                && proc.options().prefetch
+            {
+                proc.prefetch(candidate.target(), env);
+            }
+        })
+    }
+
+    /// Reserve identifiers for `count` lazy items, returns the first one
+    pub fn lazy_reserve(&self, count: usize) -> i64 {
+        self.inner.with_mut(|inner| {
+            let id = inner.lazy_next;
+            inner.lazy_next += count as i64;
+            id
+        })
+    }
+
+    /// Get rendering of the lazy item, if it is not rendered yet it is requested
+    /// from the peer and `None` is returned (also returned for not lazy items)
+    pub fn lazy_get(&self, candidate: &Candidate) -> Option<Candidate> {
+        let id = candidate.lazy_id()?;
+        let render = self.inner.with_mut(|inner| {
+            if let Some(candidate) = inner.lazy_cache.get(id) {
+                return Ok(candidate);
+            }
+            if inner.lazy_pending.contains_key(&id) {
+                return Err(None);
+            }
+            inner
+                .lazy_pending
+                .insert(id, candidate.haystack().collect());
+            Err(inner.lazy_render.clone())
+        });
+        match render {
+            Ok(candidate) => Some(candidate),
+            Err(render) => {
+                if let Some(render) = render {
+                    _ = render.send(id);
+                }
+                None
+            }
+        }
+    }
+
+    /// Store rendering of the lazy item
+    ///
+    /// Searchable text of the rendering must be equal to the text the item was
+    /// registered with, as it is what the ranker matched and highlighted positions
+    /// refer to. Otherwise rendering is rejected and the registered text is shown.
+    pub fn lazy_set(&self, id: i64, candidate: Candidate) {
+        let text = self
+            .inner
+            .with(|inner| inner.lazy_pending.get(&id).cloned());
+        match text {
+            Some(text) if !candidate.haystack().eq(text.chars()) => {
+                tracing::error!(
+                    ?id,
+                    expected = text,
+                    rendered = candidate.haystack().collect::<String>(),
+                    "[items_render] searchable text does not match"
+                );
+                self.lazy_reject(id);
+            }
+            _ => self.inner.with_mut(|inner| {
+                inner.lazy_pending.remove(&id);
+                inner.lazy_cache.insert(id, candidate);
+            }),
+        }
+    }
+
+    // store registered text as the rendering of the lazy item
+    fn lazy_reject(&self, id: i64) {
+        self.inner.with_mut(|inner| {
+            let Some(text) = inner.lazy_pending.remove(&id) else {
+                return;
+            };
+            let candidate = Candidate::new(
+                vec![Field::from(text)],
+                None,
+                Vec::new(),
+                0,
+                None,
+                Vec::new(),
+                0.0,
+                None,
+            );
+            inner.lazy_cache.insert(id, candidate);
+        })
+    }
+
+    // forget pending requests, so items are requested again on the next frame
+    fn lazy_retry(&self, ids: &[i64]) {
+        self.inner.with_mut(|inner| {
+            for id in ids {
+                inner.lazy_pending.remove(id);
             }
         })
     }
 
+    /// Start task that requests rendering of the lazy items from the peer,
+    /// requests issued while rendering a frame are sent as one `items_render` call.
+    ///
+    /// Items that failed to render (error, timeout or missing from the result) are
+    /// requested again on the next frame after a delay growing with each failure,
+    /// once the number of attempts is exhausted their registered text is shown.
+    pub fn lazy_render_start(&self, peer: RpcPeer, waker: TerminalWaker) {
+        let (send, mut recv) = mpsc::unbounded_channel();
+        self.inner.with_mut(|inner| inner.lazy_render.replace(send));
+        // weak reference, so the task stops once the context is dropped
+        let inner = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            // number of failed attempts per item
+            let mut attempts: HashMap<i64, u32> = HashMap::new();
+            // failed items stay pending until their deadline, so they are not requested
+            let mut retry: HashMap<i64, Instant> = HashMap::new();
+            loop {
+                let retry_deadline = retry.values().min().copied();
+                let retry_sleep = async move {
+                    match retry_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                let id = tokio::select! {
+                    id = recv.recv() => match id {
+                        Some(id) => id,
+                        None => return,
+                    },
+                    _ = retry_sleep => {
+                        let Some(ctx) = Weak::upgrade(&inner).map(|inner| CandidateContext { inner })
+                        else {
+                            return;
+                        };
+                        let now = Instant::now();
+                        let ids: Vec<i64> = retry
+                            .iter()
+                            .filter_map(|(id, deadline)| (*deadline <= now).then_some(*id))
+                            .collect();
+                        for id in &ids {
+                            retry.remove(id);
+                        }
+                        ctx.lazy_retry(&ids);
+                        let _ = waker.wake();
+                        continue;
+                    }
+                };
+                let mut ids = vec![id];
+                while let Ok(id) = recv.try_recv() {
+                    ids.push(id);
+                }
+                let result = peer
+                    .call_timeout(
+                        "items_render",
+                        serde_json::json!({ "ids": ids }),
+                        LAZY_RENDER_TIMEOUT,
+                    )
+                    .await;
+                let Some(ctx) = Weak::upgrade(&inner).map(|inner| CandidateContext { inner })
+                else {
+                    return;
+                };
+                let items = match result {
+                    Ok(Value::Array(items)) => items,
+                    Ok(result) => {
+                        tracing::error!(?result, "[items_render] list expected");
+                        Vec::new()
+                    }
+                    Err(error) => {
+                        tracing::error!(?error, "[items_render] failed");
+                        Vec::new()
+                    }
+                };
+                let rendered = items.len().min(ids.len());
+                for (id, item) in ids.iter().copied().zip(items) {
+                    attempts.remove(&id);
+                    match ctx.candidate_from_value(item) {
+                        Ok(candidate) => ctx.lazy_set(id, candidate),
+                        Err(error) => {
+                            tracing::error!(?error, ?id, "[items_render] invalid item");
+                            ctx.lazy_reject(id);
+                        }
+                    }
+                }
+                for id in ids[rendered..].iter().copied() {
+                    let attempt = attempts.entry(id).or_default();
+                    *attempt += 1;
+                    if *attempt >= LAZY_RENDER_ATTEMPTS {
+                        tracing::error!(?id, "[items_render] item is not rendered");
+                        attempts.remove(&id);
+                        ctx.lazy_reject(id);
+                    } else {
+                        let backoff = LAZY_RENDER_BACKOFF * 2u32.pow(*attempt - 1);
+                        tracing::warn!(?id, ?backoff, "[items_render] retry");
+                        retry.insert(id, Instant::now() + backoff);
+                    }
+                }
+                let _ = waker.wake();
+            }
+        });
+    }
+
     /// Parse [Candidate] from JSON bytes
     pub fn candidate_from_json(&self, slice: &[u8]) -> Result<Candidate, Error> {
         Ok(json_from_slice_seed(self, slice)?)
@@ -710,6 +1337,7 @@ impl<'de> de::Visitor<'de> for &CandidateContext {
         let mut preview = None;
         let mut preview_flex = 0.0;
         let mut hotkey = None;
+        let mut key = None;
 
         let view_cache: Arc<dyn ViewCache> = Arc::new(self.clone());
         let ctx = self.inner.read().map_err(de::Error::custom)?;
@@ -743,24 +1371,50 @@ impl<'de> de::Visitor<'de> for &CandidateContext {
                 "hotkey" => {
                     hotkey.replace(map.next_value()?);
                 }
+                "key" => {
+                    // numbers are accepted as keys as well, for example pids
+                    key = match map.next_value()? {
+                        Value::Null => None,
+                        Value::String(key) => Some(key),
+                        Value::Number(key) => Some(key.to_string()),
+                        _ => return Err(de::Error::custom("key must be a string or a number")),
+                    };
+                }
                 _ => {
                     extra.insert(name.into_owned(), map.next_value()?);
                 }
             }
         }
+        let mut target = target.ok_or_else(|| de::Error::missing_field("entry or fields"))?;
+        let mut right = right.unwrap_or_default();
+        let mut preview = preview.unwrap_or_default();
+        if ctx.no_color {
+            for fields in [&mut target, &mut right, &mut preview] {
+                *fields = std::mem::take(fields)
+                    .into_iter()
+                    .map(Field::strip_colors)
+                    .collect();
+            }
+            right_face = right_face.map(face_strip_colors);
+        }
         Ok(Candidate::new(
-            target.ok_or_else(|| de::Error::missing_field("entry or fields"))?,
+            target,
             (!extra.is_empty()).then_some(extra),
-            right.unwrap_or_default(),
+            right,
             right_offset,
             right_face,
-            preview.unwrap_or_default(),
+            preview,
             preview_flex,
             hotkey,
-        ))
+        )
+        .with_key(key))
     }
 }
 
+fn face_strip_colors(face: Face) -> Face {
+    Face::new(None, None, face.attrs)
+}
+
 /// Previously registered field that is used as base of the field
 ///
 /// Mainly used avoid constant sending of glyphs (icons)
@@ -898,6 +1552,14 @@ impl<'a> Field<'a> {
         }
     }
 
+    /// Remove colors from the face of the field, keeping only attributes
+    pub(crate) fn strip_colors(self) -> Self {
+        Self {
+            face: self.face.map(face_strip_colors),
+            ..self
+        }
+    }
+
     /// Borrow field
     pub fn borrow(&'a self) -> Field<'a> {
         Self {
@@ -1182,6 +1844,91 @@ mod tests {
     use serde_json::json;
     use surf_n_term::Path;
 
+    #[test]
+    fn test_truncated_text() -> Result<(), Error> {
+        use crate::HaystackViewOverflow;
+        use surf_n_term::view::ViewLayoutStore;
+
+        let ctx = CandidateContext::new();
+        let view_ctx = ViewContext::dummy();
+        let theme = Theme::light();
+        let candidate =
+            Candidate::from_string("some long prefix before the match and suffix", ' ', None);
+        let mut positions = Positions::new_owned(candidate.haystack().count());
+        (28..33).for_each(|index| positions.set(index));
+        let ct = BoxConstraint::new(Size::new(0, 20), Size::new(10, 20));
+        let truncated = |options| {
+            let mut highlight = None;
+            let view = fields_view_highlight(
+                candidate.target(),
+                positions.as_ref(),
+                &mut 0,
+                &mut highlight,
+                &ctx,
+                theme.list_text,
+                theme.list_highlight,
+                theme.list_inactive,
+                Axis::Horizontal,
+            );
+            TruncatedText {
+                text: view.right().expect("text view expected"),
+                highlight,
+                options,
+                ellipsis: Cell::new_char(theme.list_inactive, '…'),
+            }
+        };
+
+        // highlight with the context after it is visible
+        let view = truncated(HaystackViewOptions::default());
+        assert_eq!(view.highlight, Some(28..33));
+        let start = view.start(&view_ctx, 20);
+        assert_eq!(start, 22);
+        let mut store = ViewLayoutStore::new();
+        let layout = view.layout_new(&view_ctx, ct, &mut store)?;
+        assert_eq!(layout.size(), Size::new(1, 20));
+
+        // horizontal offset is added to the start
+        let view = truncated(HaystackViewOptions {
+            offset: 8,
+            ..Default::default()
+        });
+        assert_eq!(view.start(&view_ctx, 20), 30);
+
+        // cells cut off on the right side are reported
+        let overflow = HaystackViewOverflow::default();
+        let view = truncated(HaystackViewOptions {
+            offset: 0,
+            wrap: false,
+            overflow: overflow.clone(),
+        });
+        view.layout_new(
+            &view_ctx,
+            BoxConstraint::loose(Size::new(1, 10)),
+            &mut store,
+        )?;
+        assert_eq!(overflow.get(), 7);
+        view.layout_new(&view_ctx, ct, &mut store)?;
+        assert_eq!(overflow.get(), 7);
+        overflow.set(0);
+        let view = truncated(HaystackViewOptions {
+            offset: 8,
+            wrap: false,
+            overflow: overflow.clone(),
+        });
+        view.layout_new(&view_ctx, ct, &mut store)?;
+        assert_eq!(overflow.get(), 0);
+
+        // wrapped text is not truncated
+        let view = truncated(HaystackViewOptions {
+            wrap: true,
+            ..Default::default()
+        });
+        let mut store = ViewLayoutStore::new();
+        let layout = view.layout_new(&view_ctx, ct, &mut store)?;
+        assert_eq!(layout.size(), Size::new(3, 20));
+        Ok(())
+    }
+
     #[test]
     fn test_select() -> Result<(), Error> {
         let select = FieldSelect::from_str("..-1")?;
@@ -1273,7 +2020,8 @@ mod tests {
             }],
             1.0,
             None,
-        );
+        )
+        .with_key(Some("17".to_owned()));
         let value = json!({
             "fields": [
                 "one",
@@ -1285,7 +2033,8 @@ mod tests {
             "preview": [{"text": "preview"}],
             "preview_flex": 1.0,
             "offset": 7usize,
-            "extra": 127i32
+            "extra": 127i32,
+            "key": 17
         });
         let candidate_string = serde_json::to_string(&candidate)?;
         let value_string = serde_json::to_string(&value)?;
@@ -1326,6 +2075,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_no_color_fields() -> Result<(), Error> {
+        let ctx = CandidateContext::new();
+        let theme = Theme::no_color();
+        ctx.update_named_colors(&theme);
+        assert_eq!(
+            ctx.inner
+                .with(|inner| inner.named_colors.get("accent").copied()),
+            Some(theme.fg)
+        );
+
+        let value = json!({
+            "fields": [{"text": "one", "face": "fg=accent,bold"}],
+            "right": [{"text": "two", "face": "bg=#00ff00"}],
+            "right_face": "fg=red,underline",
+        });
+        let candidate = ctx.candidate_from_value(value)?;
+        assert_eq!(
+            serde_json::to_value(&candidate)?,
+            json!({
+                "target": [{"text": "one", "active": true, "face": "bold"}],
+                "right": [{"text": "two", "active": true, "face": ""}],
+                "right_face": "underline",
+            })
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_serde_field() -> Result<(), Error> {
         let mut field = Field {
@@ -1366,4 +2143,146 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_lazy_render() -> Result<(), Error> {
+        let (calls_send, mut calls) = mpsc::unbounded_channel();
+        let attempts = Arc::new(std::sync::Mutex::new(HashMap::<i64, usize>::new()));
+        let host = RpcPeer::new();
+        host.register("items_render", move |mut params: RpcParams| {
+            let calls_send = calls_send.clone();
+            let attempts = attempts.clone();
+            async move {
+                let ids: Vec<i64> = params.take(0, "ids")?;
+                _ = calls_send.send(ids.clone());
+                let mut items = Vec::new();
+                for id in ids {
+                    let attempt = attempts.with_mut(|attempts| {
+                        let attempt = attempts.entry(id).or_default();
+                        *attempt += 1;
+                        *attempt
+                    });
+                    match id {
+                        // first attempt fails
+                        2 if attempt == 1 => return Err(anyhow::anyhow!("failed").into()),
+                        // first attempt is missing from the result
+                        3 if attempt == 1 => continue,
+                        // never rendered
+                        6 => continue,
+                        // searchable text does not match
+                        4 => items.push(json!({"target": ["other"]})),
+                        _ => items.push(json!({
+                            "target": [format!("item {id}")],
+                            "preview": [{"text": "preview", "active": false}],
+                        })),
+                    }
+                }
+                Ok(items)
+            }
+        });
+        let peer = RpcPeer::new();
+        let (host_channel, peer_channel) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(host_channel);
+        tokio::spawn(host.serve(read, write));
+        let (read, write) = tokio::io::split(peer_channel);
+        tokio::spawn(peer.serve(read, write));
+
+        let (woken_send, mut woken) = mpsc::unbounded_channel();
+        let waker = TerminalWaker::new(move || {
+            _ = woken_send.send(());
+            Ok(())
+        });
+        let ctx = CandidateContext::new();
+        ctx.lazy_render_start(peer, waker);
+
+        assert_eq!(ctx.lazy_reserve(2), 0);
+        assert_eq!(ctx.lazy_reserve(1), 2);
+        let items = [
+            Candidate::lazy("item 0".to_owned(), 0),
+            Candidate::lazy("item 1".to_owned(), 1),
+        ];
+        assert_eq!(serde_json::to_value(&items[1])?["lazy"], json!(1));
+
+        // all requests of a frame are rendered with one call
+        assert!(items[0].lazy_rendered(&ctx).is_none());
+        assert!(items[1].lazy_rendered(&ctx).is_none());
+        assert!(items[0].lazy_rendered(&ctx).is_none());
+        woken.recv().await;
+        assert_eq!(calls.recv().await, Some(vec![0, 1]));
+        let rendered = items[1].lazy_rendered(&ctx).expect("rendered item");
+        assert_eq!(rendered.to_string(), "item 1");
+        assert!(!rendered.inner.preview.is_empty());
+        assert!(calls.try_recv().is_err());
+
+        // draw frames until the item is rendered
+        async fn render_wait(
+            item: &Candidate,
+            ctx: &CandidateContext,
+            woken: &mut mpsc::UnboundedReceiver<()>,
+        ) -> Candidate {
+            loop {
+                if let Some(rendered) = item.lazy_rendered(ctx) {
+                    return rendered;
+                }
+                woken.recv().await;
+            }
+        }
+
+        // failed and missing items are requested again after a delay
+        for id in [2, 3] {
+            let item = Candidate::lazy(format!("item {id}"), id);
+            let rendered = render_wait(&item, &ctx, &mut woken).await;
+            assert_eq!(rendered.to_string(), format!("item {id}"));
+            assert_eq!(calls.recv().await, Some(vec![id]));
+            assert_eq!(calls.recv().await, Some(vec![id]));
+            assert!(calls.try_recv().is_err());
+        }
+
+        // item that is never rendered does not delay other items, and its
+        // registered text is shown once attempts are exhausted
+        let item = Candidate::lazy("item 6".to_owned(), 6);
+        assert!(item.lazy_rendered(&ctx).is_none());
+        assert_eq!(calls.recv().await, Some(vec![6]));
+        let other = Candidate::lazy("item 7".to_owned(), 7);
+        let other_rendered = render_wait(&other, &ctx, &mut woken).await;
+        assert_eq!(other_rendered.to_string(), "item 7");
+        assert_eq!(calls.recv().await, Some(vec![7]));
+        let rendered = render_wait(&item, &ctx, &mut woken).await;
+        assert_eq!(rendered.to_string(), "item 6");
+        assert!(rendered.lazy_id().is_none());
+        for _ in 1..LAZY_RENDER_ATTEMPTS {
+            assert_eq!(calls.recv().await, Some(vec![6]));
+        }
+        assert!(calls.try_recv().is_err());
+
+        // rendering with different searchable text is rejected
+        let item = Candidate::lazy("item 4".to_owned(), 4);
+        assert!(item.lazy_rendered(&ctx).is_none());
+        woken.recv().await;
+        assert_eq!(calls.recv().await, Some(vec![4]));
+        let rendered = item.lazy_rendered(&ctx).expect("rendered item");
+        assert_eq!(rendered.to_string(), "item 4");
+        assert!(rendered.lazy_id().is_none());
+
+        // unrendered item has no large preview
+        let item = Candidate::lazy("item 5".to_owned(), 5);
+        let positions = Positions::new_owned(0);
+        assert!(
+            item.preview_large(&ctx, positions.as_ref(), &Theme::light())
+                .is_none()
+        );
+        woken.recv().await;
+        assert_eq!(calls.recv().await, Some(vec![5]));
+        assert!(item.lazy_rendered(&ctx).is_some());
+        assert!(calls.try_recv().is_err());
+
+        // oldest renderings are evicted
+        let mut cache = LazyCache::new(2);
+        for id in 0..3 {
+            cache.insert(id, items[0].clone());
+        }
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(2).is_some());
+        Ok(())
+    }
 }
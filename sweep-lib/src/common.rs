@@ -1,6 +1,7 @@
 use std::{
     future::Future,
     ops::Deref,
+    path::PathBuf,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -69,6 +70,16 @@ impl<V> LockExt for std::sync::RwLock<V> {
     }
 }
 
+/// Sweep configuration directory `$XDG_CONFIG_HOME/sweep` (`~/.config/sweep`
+/// if `XDG_CONFIG_HOME` is not set)
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("sweep"))
+}
+
 /// Aborts task associated with [JoinHandle] on drop
 #[derive(Debug)]
 pub struct AbortJoinHandle<T> {
@@ -209,6 +220,59 @@ pub(crate) fn byte_view_filter<T, P>(
     });
 }
 
+/// Copy of [GenericByteViewArray] with values at some indices replaced by `update`,
+/// buffers of the input array are reused
+pub(crate) fn byte_view_update<'a, T, U>(
+    array: &GenericByteViewArray<T>,
+    mut update: U,
+) -> GenericByteViewArray<T>
+where
+    T: ByteViewType,
+    T::Native: 'a,
+    U: FnMut(usize) -> Option<&'a T::Native>,
+{
+    let mut builder = GenericByteViewBuilder::new();
+    let buffers = array.data_buffers();
+    let buffer_offset = if buffers.is_empty() {
+        0
+    } else {
+        let buffer_offset = builder.append_block(buffers[0].clone());
+        for buffer in &buffers[1..] {
+            builder.append_block(buffer.clone());
+        }
+        buffer_offset
+    };
+
+    let nulls = array.nulls();
+    array.views().iter().enumerate().for_each(|(index, view)| {
+        if let Some(value) = update(index) {
+            builder.append_value(value);
+            return;
+        }
+        if nulls.map(|nulls| nulls.is_null(index)).unwrap_or(false) {
+            builder.append_null();
+            return;
+        }
+        let view = ByteView::from(*view);
+        if view.length <= 12 {
+            builder.append_value(unsafe {
+                // Safety: index comes from iterating views
+                array.value_unchecked(index)
+            });
+        } else {
+            unsafe {
+                // Safety: view/blocks are taken for source string view array
+                builder.append_view_unchecked(
+                    buffer_offset + view.buffer_index,
+                    view.offset,
+                    view.length,
+                );
+            }
+        }
+    });
+    builder.finish()
+}
+
 pub(crate) fn byte_view_concat<'a, T>(
     arrays: impl IntoIterator<Item = &'a GenericByteViewArray<T>>,
 ) -> GenericByteViewArray<T>
@@ -1,13 +1,58 @@
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use either::Either;
 use surf_n_term::{
-    CellWrite, KeyChord, Position, TerminalSurface,
+    CellWrite, Face, KeyChord, Position, Size, TerminalSurface,
     render::CellKind,
     view::{BoxConstraint, Layout, Text, View, ViewContext, ViewLayout, ViewMutLayout},
 };
 
-use crate::{Positions, Theme};
+use crate::{Positions, Scorer, Theme};
+
+/// Options of the haystack item view in a list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HaystackViewOptions {
+    /// Horizontal scroll offset in cells
+    pub offset: usize,
+    /// Wrap long item instead of truncating it
+    pub wrap: bool,
+    /// Cells cut off on the right side of the item are reported here
+    pub overflow: HaystackViewOverflow,
+}
+
+/// Maximum number of cells cut off on the right side of the list items
+///
+/// Updated during layout of the items, used to limit horizontal scroll of the list.
+#[derive(Debug, Clone, Default)]
+pub struct HaystackViewOverflow(Arc<AtomicUsize>);
+
+impl HaystackViewOverflow {
+    /// Record number of cells cut off on the right side of an item
+    pub fn update(&self, cells: usize) {
+        self.0.fetch_max(cells, Ordering::Relaxed);
+    }
+
+    /// Current maximum number of cells cut off
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Set number of cells cut off
+    pub fn set(&self, cells: usize) {
+        self.0.store(cells, Ordering::Relaxed);
+    }
+}
+
+impl PartialEq for HaystackViewOverflow {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for HaystackViewOverflow {}
 
 /// Haystack
 ///
@@ -31,9 +76,30 @@ pub trait Haystack: std::fmt::Debug + Clone + Send + Sync + 'static {
         None
     }
 
+    /// Stable key identifying the item across updates, used to replace or
+    /// remove items without tracking their haystack index
+    fn key(&self) -> Option<&str> {
+        None
+    }
+
     /// Return a view that renders haystack item in a list
     fn view(&self, ctx: &Self::Context, positions: Positions<&[u8]>, theme: &Theme) -> Self::View;
 
+    /// Return a view that renders haystack item in a list taking into account
+    /// horizontal scroll offset and wrapping of the list
+    ///
+    /// Default implementation ignores options and uses [Haystack::view]
+    fn view_with_options(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        options: HaystackViewOptions,
+    ) -> Self::View {
+        _ = options;
+        self.view(ctx, positions, theme)
+    }
+
     /// Side preview of the current item
     fn preview(
         &self,
@@ -54,6 +120,24 @@ pub trait Haystack: std::fmt::Debug + Clone + Send + Sync + 'static {
         None
     }
 
+    /// Large preview of the current item given the preview environment
+    ///
+    /// It is called again when the item, the query or the preview area changes,
+    /// defaults to [Haystack::preview_large] which ignores the environment
+    fn preview_large_with_env(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        _env: &PreviewEnv,
+    ) -> Option<Self::PreviewLarge> {
+        self.preview_large(ctx, positions, theme)
+    }
+
+    /// Prepare large preview in background, called for the items next to the
+    /// current one
+    fn preview_prefetch(&self, _ctx: &Self::Context, _env: &PreviewEnv) {}
+
     // Tag haystack with a value, useful for `quick_select`
     fn tagged<T>(self, tag: T, hotkey: Option<KeyChord>) -> HaystackTagged<Self, T>
     where
@@ -67,6 +151,43 @@ pub trait Haystack: std::fmt::Debug + Clone + Send + Sync + 'static {
     }
 }
 
+/// Environment of the large preview
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreviewEnv {
+    /// Position and size of the preview area in the terminal, None if the
+    /// preview has not been rendered yet
+    pub area: Option<(Position, Size)>,
+    /// Current query
+    pub query: String,
+    /// Index of the item in the haystack
+    pub index: usize,
+}
+
+impl PreviewEnv {
+    /// Environment variables passed to preview commands
+    ///
+    /// Geometry uses the same variables as `fzf`: `FZF_PREVIEW_TOP`,
+    /// `FZF_PREVIEW_LEFT`, `FZF_PREVIEW_LINES`, `FZF_PREVIEW_COLUMNS`, and
+    /// `LINES`/`COLUMNS` are set to the size of the preview area.
+    pub fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![
+            ("SWEEP_QUERY", self.query.clone()),
+            ("SWEEP_INDEX", self.index.to_string()),
+        ];
+        if let Some((position, size)) = self.area {
+            vars.extend([
+                ("FZF_PREVIEW_TOP", position.row.to_string()),
+                ("FZF_PREVIEW_LEFT", position.col.to_string()),
+                ("FZF_PREVIEW_LINES", size.height.to_string()),
+                ("FZF_PREVIEW_COLUMNS", size.width.to_string()),
+                ("LINES", size.height.to_string()),
+                ("COLUMNS", size.width.to_string()),
+            ]);
+        }
+        vars
+    }
+}
+
 /// View that is used for preview, and include addition methods to make it more functional
 pub trait HaystackPreview: View {
     /// Flex value when use as a child
@@ -90,6 +211,18 @@ pub trait HaystackPreview: View {
         _ = offset;
         Position::origin()
     }
+
+    /// Highlight matches of the scorer with the face, `None` clears the search
+    ///
+    /// Default implementation is not searchable
+    fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        _ = search;
+    }
+
+    /// Rows of the preview that contain matches of the current search
+    fn search_matches(&self) -> Vec<usize> {
+        Vec::new()
+    }
 }
 
 impl HaystackPreview for () {}
@@ -119,6 +252,20 @@ where
             Either::Right(right) => right.set_offset(offset),
         }
     }
+
+    fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        match self {
+            Either::Left(left) => left.search_set(search),
+            Either::Right(right) => right.search_set(search),
+        }
+    }
+
+    fn search_matches(&self) -> Vec<usize> {
+        match self {
+            Either::Left(left) => left.search_matches(),
+            Either::Right(right) => right.search_matches(),
+        }
+    }
 }
 
 impl<T: HaystackPreview + ?Sized> HaystackPreview for Arc<T> {
@@ -133,6 +280,14 @@ impl<T: HaystackPreview + ?Sized> HaystackPreview for Arc<T> {
     fn set_offset(&self, offset: Position) -> Position {
         (**self).set_offset(offset)
     }
+
+    fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        (**self).search_set(search)
+    }
+
+    fn search_matches(&self) -> Vec<usize> {
+        (**self).search_matches()
+    }
 }
 
 #[derive(Clone)]
@@ -172,10 +327,25 @@ where
         self.haystack.view(ctx, positions, theme)
     }
 
+    fn view_with_options(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        options: HaystackViewOptions,
+    ) -> Self::View {
+        self.haystack
+            .view_with_options(ctx, positions, theme, options)
+    }
+
     fn hotkey(&self) -> Option<KeyChord> {
         self.hotkey.clone().or(self.haystack.hotkey())
     }
 
+    fn key(&self) -> Option<&str> {
+        self.haystack.key()
+    }
+
     fn preview(
         &self,
         ctx: &Self::Context,
@@ -193,6 +363,21 @@ where
     ) -> Option<Self::PreviewLarge> {
         self.haystack.preview_large(ctx, positions, theme)
     }
+
+    fn preview_large_with_env(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        env: &PreviewEnv,
+    ) -> Option<Self::PreviewLarge> {
+        self.haystack
+            .preview_large_with_env(ctx, positions, theme, env)
+    }
+
+    fn preview_prefetch(&self, ctx: &Self::Context, env: &PreviewEnv) {
+        self.haystack.preview_prefetch(ctx, env)
+    }
 }
 
 impl<L, R> Haystack for Either<L, R>
@@ -222,10 +407,34 @@ where
         }
     }
 
+    fn view_with_options(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        options: HaystackViewOptions,
+    ) -> Self::View {
+        match self {
+            Either::Left(left) => left
+                .view_with_options(&ctx.0, positions, theme, options)
+                .left_view(),
+            Either::Right(right) => right
+                .view_with_options(&ctx.1, positions, theme, options)
+                .right_view(),
+        }
+    }
+
     fn hotkey(&self) -> Option<KeyChord> {
         None
     }
 
+    fn key(&self) -> Option<&str> {
+        match self {
+            Either::Left(left) => left.key(),
+            Either::Right(right) => right.key(),
+        }
+    }
+
     fn preview(
         &self,
         ctx: &Self::Context,
@@ -251,6 +460,31 @@ where
         };
         Some(preview)
     }
+
+    fn preview_large_with_env(
+        &self,
+        ctx: &Self::Context,
+        positions: Positions<&[u8]>,
+        theme: &Theme,
+        env: &PreviewEnv,
+    ) -> Option<Self::PreviewLarge> {
+        let preview = match self {
+            Either::Left(left) => left
+                .preview_large_with_env(&ctx.0, positions, theme, env)?
+                .left_view(),
+            Either::Right(right) => right
+                .preview_large_with_env(&ctx.1, positions, theme, env)?
+                .right_view(),
+        };
+        Some(preview)
+    }
+
+    fn preview_prefetch(&self, ctx: &Self::Context, env: &PreviewEnv) {
+        match self {
+            Either::Left(left) => left.preview_prefetch(&ctx.0, env),
+            Either::Right(right) => right.preview_prefetch(&ctx.1, env),
+        }
+    }
 }
 
 pub struct HaystackDefaultView {
@@ -0,0 +1,136 @@
+use anyhow::{Context, Error};
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Maximum number of entries kept in the history
+const QUERY_HISTORY_SIZE: usize = 1000;
+
+/// Persistent history of submitted queries
+///
+/// History is stored as a file with one query per line, new queries are appended
+/// to the end of the file. Duplicates are removed when history is loaded, and
+/// file is compacted if it grows too large.
+#[derive(Debug, Clone, Default)]
+pub struct QueryHistory {
+    path: Option<Arc<Path>>,
+    entries: Vec<String>,
+}
+
+impl QueryHistory {
+    /// Load history from the file, missing file is treated as an empty history
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path: PathBuf = path.into();
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(Error::from(error)
+                    .context(format!("failed to read history: {}", path.display())));
+            }
+        };
+        let lines: Vec<&str> = data.lines().filter(|line| !line.is_empty()).collect();
+
+        // keep only the latest occurrence of each query
+        let mut seen = HashSet::new();
+        let mut entries: Vec<String> = lines
+            .iter()
+            .rev()
+            .filter(|line| seen.insert(**line))
+            .take(QUERY_HISTORY_SIZE)
+            .map(|line| line.to_string())
+            .collect();
+        entries.reverse();
+
+        let history = Self {
+            path: Some(path.into()),
+            entries,
+        };
+        if lines.len() > 2 * QUERY_HISTORY_SIZE {
+            history.compact()?;
+        }
+        Ok(history)
+    }
+
+    /// Path of the history file
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// History entries, the oldest entry first
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Add query to the history and append it to the history file
+    pub fn push(&mut self, query: &str) -> Result<(), Error> {
+        // history file is line based
+        let query = query.replace(['\n', '\r'], " ");
+        if query.trim().is_empty() || self.entries.last() == Some(&query) {
+            return Ok(());
+        }
+        self.entries.retain(|entry| entry != &query);
+        self.entries.push(query.clone());
+        if self.entries.len() > QUERY_HISTORY_SIZE {
+            self.entries.remove(0);
+        }
+
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open history: {}", path.display()))?;
+        writeln!(file, "{}", query)?;
+        Ok(())
+    }
+
+    /// Rewrite history file with current entries
+    fn compact(&self) -> Result<(), Error> {
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        let mut data = String::new();
+        for entry in self.entries.iter() {
+            data.push_str(entry);
+            data.push('\n');
+        }
+        std::fs::write(path, data)
+            .with_context(|| format!("failed to write history: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_history() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("sweep-history-{}", std::process::id()));
+        std::fs::write(&path, "one\ntwo\none\n\nthree\n")?;
+
+        let mut history = QueryHistory::load(&path)?;
+        assert_eq!(history.entries(), ["two", "one", "three"]);
+
+        history.push("two")?;
+        history.push("four\nfive")?;
+        history.push("  ")?;
+        assert_eq!(history.entries(), ["one", "three", "two", "four five"]);
+
+        let history = QueryHistory::load(&path)?;
+        assert_eq!(history.entries(), ["one", "three", "two", "four five"]);
+
+        std::fs::remove_file(&path)?;
+        assert!(QueryHistory::load(&path)?.entries().is_empty());
+        Ok(())
+    }
+}
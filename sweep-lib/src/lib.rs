@@ -1,36 +1,51 @@
 #![deny(warnings)]
-#![allow(clippy::reversed_empty_ranges)]
+#![allow(
+    clippy::reversed_empty_ranges,
+    clippy::collapsible_if,
+    clippy::manual_is_multiple_of
+)]
 
 mod haystack;
 pub use haystack::{
     Haystack, HaystackBasicPreview, HaystackDefaultView, HaystackPreview, HaystackTagged,
+    HaystackViewOptions, HaystackViewOverflow, PreviewEnv,
 };
 
 mod scorer;
 pub use scorer::{
-    FuzzyScorer, KMPPattern, Positions, Score, ScoreArray, ScoreItem, ScoreIter, Scorer,
-    SubstrScorer,
+    FuzzyScorer, KMPPattern, Positions, RegexScorer, Score, ScoreArray, ScoreItem, ScoreIter,
+    Scorer, SubstrScorer,
 };
 
 mod rank;
 pub use rank::{
     ALL_SCORER_BUILDERS, RankedItems, Ranker, RankerThread, ScorerBuilder, fuzzy_scorer,
-    scorer_by_name, substr_scorer,
+    regex_scorer, scorer_by_name, substr_scorer,
 };
 
 mod candidate;
 pub use candidate::{Candidate, CandidateContext, Field, FieldRef, FieldSelector, fields_view};
 
+mod history;
+pub use history::QueryHistory;
+
 mod sweep;
 pub use crate::sweep::{
-    PROMPT_DEFAULT_ICON, Sweep, SweepEvent, SweepOptions, WindowId, WindowLayout, WindowLayoutSize,
-    sweep,
+    KeyBinding, MarksPolicy, PROMPT_DEFAULT_ICON, PreviewLayout, PreviewPosition, Sweep,
+    SweepEvent, SweepOptions, SweepSubscription, ViMode, WindowId, WindowLayout, WindowLayoutSize,
+    WindowOrientation, sweep,
 };
 
 pub mod rpc;
 
+mod preview;
+pub use preview::FilePreview;
+
 mod widgets;
-pub use widgets::{Process, ProcessCommandArg, ProcessCommandBuilder, Theme};
+pub use widgets::{
+    BorderStyle, Process, ProcessCommandArg, ProcessCommandBuilder, ProcessOptions, Theme,
+    ThemeFile,
+};
 
 pub mod common;
 
@@ -0,0 +1,457 @@
+//! Built-in file preview
+//!
+//! Renders content of a file referenced by an item into a scrollable preview,
+//! text files are syntax highlighted (with `highlight` feature) and binary files
+//! are shown as a hexdump. Previews are built in background and cached.
+use crate::{HaystackPreview, Scorer, Theme, common::LockExt, widgets::ProcessOutput};
+use anyhow::{Context, Error};
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::SystemTime,
+};
+use surf_n_term::{
+    CellWrite, Color, Face, Position, TerminalSurface, TerminalWaker,
+    view::{BoxConstraint, Layout, View, ViewContext, ViewLayout, ViewMutLayout},
+};
+#[cfg(feature = "highlight")]
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::SyntaxSet,
+};
+
+/// Maximum number of bytes read from the file
+const FILE_READ_LIMIT: u64 = 1 << 20;
+/// Number of lines that are syntax highlighted, the rest is shown as is
+#[cfg(feature = "highlight")]
+const FILE_HIGHLIGHT_LINES: usize = 5000;
+/// Number of bytes used to detect binary files
+const FILE_BINARY_PROBE: usize = 8192;
+/// Number of bytes shown in the hexdump of a binary file
+const FILE_HEXDUMP_BYTES: usize = 4096;
+/// Number of file previews kept in the cache
+const FILE_CACHE_SIZE: usize = 16;
+
+#[cfg(feature = "highlight")]
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+#[cfg(feature = "highlight")]
+static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Recently built file previews, the most recently used last
+static FILE_CACHE: LazyLock<Mutex<VecDeque<(FileKey, ProcessOutput)>>> =
+    LazyLock::new(Default::default);
+
+/// Key used to identify file preview in the cache
+#[derive(Clone, PartialEq)]
+struct FileKey {
+    path: PathBuf,
+    mtime: Option<SystemTime>,
+    line: Option<usize>,
+    // parts of the theme used to render the preview
+    theme_is_light: bool,
+    no_color: bool,
+    list_selected: Face,
+    list_inactive: Face,
+}
+
+/// Scrollable preview of a file
+///
+/// Text files are syntax highlighted, if line is specified preview is centered
+/// on this line and it is highlighted with `list_selected` face. Preview is
+/// built in background, terminal is woken up once it is ready. Previews are
+/// cached by path, modification time and line.
+#[derive(Clone)]
+pub struct FilePreview {
+    output: ProcessOutput,
+    /// Line to center on, applied on first layout when height is known
+    center: Arc<Mutex<Option<usize>>>,
+}
+
+impl FilePreview {
+    /// Create preview of the file at `path`, `line` is zero based
+    ///
+    /// Fails if file does not exist or is not a regular file, content is
+    /// loaded in background and the `waker` is called once it is ready.
+    pub fn new(
+        path: impl AsRef<Path>,
+        line: Option<usize>,
+        theme: &Theme,
+        waker: TerminalWaker,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("failed to stat file: {}", path.display()))?;
+        if !metadata.is_file() {
+            anyhow::bail!("not a file: {}", path.display());
+        }
+        let key = FileKey {
+            path: path.to_owned(),
+            mtime: metadata.modified().ok(),
+            line,
+            theme_is_light: theme.bg.luma() > theme.fg.luma(),
+            no_color: theme.no_color,
+            list_selected: theme.list_selected,
+            list_inactive: theme.list_inactive,
+        };
+        let output = FILE_CACHE.with_mut(|cache| {
+            if let Some(index) = cache.iter().position(|(k, _)| k == &key) {
+                let entry = cache.remove(index)?;
+                let output = entry.1.clone();
+                cache.push_back(entry);
+                return Some(output);
+            }
+            let output = ProcessOutput::new();
+            cache.push_back((key.clone(), output.clone()));
+            while cache.len() > FILE_CACHE_SIZE {
+                cache.pop_front();
+            }
+            let theme = theme.clone();
+            let output_load = output.clone();
+            rayon::spawn(move || {
+                file_load(output_load, &key, &theme);
+                let _ = waker.wake();
+            });
+            Some(output)
+        });
+        Ok(Self {
+            output: output.unwrap_or_default(),
+            center: Arc::new(Mutex::new(line)),
+        })
+    }
+
+    /// Create preview from location `path(:line(:column))`, line is one based
+    pub fn from_location(
+        location: &str,
+        theme: &Theme,
+        waker: TerminalWaker,
+    ) -> Result<Self, Error> {
+        let (path, line) = location_parse(location);
+        Self::new(path, line.map(|line| line.saturating_sub(1)), theme, waker)
+    }
+}
+
+/// Read file and render it into the output
+fn file_load(mut output: ProcessOutput, key: &FileKey, theme: &Theme) {
+    let mut data = Vec::new();
+    let result = std::fs::File::open(&key.path)
+        .and_then(|file| file.take(FILE_READ_LIMIT).read_to_end(&mut data));
+    if let Err(error) = result {
+        tracing::debug!(?error, path = ?key.path, "[FilePreview] failed to read file");
+        FILE_CACHE.with_mut(|cache| cache.retain(|(k, _)| k != key));
+        output.put_fmt(
+            &format_args!("failed to read file: {error}\n"),
+            Some(theme.list_inactive),
+        );
+        return;
+    }
+    match file_text(&data) {
+        Some(text) => file_highlight(&mut output, &key.path, text, key.line, theme),
+        None => file_hexdump(&mut output, &data, theme),
+    }
+}
+
+impl View for FilePreview {
+    fn render(
+        &self,
+        ctx: &ViewContext,
+        surf: TerminalSurface<'_>,
+        layout: ViewLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        self.output.render(ctx, surf, layout)
+    }
+
+    fn layout(
+        &self,
+        ctx: &ViewContext,
+        ct: BoxConstraint,
+        layout: ViewMutLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        if let Some(line) = self.center.with_mut(|center| center.take()) {
+            let offset = line.saturating_sub(ct.max().height / 2);
+            self.output.set_offset(Position::new(offset, 0));
+        }
+        self.output.layout(ctx, ct, layout)
+    }
+}
+
+impl HaystackPreview for FilePreview {
+    fn flex(&self) -> Option<f64> {
+        None
+    }
+
+    fn preview_layout(&self) -> Layout {
+        self.output.preview_layout()
+    }
+
+    fn set_offset(&self, offset: Position) -> Position {
+        // explicit scroll overrides pending centering
+        self.center.with_mut(|center| center.take());
+        self.output.set_offset(offset)
+    }
+
+    fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        self.output.search_set(search)
+    }
+
+    fn search_matches(&self) -> Vec<usize> {
+        self.output.search_matches()
+    }
+}
+
+/// Split location into a path and an optional line number
+///
+/// Numeric `:N` suffixes are only stripped if location itself is not an
+/// existing path, so files with colons in their names are still supported.
+/// Trailing `:` is ignored, as fields selected with `-d :` include it.
+fn location_parse(location: &str) -> (&str, Option<usize>) {
+    let mut path = location;
+    if !Path::new(path).exists() {
+        path = path.trim_end_matches(':');
+    }
+    let mut numbers = Vec::new();
+    while numbers.len() < 2 && !Path::new(path).exists() {
+        let Some((prefix, suffix)) = path.rsplit_once(':') else {
+            break;
+        };
+        let Ok(number) = suffix.parse::<usize>() else {
+            break;
+        };
+        numbers.push(number);
+        path = prefix;
+    }
+    (path, numbers.last().copied())
+}
+
+/// Return text if data is not binary
+fn file_text(data: &[u8]) -> Option<&str> {
+    if data[..data.len().min(FILE_BINARY_PROBE)].contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) => Some(text),
+        // data was truncated in the middle of a character
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&data[..error.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Render text with line numbers, the selected line is highlighted
+fn file_highlight(
+    output: &mut ProcessOutput,
+    path: &Path,
+    text: &str,
+    line: Option<usize>,
+    theme: &Theme,
+) {
+    let mut highlighter = FileHighlighter::new(path, text, theme);
+    let gutter_width = text.lines().count().max(1).to_string().len();
+    for (index, text_line) in text.split_inclusive('\n').enumerate() {
+        let selected = line == Some(index);
+        output.put_fmt(
+            &format_args!("{:>gutter_width$} ", index + 1),
+            Some(if selected {
+                theme.list_selected
+            } else {
+                theme.list_inactive
+            }),
+        );
+        if selected {
+            // highlighter still needs to observe the line to keep its state
+            highlighter.highlight(&mut ProcessOutput::new(), index, text_line);
+            output.put_fmt(
+                text_line.trim_end_matches(['\n', '\r']),
+                Some(theme.list_selected),
+            );
+        } else {
+            highlighter.highlight(output, index, text_line);
+        }
+        output.put_char('\n');
+    }
+}
+
+/// Syntax highlighter of the text lines
+#[cfg(feature = "highlight")]
+struct FileHighlighter {
+    highlighter: Option<HighlightLines<'static>>,
+}
+
+#[cfg(feature = "highlight")]
+impl FileHighlighter {
+    fn new(path: &Path, text: &str, theme: &Theme) -> Self {
+        if theme.no_color {
+            return Self { highlighter: None };
+        }
+        let syntax = SYNTAX_SET
+            .find_syntax_for_file(path)
+            .ok()
+            .flatten()
+            .or_else(|| SYNTAX_SET.find_syntax_by_first_line(text.lines().next().unwrap_or("")))
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+        let syntax_theme = if theme.bg.luma() > theme.fg.luma() {
+            &THEME_SET.themes["InspiredGitHub"]
+        } else {
+            &THEME_SET.themes["base16-ocean.dark"]
+        };
+        Self {
+            highlighter: Some(HighlightLines::new(syntax, syntax_theme)),
+        }
+    }
+
+    /// Write highlighted line (including line ending) into the output, without line ending
+    fn highlight(&mut self, output: &mut ProcessOutput, index: usize, line: &str) {
+        let spans = match &mut self.highlighter {
+            Some(highlighter) if index < FILE_HIGHLIGHT_LINES => highlighter
+                .highlight_line(line, &SYNTAX_SET)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        if spans.is_empty() {
+            output.put_fmt(line.trim_end_matches(['\n', '\r']), Some(Face::default()));
+            return;
+        }
+        for (style, span) in spans {
+            let color = style.foreground;
+            let mut attrs = surf_n_term::FaceAttrs::EMPTY;
+            if style.font_style.contains(FontStyle::BOLD) {
+                attrs |= surf_n_term::FaceAttrs::BOLD;
+            }
+            if style.font_style.contains(FontStyle::ITALIC) {
+                attrs |= surf_n_term::FaceAttrs::ITALIC;
+            }
+            let fg = surf_n_term::RGBA::new(color.r, color.g, color.b, color.a);
+            output.put_fmt(
+                span.trim_end_matches(['\n', '\r']),
+                Some(Face::new(Some(fg), None, attrs)),
+            );
+        }
+    }
+}
+
+/// Plain text lines, syntax highlighting is disabled
+#[cfg(not(feature = "highlight"))]
+struct FileHighlighter;
+
+#[cfg(not(feature = "highlight"))]
+impl FileHighlighter {
+    fn new(_path: &Path, _text: &str, _theme: &Theme) -> Self {
+        Self
+    }
+
+    fn highlight(&mut self, output: &mut ProcessOutput, _index: usize, line: &str) {
+        output.put_fmt(line.trim_end_matches(['\n', '\r']), Some(Face::default()));
+    }
+}
+
+fn file_hexdump(output: &mut ProcessOutput, data: &[u8], theme: &Theme) {
+    output.put_fmt(
+        &format_args!("binary file ({} bytes read)\n", data.len()),
+        Some(theme.list_inactive),
+    );
+    let mut line = String::new();
+    for (index, chunk) in data[..data.len().min(FILE_HEXDUMP_BYTES)]
+        .chunks(16)
+        .enumerate()
+    {
+        line.clear();
+        for byte in chunk {
+            let _ = write!(&mut line, "{byte:02x} ");
+        }
+        output.put_fmt(
+            &format_args!("{:08x}  ", index * 16),
+            Some(theme.list_inactive),
+        );
+        output.put_fmt(&format_args!("{line:<48} "), Some(Face::default()));
+        line.clear();
+        line.extend(chunk.iter().map(|byte| {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            }
+        }));
+        output.put_fmt(&format_args!("|{line}|\n"), Some(theme.list_inactive));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_parse() {
+        assert_eq!(location_parse("/no/such/file"), ("/no/such/file", None));
+        assert_eq!(
+            location_parse("/no/such/file:12"),
+            ("/no/such/file", Some(12))
+        );
+        assert_eq!(
+            location_parse("/no/such/file:12:5"),
+            ("/no/such/file", Some(12))
+        );
+        assert_eq!(
+            location_parse("/no/such/file:12:"),
+            ("/no/such/file", Some(12))
+        );
+        assert_eq!(
+            location_parse("/no/such/file:name"),
+            ("/no/such/file:name", None)
+        );
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        assert_eq!(location_parse(path), (path, None));
+    }
+
+    #[test]
+    fn test_file_preview_cache() -> Result<(), Error> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let waker = TerminalWaker::new({
+            let loads = loads.clone();
+            move || {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let theme = Theme::light();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let preview = FilePreview::new(path, Some(1), &theme, waker.clone())?;
+        for _ in 0..100 {
+            if loads.load(Ordering::SeqCst) > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        preview.search_set(Some((crate::substr_scorer()("[package]"), Face::default())));
+        assert_eq!(preview.search_matches(), vec![0]);
+
+        // cached preview is not loaded again
+        let preview = FilePreview::new(path, Some(1), &theme, waker.clone())?;
+        preview.search_set(Some((crate::substr_scorer()("[package]"), Face::default())));
+        assert_eq!(preview.search_matches(), vec![0]);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        assert!(FilePreview::new("/no/such/file", None, &theme, waker.clone()).is_err());
+        assert!(FilePreview::new(env!("CARGO_MANIFEST_DIR"), None, &theme, waker).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_text() {
+        assert_eq!(file_text(b"fn main() {}\n"), Some("fn main() {}\n"));
+        assert_eq!(file_text(b"text\0binary"), None);
+        assert_eq!(file_text(b"\xff\xfe\xfd"), None);
+        // truncated multi-byte character at the end
+        assert_eq!(
+            file_text("abc\u{00e9}".as_bytes().split_last().unwrap().1),
+            Some("abc")
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    FuzzyScorer, Haystack, Scorer, SubstrScorer,
-    common::{LockExt, byte_view_concat},
+    FuzzyScorer, Haystack, RegexScorer, Scorer, SubstrScorer,
+    common::{LockExt, byte_view_concat, byte_view_update},
     scorer::{ScoreArray, ScoreItem},
 };
 use arrow_array::{Array, StringViewArray, builder::StringViewBuilder};
@@ -42,6 +42,12 @@ pub fn substr_scorer() -> ScorerBuilder {
     })
 }
 
+/// Create case-insensitive regular expression scorer builder, it is not a part
+/// of [ALL_SCORER_BUILDERS] and only used to search within the large preview
+pub fn regex_scorer() -> ScorerBuilder {
+    Arc::new(|needle: &str| Arc::new(RegexScorer::new(needle)))
+}
+
 /// Find scorer by name, returns selected scorer builder
 pub fn scorer_by_name(
     scorers: &mut VecDeque<ScorerBuilder>,
@@ -108,14 +114,38 @@ impl Ranker {
     ) where
         H: Haystack,
     {
-        let mut builder = StringViewBuilder::new();
-        let mut string_buf = String::new();
-        for haystack in haystack {
-            string_buf.clear();
-            haystack.haystack_scope(ctx, |ch| string_buf.push(ch));
-            builder.append_value(&string_buf);
-        }
-        self.send(RankerCmd::HaystackAppend(builder.finish()));
+        self.send(RankerCmd::HaystackAppend(haystack_array(ctx, haystack)));
+    }
+
+    /// Replace whole haystack with new entries
+    ///
+    /// Unlike clear followed by extend, it is never ranked in between.
+    pub fn haystack_set<'a, H>(&self, ctx: &H::Context, haystack: impl IntoIterator<Item = &'a H>)
+    where
+        H: Haystack,
+    {
+        self.send(RankerCmd::HaystackSet(haystack_array(ctx, haystack)));
+    }
+
+    /// Replace entries of the haystack at the specified indices
+    ///
+    /// Only updated entries are sent to the ranker, the rest of the haystack is reused.
+    pub fn haystack_update<'a, H>(
+        &self,
+        ctx: &H::Context,
+        haystack: impl IntoIterator<Item = (usize, &'a H)>,
+    ) where
+        H: Haystack,
+    {
+        let updates = haystack
+            .into_iter()
+            .map(|(index, haystack)| {
+                let mut string = String::new();
+                haystack.haystack_scope(ctx, |ch| string.push(ch));
+                (index, string)
+            })
+            .collect();
+        self.send(RankerCmd::HaystackUpdate(updates));
     }
 
     /// Clear haystack
@@ -155,9 +185,29 @@ impl Ranker {
     }
 }
 
+// collect searchable strings of the haystack items
+fn haystack_array<'a, H>(
+    ctx: &H::Context,
+    haystack: impl IntoIterator<Item = &'a H>,
+) -> StringViewArray
+where
+    H: Haystack,
+{
+    let mut builder = StringViewBuilder::new();
+    let mut string_buf = String::new();
+    for haystack in haystack {
+        string_buf.clear();
+        haystack.haystack_scope(ctx, |ch| string_buf.push(ch));
+        builder.append_value(&string_buf);
+    }
+    builder.finish()
+}
+
 enum RankerCmd {
     HaystackClear,
     HaystackAppend(StringViewArray),
+    HaystackSet(StringViewArray),
+    HaystackUpdate(HashMap<usize, String>),
     Needle(String),
     Scorer(ScorerBuilder),
     KeepOrder(Option<bool>),
@@ -247,6 +297,20 @@ impl RankerState {
                 self.haystack_appends.clear();
                 self.haystack = byte_view_concat([]);
             }
+            HaystackSet(haystack) => {
+                self.action = All;
+                self.haystack_gen = self.haystack_gen.wrapping_add(1);
+                self.haystack_appends.clear();
+                self.haystack = haystack;
+            }
+            HaystackUpdate(updates) => {
+                // indices are preserved, so haystack generation stays the same
+                self.action = All;
+                self.haystack_collect();
+                self.haystack = byte_view_update(&self.haystack, |index| {
+                    updates.get(&index).map(String::as_str)
+                });
+            }
             KeepOrder(toggle) => {
                 self.action = All;
                 match toggle {
@@ -264,16 +328,20 @@ impl RankerState {
         }
     }
 
-    // do actual ranking
-    fn rank(&mut self) -> Arc<RankedItems> {
-        use RankAction::*;
-
-        // collect haystack
+    // merge pending appends into haystack
+    fn haystack_collect(&mut self) {
         if !self.haystack_appends.is_empty() {
             self.haystack =
                 byte_view_concat(iter::once(&self.haystack).chain(&self.haystack_appends));
             self.haystack_appends.clear();
         }
+    }
+
+    // do actual ranking
+    fn rank(&mut self) -> Arc<RankedItems> {
+        use RankAction::*;
+
+        self.haystack_collect();
 
         // rank
         let rank_instant = Instant::now();
@@ -522,6 +590,23 @@ mod tests {
         assert_eq!(result.len(), 3);
         assert_eq!(result.get(0).map(|r| r.haystack_index), Some(0));
 
+        ranker.haystack_set(&(), &["two", "poe"]);
+        let result = recv.recv_timeout(timeout)?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.get(0).map(|r| r.haystack_index), Some(1));
+
+        ranker.haystack_extend(&(), &["one"]);
+        let result = recv.recv_timeout(timeout)?;
+        assert_eq!(result.len(), 2);
+        let haystack_gen = result.generation().0;
+
+        // only updated entries are replaced, indices are kept
+        ranker.haystack_update(&(), [(0, &"ponies"), (2, &"xyz long string without match")]);
+        let result = recv.recv_timeout(timeout)?;
+        assert_eq!(result.generation().0, haystack_gen);
+        let indices: Vec<_> = result.iter().map(|r| r.haystack_index).collect();
+        assert_eq!(indices, [0, 1]);
+
         ranker.haystack_clear();
         let result = recv.recv_timeout(timeout)?;
         assert_eq!(result.len(), 0);
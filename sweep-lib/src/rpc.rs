@@ -9,22 +9,36 @@ use serde::{
     de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, Visitor},
     ser::SerializeMap,
 };
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     future::Future,
     io::Write,
     marker::PhantomData,
+    str::FromStr,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use tokio::{
-    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+    io::{
+        AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter,
+    },
     sync::{mpsc, oneshot},
+    task::AbortHandle,
 };
 use tracing_futures::Instrument;
 
+/// Notification used to cancel request by its id (same as in LSP)
+pub const RPC_CANCEL_METHOD: &str = "$/cancelRequest";
+
+/// Method returning description of all registered methods, see [RpcPeer::discover]
+pub const RPC_DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Maximum accepted value of `Content-Length` header (64MiB)
+pub const RPC_CONTENT_LENGTH_MAX: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RpcRequest {
     pub method: String,
@@ -42,6 +56,8 @@ pub struct RpcResponse {
 pub enum RpcMessage {
     Request(RpcRequest),
     Response(RpcResponse),
+    /// [Batch](https://www.jsonrpc.org/specification#batch) of requests or responses
+    Batch(Vec<RpcMessage>),
 }
 
 impl From<RpcRequest> for RpcMessage {
@@ -253,11 +269,13 @@ pub enum RpcErrorKind {
     MethodNotFound,
     InvalidParams,
     InternalError,
+    RequestCancelled,
     PeerDisconnected,
     SerdeError,
     IOError,
     ServeError,
     SurfNTerm,
+    Timeout,
     Other { code: i32, message: String },
 }
 
@@ -270,11 +288,13 @@ impl RpcErrorKind {
             -32601 => MethodNotFound,
             -32602 => InvalidParams,
             -32603 => InternalError,
+            -32800 => RequestCancelled,
             -32000 => ServeError,
             1000 => PeerDisconnected,
             1001 => SerdeError,
             1002 => IOError,
             1003 => SurfNTerm,
+            1004 => Timeout,
             _ => Other { code, message },
         }
     }
@@ -287,11 +307,13 @@ impl RpcErrorKind {
             MethodNotFound => -32601,
             InvalidParams => -32602,
             InternalError => -32603,
+            RequestCancelled => -32800,
             ServeError => -32000,
             PeerDisconnected => 1000,
             SerdeError => 1001,
             IOError => 1002,
             SurfNTerm => 1003,
+            Timeout => 1004,
             Other { code, .. } => *code,
         }
     }
@@ -304,11 +326,13 @@ impl RpcErrorKind {
             MethodNotFound => "Method not found",
             InvalidParams => "Invalid params",
             InternalError => "Internal error",
+            RequestCancelled => "Request cancelled",
             PeerDisconnected => "Peer disconnected",
             SerdeError => "Faield to (de)serialize",
             IOError => "Imput/Output error",
             ServeError => "Serve error",
             SurfNTerm => "surf-n-term error",
+            Timeout => "Request timed out",
             Other { message, .. } => message.as_ref(),
         }
     }
@@ -643,6 +667,7 @@ impl Serialize for RpcMessage {
         match self {
             RpcMessage::Request(request) => request.serialize(serializer),
             RpcMessage::Response(response) => response.serialize(serializer),
+            RpcMessage::Batch(messages) => messages.serialize(serializer),
         }
     }
 }
@@ -658,7 +683,23 @@ impl<'de> Deserialize<'de> for RpcMessage {
             type Value = RpcMessage;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("dictionary as per https://www.jsonrpc.org/specification")
+                formatter.write_str(
+                    "dictionary or list of dictionaries as per https://www.jsonrpc.org/specification",
+                )
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let mut messages = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(message) = seq.next_element()? {
+                    if let RpcMessage::Batch(_) = message {
+                        return Err(de::Error::custom("nested batches are not allowed"));
+                    }
+                    messages.push(message);
+                }
+                Ok(RpcMessage::Batch(messages))
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
@@ -717,17 +758,208 @@ impl<'de> Deserialize<'de> for RpcMessage {
             }
         }
 
-        deserializer.deserialize_map(RpcMessageVisitor)
+        deserializer.deserialize_any(RpcMessageVisitor)
+    }
+}
+
+/// Message read by [rpc_reader], elements of the batch that are not valid
+/// messages are kept as errors so they can be replied to individually
+enum RpcIncoming {
+    Message(RpcMessage),
+    Batch(Vec<Result<RpcMessage, RpcError>>),
+}
+
+/// Framing of messages on the wire
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RpcFraming {
+    /// Message is encoded as JSON per line
+    #[default]
+    Newline,
+    /// Message is prefixed with `Content-Length` header (LSP style)
+    ContentLength,
+    /// Framing is detected from the first bytes received from the other peer,
+    /// nothing is sent until then, so messages sent by this peer first (for example
+    /// `ready` event) are held back until the other peer sends something
+    Auto,
+}
+
+impl FromStr for RpcFraming {
+    type Err = anyhow::Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        use RpcFraming::*;
+        match string.trim() {
+            "newline" | "line" => Ok(Newline),
+            "content-length" | "lsp" => Ok(ContentLength),
+            "auto" => Ok(Auto),
+            _ => Err(anyhow::anyhow!("invalid rpc framing: {}", string)),
+        }
+    }
+}
+
+impl fmt::Display for RpcFraming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RpcFraming::*;
+        let name = match self {
+            Newline => "newline",
+            ContentLength => "content-length",
+            Auto => "auto",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Description of the RPC method reported by [RPC_DISCOVER_METHOD]
+///
+/// Types are specified with the same notation as used in the documentation,
+/// `String`, `int`, `float`, `bool`, `[T]` for lists, `T?` for nullable
+/// values, `A | B` for alternatives, and any other name for named types
+/// (i.e `Item` or `WindowId`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RpcMethodDesc {
+    pub name: String,
+    pub doc: String,
+    pub params: Vec<RpcParamDesc>,
+    pub result: Option<String>,
+}
+
+/// Description of the RPC method parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcParamDesc {
+    pub name: String,
+    pub ty: String,
+    pub required: bool,
+}
+
+impl RpcMethodDesc {
+    pub fn new(name: impl Into<String>, doc: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            doc: doc.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add required parameter, positional index of the parameter is the order
+    /// in which it was added
+    pub fn param(mut self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.params.push(RpcParamDesc {
+            name: name.into(),
+            ty: ty.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Add optional parameter
+    pub fn param_opt(mut self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.params.push(RpcParamDesc {
+            name: name.into(),
+            ty: ty.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Set type of the result, methods without result return `null`
+    pub fn result(mut self, ty: impl Into<String>) -> Self {
+        self.result = Some(ty.into());
+        self
+    }
+
+    /// OpenRPC-like method description
+    fn to_value(&self) -> Value {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|param| {
+                json!({
+                    "name": param.name,
+                    "required": param.required,
+                    "schema": rpc_type_schema(&param.ty),
+                })
+            })
+            .collect();
+        let result = match &self.result {
+            Some(ty) => json!({"name": "result", "schema": rpc_type_schema(ty)}),
+            None => json!({"name": "result", "schema": {"type": "null"}}),
+        };
+        json!({
+            "name": self.name,
+            "description": self.doc,
+            "params": params,
+            "result": result,
+        })
+    }
+}
+
+/// Convert type in documentation notation into JSON schema
+fn rpc_type_schema(ty: &str) -> Value {
+    let ty = ty.trim();
+    // split alternatives that are not nested inside of the list
+    let mut depth = 0;
+    let alternatives: Vec<_> = ty
+        .split(|c| {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            c == '|' && depth == 0
+        })
+        .collect();
+    if alternatives.len() > 1 {
+        let schemas: Vec<_> = alternatives.into_iter().map(rpc_type_schema).collect();
+        return json!({ "oneOf": schemas });
+    }
+    if let Some(ty) = ty.strip_suffix('?') {
+        return json!({ "oneOf": [rpc_type_schema(ty), {"type": "null"}] });
+    }
+    if let Some(item) = ty.strip_prefix('[').and_then(|ty| ty.strip_suffix(']')) {
+        return json!({ "type": "array", "items": rpc_type_schema(item) });
+    }
+    match ty {
+        "String" => json!({ "type": "string" }),
+        "int" => json!({ "type": "integer" }),
+        "float" => json!({ "type": "number" }),
+        "bool" => json!({ "type": "boolean" }),
+        "Any" => json!({}),
+        name => json!({ "title": name }),
     }
 }
 
 pub type RpcHandler =
     Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Value, RpcError>> + Sync + Send>;
 
+/// Removes pending request once the call future is dropped (completed, timed out
+/// or cancelled by the caller), and if the response was not received yet notifies
+/// the other peer with [RPC_CANCEL_METHOD]
+struct RpcCallGuard {
+    peer: RpcPeer,
+    id: RpcId,
+}
+
+impl Drop for RpcCallGuard {
+    fn drop(&mut self) {
+        let pending = self
+            .peer
+            .inner
+            .with_mut(|inner| inner.requests.remove(&self.id).is_some());
+        if pending {
+            let _ = self
+                .peer
+                .notify(RPC_CANCEL_METHOD, json!({ "id": self.id }));
+        }
+    }
+}
+
 pub struct RpcPeerInner {
     handlers: HashMap<String, RpcHandler>,
+    methods: BTreeMap<String, RpcMethodDesc>,
     requests_next_id: i64,
     requests: HashMap<RpcId, oneshot::Sender<Result<Value, RpcError>>>,
+    /// Handlers of the requests from the other peer that are still running
+    running: HashMap<RpcId, AbortHandle>,
     write_sender: mpsc::UnboundedSender<RpcMessage>,
     write_receiver: Option<mpsc::UnboundedReceiver<RpcMessage>>,
 }
@@ -748,8 +980,10 @@ impl RpcPeer {
         let (write_sender, write_receiver) = mpsc::unbounded_channel();
         let inner = Arc::new(Mutex::new(RpcPeerInner {
             handlers: HashMap::new(),
+            methods: BTreeMap::new(),
             requests_next_id: 0,
             requests: HashMap::new(),
+            running: HashMap::new(),
             write_sender,
             write_receiver: Some(write_receiver),
         }));
@@ -768,6 +1002,24 @@ impl RpcPeer {
         HR: Into<Value> + Send + 'static,
         HP: DeserializeOwned,
     {
+        self.register_desc(RpcMethodDesc::new(method, ""), callback)
+    }
+
+    /// Register callback for the method with its description
+    pub fn register_desc<H, HP, HF, HR>(
+        &self,
+        desc: RpcMethodDesc,
+        callback: H,
+    ) -> Option<RpcHandler>
+    where
+        H: Fn(HP) -> HF + Send + Sync + 'static,
+        HF: Future<Output = Result<HR, RpcError>> + Send + 'static,
+        HR: Into<Value> + Send + 'static,
+        HP: DeserializeOwned,
+    {
+        let method = desc.name.clone();
+        self.inner
+            .with_mut(|inner| inner.methods.insert(method.clone(), desc));
         let handler = Arc::new(move |params| {
             let params = match serde_json::from_value(params) {
                 Ok(params) => params,
@@ -784,8 +1036,42 @@ impl RpcPeer {
         method: impl Into<String>,
         handler: RpcHandler,
     ) -> Option<RpcHandler> {
-        self.inner
-            .with_mut(move |inner| inner.handlers.insert(method.into(), handler))
+        let method = method.into();
+        self.inner.with_mut(move |inner| {
+            inner
+                .methods
+                .entry(method.clone())
+                .or_insert_with(|| RpcMethodDesc::new(method.clone(), ""));
+            inner.handlers.insert(method, handler)
+        })
+    }
+
+    /// OpenRPC-like description of all registered methods
+    pub fn discover(&self) -> Value {
+        let methods: Vec<_> = self.inner.with(|inner| {
+            inner
+                .methods
+                .values()
+                .map(RpcMethodDesc::to_value)
+                .collect()
+        });
+        json!({
+            "openrpc": "1.2.6",
+            "info": {
+                "title": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "methods": methods,
+        })
+    }
+
+    /// Protocol features supported by the peer
+    pub fn capabilities(&self) -> Value {
+        json!({
+            "batch": true,
+            "cancel": RPC_CANCEL_METHOD,
+            "discover": RPC_DISCOVER_METHOD,
+        })
     }
 
     /// Send event to the other peer
@@ -830,10 +1116,50 @@ impl RpcPeer {
     }
 
     /// Issue rpc call and wait for response
+    ///
+    /// If the returned future is dropped before the response is received, request
+    /// is cancelled on the other side with [RPC_CANCEL_METHOD] notification
     pub async fn call_with_value(
         &self,
         method: impl Into<String>,
         params: impl Into<Value>,
+    ) -> Result<Value, RpcError> {
+        self.call_inner(method.into(), params.into(), None).await
+    }
+
+    /// Issue rpc call and wait for response at most `timeout`
+    ///
+    /// Serializes params to `serde_json::Value` and issues `call_with_value_timeout`
+    pub async fn call_timeout<V: Serialize>(
+        &self,
+        method: impl Into<String>,
+        params: V,
+        timeout: Duration,
+    ) -> Result<Value, RpcError> {
+        let params = serde_json::to_value(params)?;
+        self.call_with_value_timeout(method, params, timeout).await
+    }
+
+    /// Issue rpc call and wait for response at most `timeout`
+    ///
+    /// If response is not received within `timeout`, request is cancelled on the
+    /// other side with [RPC_CANCEL_METHOD] notification and [RpcErrorKind::Timeout]
+    /// is returned
+    pub async fn call_with_value_timeout(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Value>,
+        timeout: Duration,
+    ) -> Result<Value, RpcError> {
+        self.call_inner(method.into(), params.into(), Some(timeout))
+            .await
+    }
+
+    async fn call_inner(
+        &self,
+        method: String,
+        params: Value,
+        timeout: Option<Duration>,
     ) -> Result<Value, RpcError> {
         let (tx, rx) = oneshot::channel();
         let id = self.inner.with_mut(|inner| {
@@ -842,8 +1168,11 @@ impl RpcPeer {
             inner.requests.insert(id.clone(), tx);
             id
         });
-        let method = method.into();
-        let params = params.into();
+        // cancels request if it is still pending once the call is dropped
+        let _guard = RpcCallGuard {
+            peer: self.clone(),
+            id: id.clone(),
+        };
         let span = tracing::debug_span!(
             "[RpcPeer.call_with_value]",
             method = %method,
@@ -855,7 +1184,20 @@ impl RpcPeer {
             params,
             id: id.clone(),
         })?;
-        let result = rx.instrument(span).await.map_err(|_| RpcError {
+        let rx = rx.instrument(span);
+        let rx = match timeout {
+            None => rx.await,
+            Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+                Ok(rx) => rx,
+                Err(_) => {
+                    return Err(RpcError {
+                        kind: RpcErrorKind::Timeout,
+                        data: format!("[{}] no response in {:?}", method, timeout),
+                    });
+                }
+            },
+        };
+        let result = rx.map_err(|_| RpcError {
             kind: RpcErrorKind::PeerDisconnected,
             data: "one shot channeld was destroyed".to_owned(),
         })?;
@@ -868,12 +1210,26 @@ impl RpcPeer {
         result
     }
 
-    /// Start serving rpc requests
+    /// Start serving rpc requests with JSON per line framing
     pub fn serve<'a, R, W>(
         &self,
         read: R,
         write: W,
     ) -> impl Future<Output = Result<(), RpcError>> + 'a
+    where
+        R: AsyncRead + 'a,
+        W: AsyncWrite + 'a,
+    {
+        self.serve_framed(read, write, RpcFraming::Newline)
+    }
+
+    /// Start serving rpc requests with specified framing
+    pub fn serve_framed<'a, R, W>(
+        &self,
+        read: R,
+        write: W,
+        framing: RpcFraming,
+    ) -> impl Future<Output = Result<(), RpcError>> + 'a
     where
         R: AsyncRead + 'a,
         W: AsyncWrite + 'a,
@@ -887,13 +1243,47 @@ impl RpcPeer {
                 .with_mut(|inner| inner.write_receiver.take())
                 .ok_or_else(|| RpcError::from(RpcErrorKind::ServeError))?;
 
-            let writer = rpc_writer(write, write_receiver);
-            let reader = rpc_reader(read).try_for_each(|message| peer.handle_message(message));
-            tokio::select! {
+            let (detected_send, detected_recv) = oneshot::channel();
+            let writer = rpc_writer(write, write_receiver, framing, detected_recv);
+            let reader = rpc_reader(read, framing, detected_send)
+                .try_for_each(|message| peer.handle_incoming(message));
+            let result = tokio::select! {
                 result = reader => result,
                 result = writer => result,
-            }
+            };
+            peer.disconnected();
+            result
+        }
+    }
+
+    /// Resolve all pending requests with [RpcErrorKind::PeerDisconnected] and
+    /// abort running handlers, as there is nobody to receive their results
+    fn disconnected(&self) {
+        let (requests, running) = self.inner.with_mut(|inner| {
+            (
+                std::mem::take(&mut inner.requests),
+                std::mem::take(&mut inner.running),
+            )
+        });
+        for (id, request) in requests {
+            let _ = request.send(Err(RpcError {
+                kind: RpcErrorKind::PeerDisconnected,
+                data: format!("no response for request: {:?}", id),
+            }));
+        }
+        for handle in running.into_values() {
+            handle.abort();
+        }
+    }
+
+    /// Cancel running handler of the request with `id`
+    fn cancel(&self, mut params: RpcParams) -> Result<(), RpcError> {
+        let id: RpcId = params.take(0, "id")?;
+        if let Some(handle) = self.inner.with_mut(|inner| inner.running.remove(&id)) {
+            tracing::debug!(?id, "[RpcPeer.cancel]");
+            handle.abort();
         }
+        Ok(())
     }
 
     /// Submit message to be send to the other peer
@@ -911,76 +1301,196 @@ impl RpcPeer {
     /// Handle incoming rpc message
     async fn handle_message(&self, message: RpcMessage) -> Result<(), RpcError> {
         match message {
-            RpcMessage::Response(response) => {
-                if response.id == RpcId::Null {
-                    // propagate errors with no id
-                    return response.result.map(|_| ());
-                }
-                if let Some(future) = self
-                    .inner
-                    .with_mut(|inner| inner.requests.remove(&response.id))
-                {
-                    let _ = future.send(response.result);
-                }
-            }
+            RpcMessage::Response(response) => self.handle_response(response)?,
             RpcMessage::Request(request) => {
-                let handler = self
-                    .inner
-                    .with(|inner| inner.handlers.get(&request.method).cloned());
-                if let Some(handler) = handler {
-                    let peer = self.clone();
-                    tokio::spawn(async move {
-                        let span = tracing::debug_span!(
-                            "[RpcPeer.handle_message]",
-                            method = %request.method,
-                            params = %request.params,
-                            id = ?request.id,
-                        );
-                        let result = handler(request.params).instrument(span).await;
-                        tracing::debug!(
-                            method = %request.method,
-                            result = ?result,
-                            id = ?request.id,
-                            "[RpcPeer.handle_message]"
-                        );
-                        if request.id != RpcId::Null {
-                            let method = request.method;
-                            let response = RpcResponse {
-                                result: result.map_err(|error| RpcError {
-                                    kind: error.kind,
-                                    data: format!("[{}] {}", method, error.data),
-                                }),
-                                id: request.id,
-                            };
-                            let _ = peer.submit_message(response);
-                        }
-                    });
-                } else if request.id != RpcId::Null {
-                    tracing::warn!(
-                        method = %request.method,
-                        params = %request.params,
-                        id = ?request.id,
-                        "[RpcPeer.handle_message]"
-                    );
-                    let response = RpcResponse {
-                        result: Err(RpcError {
-                            kind: RpcErrorKind::MethodNotFound,
-                            data: format!("no shuch method: {}", request.method),
-                        }),
-                        id: request.id,
-                    };
-                    self.submit_message(response)?;
+                let response = self.handle_request(request);
+                let peer = self.clone();
+                tokio::spawn(async move {
+                    if let Some(response) = response.await {
+                        let _ = peer.submit_message(response);
+                    }
+                });
+            }
+            RpcMessage::Batch(messages) => {
+                self.handle_batch(messages.into_iter().map(Ok).collect())?
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle incoming rpc message or batch
+    async fn handle_incoming(&self, incoming: RpcIncoming) -> Result<(), RpcError> {
+        match incoming {
+            RpcIncoming::Message(message) => self.handle_message(message).await,
+            RpcIncoming::Batch(messages) => self.handle_batch(messages),
+        }
+    }
+
+    /// Handle batch of messages
+    ///
+    /// Requests are started in order, responses to all requests that are not
+    /// notifications are sent back as a single batch once all of them are done.
+    fn handle_batch(&self, messages: Vec<Result<RpcMessage, RpcError>>) -> Result<(), RpcError> {
+        if messages.is_empty() {
+            return self.submit_message(RpcResponse {
+                result: Err(RpcError {
+                    kind: RpcErrorKind::InvalidRequest,
+                    data: "empty batch".to_owned(),
+                }),
+                id: RpcId::Null,
+            });
+        }
+        let invalid = |data: String| {
+            let response = RpcResponse {
+                result: Err(RpcError {
+                    kind: RpcErrorKind::InvalidRequest,
+                    data,
+                }),
+                id: RpcId::Null,
+            };
+            future::ready(Some(response)).boxed()
+        };
+        let mut responses = Vec::new();
+        for message in messages {
+            match message {
+                Ok(RpcMessage::Request(request)) => responses.push(self.handle_request(request)),
+                Ok(RpcMessage::Response(response)) => self.handle_response(response)?,
+                Ok(RpcMessage::Batch(_)) => {
+                    responses.push(invalid("nested batches are not allowed".to_owned()))
                 }
+                Err(error) => responses.push(invalid(error.data)),
             }
         }
+        if responses.is_empty() {
+            return Ok(());
+        }
+        let peer = self.clone();
+        tokio::spawn(async move {
+            let responses: Vec<_> = future::join_all(responses)
+                .await
+                .into_iter()
+                .flatten()
+                .map(RpcMessage::from)
+                .collect();
+            // batch of notifications is not replied to
+            if !responses.is_empty() {
+                let _ = peer.submit_message(RpcMessage::Batch(responses));
+            }
+        });
         Ok(())
     }
+
+    /// Resolve pending call with the response
+    fn handle_response(&self, response: RpcResponse) -> Result<(), RpcError> {
+        if response.id == RpcId::Null {
+            // propagate errors with no id
+            return response.result.map(|_| ());
+        }
+        if let Some(future) = self
+            .inner
+            .with_mut(|inner| inner.requests.remove(&response.id))
+        {
+            let _ = future.send(response.result);
+        }
+        Ok(())
+    }
+
+    /// Spawn handler for the request, resolves to the response unless the
+    /// request is a notification
+    fn handle_request(&self, request: RpcRequest) -> BoxFuture<'static, Option<RpcResponse>> {
+        if request.method == RPC_CANCEL_METHOD {
+            let result = RpcParams::try_from(request.params).and_then(|params| self.cancel(params));
+            if let Err(error) = result {
+                tracing::warn!(?error, "[RpcPeer.handle_message] invalid cancel request");
+            }
+            return future::ready(None).boxed();
+        }
+        if request.method == RPC_DISCOVER_METHOD && !request.id.is_null() {
+            let response = RpcResponse {
+                result: Ok(self.discover()),
+                id: request.id,
+            };
+            return future::ready(Some(response)).boxed();
+        }
+        let handler = self
+            .inner
+            .with(|inner| inner.handlers.get(&request.method).cloned());
+        let Some(handler) = handler else {
+            if request.id == RpcId::Null {
+                return future::ready(None).boxed();
+            }
+            tracing::warn!(
+                method = %request.method,
+                params = %request.params,
+                id = ?request.id,
+                "[RpcPeer.handle_message]"
+            );
+            let response = RpcResponse {
+                result: Err(RpcError {
+                    kind: RpcErrorKind::MethodNotFound,
+                    data: format!("no shuch method: {}", request.method),
+                }),
+                id: request.id,
+            };
+            return future::ready(Some(response)).boxed();
+        };
+        let id = request.id.clone();
+        let task = tokio::spawn(async move {
+            let span = tracing::debug_span!(
+                "[RpcPeer.handle_message]",
+                method = %request.method,
+                params = %request.params,
+                id = ?request.id,
+            );
+            let result = handler(request.params).instrument(span).await;
+            tracing::debug!(
+                method = %request.method,
+                result = ?result,
+                id = ?request.id,
+                "[RpcPeer.handle_message]"
+            );
+            if request.id == RpcId::Null {
+                return None;
+            }
+            let method = request.method;
+            Some(RpcResponse {
+                result: result.map_err(|error| RpcError {
+                    kind: error.kind,
+                    data: format!("[{}] {}", method, error.data),
+                }),
+                id: request.id,
+            })
+        });
+        if id == RpcId::Null {
+            return task.map(|response| response.ok().flatten()).boxed();
+        }
+        let peer = self.clone();
+        self.inner
+            .with_mut(|inner| inner.running.insert(id.clone(), task.abort_handle()));
+        async move {
+            let response = task.await;
+            peer.inner.with_mut(|inner| inner.running.remove(&id));
+            match response {
+                Ok(response) => response,
+                Err(error) if error.is_cancelled() => Some(RpcResponse {
+                    result: Err(RpcErrorKind::RequestCancelled.into()),
+                    id,
+                }),
+                Err(_) => None,
+            }
+        }
+        .boxed()
+    }
 }
 
 /// Write stream of messages from message receiver
+///
+/// With [RpcFraming::Auto] writer waits for the framing detected by the reader
 async fn rpc_writer<W>(
     write: W,
     mut messages: mpsc::UnboundedReceiver<RpcMessage>,
+    framing: RpcFraming,
+    detected: oneshot::Receiver<RpcFraming>,
 ) -> Result<(), RpcError>
 where
     W: AsyncWrite,
@@ -990,13 +1500,27 @@ where
     let write = BufWriter::new(write);
     tokio::pin!(write);
 
+    let framing = match framing {
+        RpcFraming::Auto => match detected.await {
+            Ok(framing) => framing,
+            Err(_) => return Ok(()), // reader is done before any message
+        },
+        framing => framing,
+    };
+
     while let Some(message) = messages.recv().await {
         // clear buffers
         message_data.clear();
         // serialize
         serde_json::to_writer(&mut message_data, &message)?;
-        writeln!(&mut message_data)?;
         // write
+        match framing {
+            RpcFraming::ContentLength => {
+                let header = format!("Content-Length: {}\r\n\r\n", message_data.len());
+                write.write_all(header.as_bytes()).await?;
+            }
+            _ => writeln!(&mut message_data)?,
+        }
         write.write_all(message_data.as_ref()).await?;
         write.flush().await?;
     }
@@ -1004,31 +1528,73 @@ where
 }
 
 /// Read stream of RpcMessages from AsyncRead
-fn rpc_reader<'a, R>(read: R) -> impl Stream<Item = Result<RpcMessage, RpcError>> + 'a
+///
+/// With [RpcFraming::Auto] framing is detected from the first non whitespace
+/// byte, and reported with `detected`
+fn rpc_reader<'a, R>(
+    read: R,
+    framing: RpcFraming,
+    detected: oneshot::Sender<RpcFraming>,
+) -> impl Stream<Item = Result<RpcIncoming, RpcError>> + 'a
 where
     R: AsyncRead + Unpin + 'a,
 {
     struct State<R> {
         reader: BufReader<R>,
         message_buf: Vec<u8>,
+        framing: RpcFraming,
+        detected: Option<oneshot::Sender<RpcFraming>>,
     }
     futures::stream::try_unfold(
         State {
             reader: BufReader::new(read),
             message_buf: Vec::new(),
+            framing,
+            detected: Some(detected),
         },
         |mut state| {
             async move {
+                let read_error = |error: std::io::Error| RpcError {
+                    kind: RpcErrorKind::ParseError,
+                    data: format!("failed to read message: {}", error),
+                };
+
+                // detect framing
+                if state.framing == RpcFraming::Auto {
+                    let Some(framing) = framing_detect(&mut state.reader)
+                        .await
+                        .map_err(read_error)?
+                    else {
+                        return Ok(None);
+                    };
+                    state.framing = framing;
+                    if let Some(detected) = state.detected.take() {
+                        _ = detected.send(framing);
+                    }
+                }
+
                 // read message
                 state.message_buf.clear();
-                state
-                    .reader
-                    .read_until(b'\n', &mut state.message_buf)
-                    .await
-                    .map_err(|error| RpcError {
-                        kind: RpcErrorKind::ParseError,
-                        data: format!("failed to read message: {}", error),
-                    })?;
+                match state.framing {
+                    RpcFraming::ContentLength => {
+                        let Some(length) = content_length_read(&mut state.reader).await? else {
+                            return Ok(None);
+                        };
+                        state.message_buf.resize(length, 0);
+                        state
+                            .reader
+                            .read_exact(&mut state.message_buf)
+                            .await
+                            .map_err(read_error)?;
+                    }
+                    _ => {
+                        state
+                            .reader
+                            .read_until(b'\n', &mut state.message_buf)
+                            .await
+                            .map_err(read_error)?;
+                    }
+                }
 
                 // eof
                 if state.message_buf.is_empty() {
@@ -1036,13 +1602,33 @@ where
                 }
 
                 // parse message
-                let message =
-                    serde_json::from_slice(state.message_buf.as_ref()).map_err(|error| {
-                        RpcError {
-                            kind: RpcErrorKind::ParseError,
-                            data: format!("failed parse message: {}", error),
-                        }
-                    })?;
+                let parse_error = |error: serde_json::Error| RpcError {
+                    kind: RpcErrorKind::ParseError,
+                    data: format!("failed parse message: {}", error),
+                };
+                let is_batch = state
+                    .message_buf
+                    .iter()
+                    .find(|byte| !byte.is_ascii_whitespace())
+                    == Some(&b'[');
+                let message = if is_batch {
+                    let values: Vec<Value> =
+                        serde_json::from_slice(state.message_buf.as_ref()).map_err(parse_error)?;
+                    let messages = values
+                        .into_iter()
+                        .map(|value| {
+                            serde_json::from_value(value).map_err(|error| RpcError {
+                                kind: RpcErrorKind::InvalidRequest,
+                                data: error.to_string(),
+                            })
+                        })
+                        .collect();
+                    RpcIncoming::Batch(messages)
+                } else {
+                    RpcIncoming::Message(
+                        serde_json::from_slice(state.message_buf.as_ref()).map_err(parse_error)?,
+                    )
+                };
 
                 Ok(Some((message, state)))
             }
@@ -1050,6 +1636,87 @@ where
     )
 }
 
+/// Detect framing from the first non whitespace byte, `None` on EOF
+async fn framing_detect<R>(reader: &mut BufReader<R>) -> Result<Option<RpcFraming>, std::io::Error>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        let buf = reader.fill_buf().await?;
+        let Some(first) = buf.first().copied() else {
+            return Ok(None);
+        };
+        if first.is_ascii_whitespace() {
+            reader.consume(1);
+            continue;
+        }
+        let framing = if matches!(first, b'{' | b'[') {
+            RpcFraming::Newline
+        } else {
+            RpcFraming::ContentLength
+        };
+        return Ok(Some(framing));
+    }
+}
+
+/// Read headers of the message, and return value of `Content-Length` header,
+/// `None` on EOF
+async fn content_length_read<R>(reader: &mut BufReader<R>) -> Result<Option<usize>, RpcError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let size = reader
+            .read_line(&mut line)
+            .await
+            .map_err(|error| RpcError {
+                kind: RpcErrorKind::ParseError,
+                data: format!("failed to read header: {}", error),
+            })?;
+        if size == 0 {
+            if length.is_none() && line.is_empty() {
+                return Ok(None);
+            }
+            return Err(RpcError {
+                kind: RpcErrorKind::ParseError,
+                data: "unexpected EOF in headers".to_owned(),
+            });
+        }
+        let header = line.trim();
+        if header.is_empty() {
+            match length {
+                Some(length) => return Ok(Some(length)),
+                None => continue, // separator between messages
+            }
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(RpcError {
+                kind: RpcErrorKind::ParseError,
+                data: format!("invalid header: {}", header),
+            });
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let value: usize = value.trim().parse().map_err(|error| RpcError {
+                kind: RpcErrorKind::ParseError,
+                data: format!("invalid Content-Length: {}", error),
+            })?;
+            if value > RPC_CONTENT_LENGTH_MAX {
+                return Err(RpcError {
+                    kind: RpcErrorKind::ParseError,
+                    data: format!(
+                        "Content-Length {} exceeds maximum {}",
+                        value, RPC_CONTENT_LENGTH_MAX
+                    ),
+                });
+            }
+            length.replace(value);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1123,6 +1790,20 @@ mod tests {
         assert_eq!(expected[1..], serde_json::to_string(&request)?);
         assert_eq!(request, serde_json::from_str::<RpcRequest>(expected)?);
 
+        // batch
+        let batch = RpcMessage::Batch(vec![
+            request.into(),
+            RpcResponse {
+                result: Ok(1.into()),
+                id: RpcId::Int(1),
+            }
+            .into(),
+        ]);
+        let expected = "[{\"method\":\"func\"},{\"result\":1,\"id\":1}]";
+        assert_eq!(expected, serde_json::to_string(&batch)?);
+        assert_eq!(batch, serde_json::from_str::<RpcMessage>(expected)?);
+        assert!(serde_json::from_str::<RpcMessage>("[[{\"method\":\"func\"}]]").is_err());
+
         Ok(())
     }
 
@@ -1191,17 +1872,276 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rpc_batch() -> Result<(), RpcError> {
+        let peer = RpcPeer::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        peer.register("add", |mut params: RpcParams| async move {
+            let a: i64 = params.take(0, "a")?;
+            let b: i64 = params.take(1, "b")?;
+            Ok(a + b)
+        });
+        peer.register("send", move |params: Value| {
+            let tx = tx.clone();
+            async move {
+                tx.send(params).unwrap();
+                Ok(Value::Null)
+            }
+        });
+
+        let (channel, peer_channel) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(peer_channel);
+        tokio::spawn(peer.serve(read, write));
+        let (read, mut write) = tokio::io::split(channel);
+        let mut lines = BufReader::new(read).lines();
+        let mut roundtrip = async |request: &str| -> Result<Value, RpcError> {
+            write.write_all(request.as_bytes()).await?;
+            write.write_all(b"\n").await?;
+            let line = lines.next_line().await?.unwrap_or_default();
+            Ok(serde_json::from_str(&line)?)
+        };
+
+        // responses are gathered into a single batch, notifications are not replied
+        let reply = roundtrip(
+            r#"[
+                {"method": "add", "params": [1, 2], "id": 1},
+                {"method": "send", "params": [1]},
+                {"method": "add", "params": {"a": 3, "b": 4}, "id": "two"},
+                {"method": "no_such_method", "id": 3},
+                1
+            ]"#
+            .replace('\n', " ")
+            .as_str(),
+        )
+        .await?;
+        let reply = reply.as_array().expect("batch reply");
+        assert_eq!(reply.len(), 4);
+        assert_eq!(reply[0], json!({"result": 3, "id": 1}));
+        assert_eq!(reply[1], json!({"result": 7, "id": "two"}));
+        assert_eq!(reply[2]["error"]["code"], json!(-32601));
+        assert_eq!(reply[2]["id"], json!(3));
+        assert_eq!(reply[3]["error"]["code"], json!(-32600));
+        assert_eq!(rx.recv().await, Some(json!([1])));
+
+        // empty batch is an invalid request
+        let reply = roundtrip("[]").await?;
+        assert_eq!(reply["error"]["code"], json!(-32600));
+
+        // batch of notifications is not replied to
+        let reply =
+            roundtrip(r#"[{"method": "send", "params": [2]}, {"method": "send", "params": [3]}]"#);
+        let reply = tokio::time::timeout(std::time::Duration::from_millis(50), reply).await;
+        assert!(reply.is_err());
+        assert_eq!(rx.recv().await, Some(json!([2])));
+        assert_eq!(rx.recv().await, Some(json!([3])));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rpc_peer_duplex() -> Result<(), RpcError> {
-        test_rpc_peer(tokio::io::duplex(16)).await
+        let framing = (RpcFraming::Newline, RpcFraming::Newline);
+        test_rpc_peer(tokio::io::duplex(16), framing).await
     }
 
     #[tokio::test]
     async fn test_rpc_peer_socket() -> Result<(), RpcError> {
-        test_rpc_peer(tokio::net::UnixStream::pair()?).await
+        let framing = (RpcFraming::Newline, RpcFraming::Newline);
+        test_rpc_peer(tokio::net::UnixStream::pair()?, framing).await
+    }
+
+    #[tokio::test]
+    async fn test_rpc_peer_content_length() -> Result<(), RpcError> {
+        let framing = (RpcFraming::ContentLength, RpcFraming::ContentLength);
+        test_rpc_peer(tokio::io::duplex(16), framing).await?;
+        let framing = (RpcFraming::Auto, RpcFraming::ContentLength);
+        test_rpc_peer(tokio::io::duplex(16), framing).await?;
+        let framing = (RpcFraming::Auto, RpcFraming::Newline);
+        test_rpc_peer(tokio::io::duplex(16), framing).await
+    }
+
+    #[tokio::test]
+    async fn test_rpc_framing() -> Result<(), RpcError> {
+        let peer = RpcPeer::new();
+        peer.register("echo", |params: Value| async move { Ok(params) });
+        let (channel, peer_channel) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(peer_channel);
+        tokio::spawn(peer.serve_framed(read, write, RpcFraming::Auto));
+
+        // nothing is sent until framing is detected
+        peer.notify("ready", Value::Null)?;
+        let (read, mut write) = tokio::io::split(channel);
+        let mut read = BufReader::new(read);
+        let message = r#"{"method":"echo","params":[1],"id":1}"#;
+        let request = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+            message.len(),
+            message,
+        );
+        write.write_all(request.as_bytes()).await?;
+
+        for expected in [json!({"method": "ready"}), json!({"result": [1], "id": 1})] {
+            let length = content_length_read(&mut read).await?.unwrap_or_default();
+            let mut data = vec![0; length];
+            read.read_exact(&mut data).await?;
+            assert_eq!(serde_json::from_slice::<Value>(&data)?, expected);
+        }
+
+        assert_eq!(
+            "lsp".parse::<RpcFraming>().ok(),
+            Some(RpcFraming::ContentLength)
+        );
+        assert!("bla".parse::<RpcFraming>().is_err());
+
+        // too large messages are rejected
+        let header = format!("Content-Length: {}\r\n\r\n", RPC_CONTENT_LENGTH_MAX + 1);
+        let error = content_length_read(&mut BufReader::new(header.as_bytes()))
+            .await
+            .expect_err("Content-Length above maximum must fail");
+        assert_eq!(error.kind, RpcErrorKind::ParseError);
+
+        Ok(())
     }
 
-    async fn test_rpc_peer<C>((a_channel, b_channel): (C, C)) -> Result<(), RpcError>
+    #[tokio::test]
+    async fn test_rpc_cancel() -> Result<(), RpcError> {
+        // handler that never finishes, and reports when it is dropped
+        struct DropGuard(mpsc::UnboundedSender<()>);
+        impl Drop for DropGuard {
+            fn drop(&mut self) {
+                let _ = self.0.send(());
+            }
+        }
+        let (dropped_send, mut dropped) = mpsc::unbounded_channel();
+        let a = RpcPeer::new();
+        a.register("pending", move |_params: Value| {
+            let guard = DropGuard(dropped_send.clone());
+            async move {
+                let _guard = guard;
+                future::pending::<Result<Value, RpcError>>().await
+            }
+        });
+        let b = RpcPeer::new();
+
+        let (a_channel, b_channel) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(a_channel);
+        let a_serve = tokio::spawn(a.serve(read, write));
+        let (read, write) = tokio::io::split(b_channel);
+        tokio::spawn(b.serve(read, write));
+
+        // timeout cancels handler on the other side
+        let timeout = Duration::from_millis(50);
+        let error = b
+            .call_timeout("pending", Value::Null, timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind, RpcErrorKind::Timeout);
+        assert_eq!(dropped.recv().await, Some(()));
+
+        // explicit cancel
+        let call = tokio::spawn({
+            let b = b.clone();
+            async move { b.call("pending", Value::Null).await }
+        });
+        tokio::task::yield_now().await;
+        b.notify(RPC_CANCEL_METHOD, json!({"id": 1}))?;
+        let error = call.await.unwrap().unwrap_err();
+        assert_eq!(error.kind, RpcErrorKind::RequestCancelled);
+        assert_eq!(dropped.recv().await, Some(()));
+
+        // dropped call cancels request on the other side
+        let call = tokio::spawn({
+            let b = b.clone();
+            async move { b.call("pending", Value::Null).await }
+        });
+        tokio::task::yield_now().await;
+        call.abort();
+        assert!(call.await.unwrap_err().is_cancelled());
+        assert_eq!(dropped.recv().await, Some(()));
+        assert!(b.inner.with(|inner| inner.requests.is_empty()));
+
+        // pending requests are resolved on disconnect
+        let call = tokio::spawn({
+            let b = b.clone();
+            async move { b.call("pending", Value::Null).await }
+        });
+        tokio::task::yield_now().await;
+        a_serve.abort();
+        let error = call.await.unwrap().unwrap_err();
+        assert_eq!(error.kind, RpcErrorKind::PeerDisconnected);
+        let error = b.call("pending", Value::Null).await.unwrap_err();
+        assert_eq!(error.kind, RpcErrorKind::PeerDisconnected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rpc_discover() -> Result<(), RpcError> {
+        let a = RpcPeer::new();
+        a.register_desc(
+            RpcMethodDesc::new("sum", "Sum of the values")
+                .param("values", "[int | float]")
+                .param_opt("start", "float")
+                .result("float"),
+            |mut params: RpcParams| async move {
+                let values: Vec<f64> = params.take(0, "values")?;
+                let start: Option<f64> = params.take_opt(1, "start")?;
+                Ok(values.into_iter().sum::<f64>() + start.unwrap_or(0.0))
+            },
+        );
+        a.register(
+            "undocumented",
+            |_params: Value| async move { Ok(Value::Null) },
+        );
+        let b = RpcPeer::new();
+
+        let (a_channel, b_channel) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(a_channel);
+        tokio::spawn(a.serve(read, write));
+        let (read, write) = tokio::io::split(b_channel);
+        tokio::spawn(b.serve(read, write));
+
+        let desc = b.call(RPC_DISCOVER_METHOD, Value::Null).await?;
+        assert_eq!(desc["openrpc"], json!("1.2.6"));
+        assert_eq!(
+            desc["methods"],
+            json!([
+                {
+                    "name": "sum",
+                    "description": "Sum of the values",
+                    "params": [
+                        {
+                            "name": "values",
+                            "required": true,
+                            "schema": {
+                                "type": "array",
+                                "items": {"oneOf": [{"type": "integer"}, {"type": "number"}]},
+                            },
+                        },
+                        {"name": "start", "required": false, "schema": {"type": "number"}},
+                    ],
+                    "result": {"name": "result", "schema": {"type": "number"}},
+                },
+                {
+                    "name": "undocumented",
+                    "description": "",
+                    "params": [],
+                    "result": {"name": "result", "schema": {"type": "null"}},
+                },
+            ])
+        );
+        assert_eq!(
+            rpc_type_schema("Item?"),
+            json!({"oneOf": [{"title": "Item"}, {"type": "null"}]})
+        );
+
+        Ok(())
+    }
+
+    async fn test_rpc_peer<C>(
+        (a_channel, b_channel): (C, C),
+        (a_framing, b_framing): (RpcFraming, RpcFraming),
+    ) -> Result<(), RpcError>
     where
         C: AsyncRead + AsyncWrite + Send + 'static,
     {
@@ -1239,9 +2179,9 @@ mod tests {
 
         // serve
         let (read, write) = tokio::io::split(a_channel);
-        tokio::spawn(a.serve(read, write));
+        tokio::spawn(a.serve_framed(read, write, a_framing));
         let (read, write) = tokio::io::split(b_channel);
-        tokio::spawn(b.serve(read, write));
+        tokio::spawn(b.serve_framed(read, write, b_framing));
 
         // basic
         let hello_result = b.call("name", Value::Null).await?;
@@ -7,6 +7,7 @@ use arrow_array::{
     },
 };
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::{Regex, RegexBuilder};
 use std::{cell::RefCell, cmp::Ordering, fmt, sync::Arc};
 
 /// Scorer
@@ -473,6 +474,99 @@ impl Scorer for SubstrScorer {
     }
 }
 
+thread_local! {
+    static TEXT_CELL: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Regular expression scorer
+///
+/// Matches the first occurrence of the case-insensitive regular expression
+/// inside the haystack. Invalid expression does not match anything.
+#[derive(Debug, Clone)]
+pub struct RegexScorer {
+    needle: String,
+    regex: Option<Regex>,
+}
+
+impl RegexScorer {
+    pub fn new(needle: &str) -> Self {
+        let regex = if needle.is_empty() {
+            None
+        } else {
+            RegexBuilder::new(needle)
+                .case_insensitive(true)
+                .build()
+                .ok()
+        };
+        Self {
+            needle: needle.to_owned(),
+            regex,
+        }
+    }
+}
+
+impl Scorer for RegexScorer {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    fn needle(&self) -> &str {
+        self.needle.as_str()
+    }
+
+    fn score_ref(
+        &self,
+        haystack: &[char],
+        score: &mut Score,
+        mut positions: Positions<&mut [u8]>,
+    ) -> bool {
+        positions.clear();
+        if self.needle.is_empty() {
+            *score = Score::MAX;
+            return true;
+        }
+        let Some(regex) = &self.regex else {
+            return false;
+        };
+
+        // haystack is converted into a reused buffer, to avoid allocation per item
+        let found = TEXT_CELL.with_borrow_mut(|text| {
+            text.clear();
+            text.extend(haystack.iter());
+            regex.find(text).map(|found| found.range())
+        });
+        let Some(found) = found else {
+            return false;
+        };
+        let mut match_start = 0;
+        let mut match_end = 0;
+        let mut offset = 0;
+        for (index, ch) in haystack.iter().enumerate() {
+            if offset < found.start {
+                match_start = index + 1;
+                match_end = index + 1;
+            } else if offset < found.end {
+                positions.set(index);
+                match_end = index + 1;
+            } else {
+                break;
+            }
+            offset += ch.len_utf8();
+        }
+
+        let match_start = match_start as f32;
+        let match_end = match_end as f32;
+        let heystack_len = haystack.len() as f32;
+        *score = Score::new(
+            (match_start - match_end)
+                + (match_end - match_start) / heystack_len
+                + (match_start + 1.0).recip()
+                + (heystack_len - match_end + 1.0).recip(),
+        );
+        true
+    }
+}
+
 /// Knuth-Morris-Pratt pattern
 #[derive(Debug, Clone)]
 pub struct KMPPattern<T> {
@@ -991,6 +1085,20 @@ mod tests {
         assert_eq!(positions, ps([0]));
     }
 
+    #[test]
+    fn test_regex_scorer() {
+        let scorer: Box<dyn Scorer> = Box::new(RegexScorer::new("b+c"));
+        let (_, positions) = score_haystack(&scorer, &(), "aBbbcd".to_string()).unwrap();
+        assert_eq!(positions, ps([1, 2, 3, 4]));
+
+        let scorer: Box<dyn Scorer> = Box::new(RegexScorer::new("^ä.x$"));
+        let (_, positions) = score_haystack(&scorer, &(), "äyx".to_string()).unwrap();
+        assert_eq!(positions, ps([0, 1, 2]));
+
+        let scorer: Box<dyn Scorer> = Box::new(RegexScorer::new("one("));
+        assert!(score_haystack(&scorer, &(), "one(".to_string()).is_none());
+    }
+
     #[test]
     fn test_scorer() {
         let haystack: StringViewArray = [
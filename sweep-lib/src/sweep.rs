@@ -1,10 +1,13 @@
 use crate::{
-    ALL_SCORER_BUILDERS, Haystack, HaystackPreview, RankedItems, Ranker, RankerThread, ScoreItem,
-    ScorerBuilder,
+    ALL_SCORER_BUILDERS, Haystack, HaystackPreview, HaystackViewOptions, HaystackViewOverflow,
+    PreviewEnv, QueryHistory, RankedItems, Ranker, RankerThread, ScoreItem, Scorer, ScorerBuilder,
     common::{LockExt, VecDeserializeSeed},
-    rpc::{RpcError, RpcParams, RpcPeer},
-    scorer_by_name,
-    widgets::{ActionDesc, Input, InputAction, List, ListAction, ListItems, Theme},
+    regex_scorer,
+    rpc::{RpcError, RpcFraming, RpcMethodDesc, RpcParams, RpcPeer},
+    scorer_by_name, substr_scorer,
+    widgets::{
+        ActionDesc, Border, BorderStyle, Input, InputAction, List, ListAction, ListItems, Theme,
+    },
 };
 use anyhow::{Context, Error};
 use crossbeam_channel::{Receiver, Sender, unbounded};
@@ -17,12 +20,13 @@ use serde::{
 use serde_json::{Value, json};
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fmt,
     future::Future,
     marker::PhantomData,
     mem,
     ops::Deref,
+    path::PathBuf,
     sync::{
         Arc, LazyLock, RwLock,
         atomic::{AtomicBool, AtomicUsize, Ordering},
@@ -32,9 +36,10 @@ use std::{
 };
 use surf_n_term::{
     CellWrite, Face, FaceAttrs, Glyph, Key, KeyChord, KeyMap, KeyMapHandler, KeyMod, KeyName,
-    Position, Size, SystemTerminal, Terminal, TerminalAction, TerminalCommand, TerminalEvent,
-    TerminalSize, TerminalSurface, TerminalSurfaceExt, TerminalWaker,
+    Position, Size, Surface, SurfaceMut, SystemTerminal, Terminal, TerminalAction, TerminalCommand,
+    TerminalEvent, TerminalSize, TerminalSurface, TerminalSurfaceExt, TerminalWaker,
     encoder::ColorDepth,
+    keys::KeyMapResult,
     terminal::Mouse,
     view::{
         Align, Axis, BoxConstraint, BoxView, Container, Flex, FlexChild, FlexRef, IntoView,
@@ -45,6 +50,7 @@ use surf_n_term::{
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::{Mutex, mpsc},
+    time::{self, Instant},
 };
 
 static ICONS: LazyLock<HashMap<String, Glyph>> = LazyLock::new(|| {
@@ -57,7 +63,14 @@ pub static PROMPT_DEFAULT_ICON: LazyLock<&'static Glyph> = LazyLock::new(|| {
 });
 static KEYBOARD_ICON: LazyLock<&'static Glyph> =
     LazyLock::new(|| ICONS.get("keyboard").expect("failed to get keyboard icon"));
+static HISTORY_ICON: LazyLock<&'static Glyph> =
+    LazyLock::new(|| ICONS.get("history").expect("failed to get history icon"));
 const SWEEP_SCORER_NEXT_TAG: &str = "sweep.scorer.next";
+/// Number of cells list items are scrolled horizontally by a single action
+const LIST_SCROLL_STEP: usize = 8;
+// window state change notifications are sent at most once per this period, with
+// the latest state
+const CHANGES_NOTIFY_THROTTLE: Duration = Duration::from_millis(50);
 
 #[derive(Clone)]
 pub struct SweepOptions {
@@ -71,6 +84,17 @@ pub struct SweepOptions {
     pub layout: WindowLayout,
     /// default window id, if None no default window is created
     pub window_uid: Option<WindowId>,
+    /// additional key bindings applied to every window
+    pub bindings: Vec<KeyBinding>,
+    /// file used to store history of submitted queries
+    pub history: Option<PathBuf>,
+    /// enable vi-style modal key bindings
+    pub vi_mode: bool,
+    /// placement of the large preview in full layout, if None preview is
+    /// placed according to the layout height
+    pub preview_layout: Option<PreviewLayout>,
+    /// policy applied to the marked items
+    pub marks_policy: MarksPolicy,
 }
 
 impl Default for SweepOptions {
@@ -85,6 +109,11 @@ impl Default for SweepOptions {
             window_uid: Some(WindowId::String("default".into())),
             scorers: ALL_SCORER_BUILDERS.clone(),
             layout: WindowLayout::default(),
+            bindings: Vec::new(),
+            history: None,
+            vi_mode: false,
+            preview_layout: None,
+            marks_policy: MarksPolicy::default(),
         }
     }
 }
@@ -105,6 +134,11 @@ impl fmt::Debug for SweepOptions {
             .field("title", &self.title)
             .field("tty_path", &self.tty_path)
             .field("layout", &self.layout)
+            .field("bindings", &self.bindings)
+            .field("history", &self.history)
+            .field("vi_mode", &self.vi_mode)
+            .field("preview_layout", &self.preview_layout)
+            .field("marks_policy", &self.marks_policy)
             .finish()
     }
 }
@@ -144,19 +178,28 @@ enum SweepWindowRequest<H> {
     NeedleGet(oneshot::Sender<String>),
     PromptSet(Option<String>, Option<Glyph>),
     ThemeGet(oneshot::Sender<Theme>),
+    ThemeSet(Theme),
+    ThemeReload,
+    LayoutSet(Option<WindowLayout>),
     Bind {
+        mode: ViMode,
         chord: KeyChord,
-        tag: String,
+        tags: Vec<String>,
         desc: String,
     },
     Current(oneshot::Sender<Option<H>>),
     Marked(oneshot::Sender<Vec<H>>),
+    MarksSet(Vec<usize>),
+    MarksToggle(Vec<usize>),
+    MarksClear,
+    MarksPolicySet(MarksPolicy),
     CursorSet {
         position: usize,
     },
     ScorerByName(Option<String>, oneshot::Sender<bool>),
     ScorerSet(ScorerBuilder),
     PreviewSet(Option<bool>),
+    PreviewLayoutSet(Option<PreviewLayout>),
     FooterSet(Option<Arc<dyn View>>),
     HaystackExtend(Vec<H>),
     HaystackUpdate {
@@ -164,8 +207,17 @@ enum SweepWindowRequest<H> {
         item: H,
     },
     HaystackClear,
+    HaystackUpsert(Vec<H>),
+    HaystackRemove(Vec<String>),
     RankerKeepOrder(Option<bool>),
     RenderSuppress(bool),
+    HistorySet(QueryHistory),
+    Subscribe(SweepSubscription),
+    ActionRun {
+        name: String,
+        args: Option<Value>,
+        resolve: oneshot::Sender<Result<(), Error>>,
+    },
 }
 
 /// Request generated by [Sweep] type
@@ -181,6 +233,7 @@ enum SweepRequest<H> {
         uid: Option<WindowId>,
         request: SweepWindowRequest<H>,
     },
+    ThemeReload,
 }
 
 /// Events returned to [Sweep] type
@@ -197,6 +250,110 @@ pub enum SweepEvent<H> {
     },
     Window(WindowEvent),
     Resize(TerminalSize),
+    /// Query has changed, only generated if subscribed with [Sweep::subscribe]
+    QueryChanged {
+        uid: WindowId,
+        query: String,
+    },
+    /// Item under the cursor has changed, only generated if subscribed
+    CursorChanged {
+        uid: WindowId,
+        item: Option<H>,
+    },
+    /// Set of marked items has changed, only generated if subscribed
+    MarksChanged {
+        uid: WindowId,
+        items: Vec<H>,
+    },
+    /// Scorer used for ranking has changed, only generated if subscribed
+    ScorerChanged {
+        uid: WindowId,
+        name: String,
+    },
+    /// Whether to keep order of the items has changed, only generated if subscribed
+    KeepOrderChanged {
+        uid: WindowId,
+        keep_order: bool,
+    },
+    /// Theme has been set or reloaded, only generated if subscribed
+    ThemeChanged {
+        uid: WindowId,
+        theme: Theme,
+    },
+    /// Layout has been set, `None` means default layout is used, only generated if subscribed
+    LayoutChanged {
+        uid: WindowId,
+        layout: Option<WindowLayout>,
+    },
+}
+
+/// Window state changes reported as [SweepEvent]s, nothing is reported by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepSubscription {
+    /// Generate [SweepEvent::QueryChanged]
+    pub query: bool,
+    /// Generate [SweepEvent::CursorChanged]
+    pub cursor: bool,
+    /// Generate [SweepEvent::MarksChanged]
+    pub marks: bool,
+    /// Generate [SweepEvent::ScorerChanged]
+    pub scorer: bool,
+    /// Generate [SweepEvent::KeepOrderChanged]
+    pub keep_order: bool,
+    /// Generate [SweepEvent::ThemeChanged]
+    pub theme: bool,
+    /// Generate [SweepEvent::LayoutChanged]
+    pub layout: bool,
+}
+
+impl SweepSubscription {
+    /// Create subscription from event names (`query_changed`, `cursor_changed`, `marks_changed`,
+    /// `scorer_changed`, `keep_order_changed`, `theme_changed`, `layout_changed`)
+    pub fn from_events<I>(events: I) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut subscription = Self::default();
+        for event in events {
+            match event.as_ref() {
+                "query_changed" => subscription.query = true,
+                "cursor_changed" => subscription.cursor = true,
+                "marks_changed" => subscription.marks = true,
+                "scorer_changed" => subscription.scorer = true,
+                "keep_order_changed" => subscription.keep_order = true,
+                "theme_changed" => subscription.theme = true,
+                "layout_changed" => subscription.layout = true,
+                event => anyhow::bail!("unknown event: {}", event),
+            }
+        }
+        Ok(subscription)
+    }
+
+    /// Whether any of the events is subscribed
+    pub fn is_empty(&self) -> bool {
+        !(self.query
+            || self.cursor
+            || self.marks
+            || self.scorer
+            || self.keep_order
+            || self.theme
+            || self.layout)
+    }
+
+    /// Whether the event is generated, events other than state changes are always generated
+    pub fn contains<H>(&self, event: &SweepEvent<H>) -> bool {
+        match event {
+            SweepEvent::QueryChanged { .. } => self.query,
+            SweepEvent::CursorChanged { .. } => self.cursor,
+            SweepEvent::MarksChanged { .. } => self.marks,
+            SweepEvent::ScorerChanged { .. } => self.scorer,
+            SweepEvent::KeepOrderChanged { .. } => self.keep_order,
+            SweepEvent::ThemeChanged { .. } => self.theme,
+            SweepEvent::LayoutChanged { .. } => self.layout,
+            _ => true,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -241,7 +398,14 @@ where
         self.send_window_request(uid, SweepWindowRequest::PreviewSet(value));
     }
 
+    /// Set placement of the large preview, None restores default placement
+    pub fn preview_layout_set(&self, uid: Option<WindowId>, layout: Option<PreviewLayout>) {
+        self.send_window_request(uid, SweepWindowRequest::PreviewLayoutSet(layout));
+    }
+
     /// Extend list of searchable items from iterator
+    ///
+    /// Items with the key of an existing item replace it, same as [Sweep::items_upsert]
     pub fn items_extend<HS>(&self, uid: Option<WindowId>, items: HS)
     where
         HS: IntoIterator,
@@ -271,6 +435,8 @@ where
     }
 
     /// Update item by its index
+    ///
+    /// Update is ignored if the item has the key of another item
     pub fn item_update(&self, uid: Option<WindowId>, index: usize, item: H) {
         self.send_window_request(uid, SweepWindowRequest::HaystackUpdate { index, item })
     }
@@ -280,6 +446,22 @@ where
         self.send_window_request(uid, SweepWindowRequest::HaystackClear)
     }
 
+    /// Replace items with the same key, items without a key or with
+    /// an unknown key are appended
+    pub fn items_upsert<HS>(&self, uid: Option<WindowId>, items: HS)
+    where
+        HS: IntoIterator,
+        H: From<HS::Item>,
+    {
+        let items = items.into_iter().map(From::from).collect();
+        self.send_window_request(uid, SweepWindowRequest::HaystackUpsert(items))
+    }
+
+    /// Remove items by their keys, unknown keys are ignored
+    pub fn items_remove(&self, uid: Option<WindowId>, keys: Vec<String>) {
+        self.send_window_request(uid, SweepWindowRequest::HaystackRemove(keys))
+    }
+
     /// Get currently selected items
     pub async fn items_current(&self, uid: Option<WindowId>) -> Result<Option<H>, Error> {
         let (send, recv) = oneshot::channel();
@@ -294,6 +476,26 @@ where
         recv.await.context("items_marked")
     }
 
+    /// Mark items by their indices, other items are un-marked
+    pub fn marks_set(&self, uid: Option<WindowId>, indices: Vec<usize>) {
+        self.send_window_request(uid, SweepWindowRequest::MarksSet(indices))
+    }
+
+    /// Toggle marks of items by their indices
+    pub fn marks_toggle(&self, uid: Option<WindowId>, indices: Vec<usize>) {
+        self.send_window_request(uid, SweepWindowRequest::MarksToggle(indices))
+    }
+
+    /// Un-mark all items
+    pub fn marks_clear(&self, uid: Option<WindowId>) {
+        self.send_window_request(uid, SweepWindowRequest::MarksClear)
+    }
+
+    /// Set policy applied to the marked items
+    pub fn marks_policy_set(&self, uid: Option<WindowId>, policy: MarksPolicy) {
+        self.send_window_request(uid, SweepWindowRequest::MarksPolicySet(policy))
+    }
+
     /// Set needle to the specified string
     pub fn query_set(&self, uid: Option<WindowId>, needle: impl AsRef<str>) {
         self.send_window_request(
@@ -345,11 +547,31 @@ where
         recv.await.context("theme_get")
     }
 
+    /// Set theme
+    pub fn theme_set(&self, uid: Option<WindowId>, theme: Theme) {
+        self.send_window_request(uid, SweepWindowRequest::ThemeSet(theme))
+    }
+
+    /// Set layout of the window, `None` restores layout specified by options
+    pub fn layout_set(&self, uid: Option<WindowId>, layout: Option<WindowLayout>) {
+        self.send_window_request(uid, SweepWindowRequest::LayoutSet(layout))
+    }
+
+    /// Reload themes loaded from theme files for all windows
+    pub fn theme_reload(&self) {
+        self.send_request(SweepRequest::ThemeReload)
+    }
+
     /// Set footer
     pub fn footer_set(&self, uid: Option<WindowId>, footer: Option<Arc<dyn View>>) {
         self.send_window_request(uid, SweepWindowRequest::FooterSet(footer))
     }
 
+    /// Set query history, queries are recorded on selection
+    pub fn history_set(&self, uid: Option<WindowId>, history: QueryHistory) {
+        self.send_window_request(uid, SweepWindowRequest::HistorySet(history))
+    }
+
     /// Set cursor to specified position
     pub fn cursor_set(&self, uid: Option<WindowId>, position: usize) {
         self.send_window_request(uid, SweepWindowRequest::CursorSet { position })
@@ -362,7 +584,71 @@ where
     /// and no event will be generated. Tag can also be a standard action name
     /// (see available with `ctrl+h`) in this case [SweepEvent::Bind] is not generated.
     pub fn bind(&self, uid: Option<WindowId>, chord: KeyChord, tag: String, desc: String) {
-        self.send_window_request(uid, SweepWindowRequest::Bind { chord, tag, desc })
+        self.bind_chain(uid, chord, vec![tag], desc)
+    }
+
+    /// Bind specified chord to the sequence of actions
+    ///
+    /// Actions are executed in order on a single key press, each one is either
+    /// a standard action name or a user tag (same as [Sweep::bind]). Execution
+    /// stops on the first action that closes or switches the window.
+    pub fn bind_chain(
+        &self,
+        uid: Option<WindowId>,
+        chord: KeyChord,
+        tags: Vec<String>,
+        desc: String,
+    ) {
+        self.bind_mode(uid, ViMode::Insert, chord, tags, desc)
+    }
+
+    /// Bind specified chord to the sequence of actions in the key map of the mode
+    ///
+    /// Same as [Sweep::bind_chain], but [ViMode::Normal] binds key in the vi normal
+    /// mode key map, which is only used when vi key bindings are enabled.
+    pub fn bind_mode(
+        &self,
+        uid: Option<WindowId>,
+        mode: ViMode,
+        chord: KeyChord,
+        tags: Vec<String>,
+        desc: String,
+    ) {
+        let request = SweepWindowRequest::Bind {
+            mode,
+            chord,
+            tags,
+            desc,
+        };
+        self.send_window_request(uid, request)
+    }
+
+    /// Subscribe to the window state changes, replaces previous subscription
+    ///
+    /// Events are generated at most once per render, with the latest state
+    pub fn subscribe(&self, uid: Option<WindowId>, subscription: SweepSubscription) {
+        self.send_window_request(uid, SweepWindowRequest::Subscribe(subscription))
+    }
+
+    /// Run action by its name (as listed in help), same as pressing bound key
+    ///
+    /// `args` is only used by the `input.insert.char` action, and is a string to insert.
+    pub async fn action_run(
+        &self,
+        uid: Option<WindowId>,
+        name: String,
+        args: Option<Value>,
+    ) -> Result<(), Error> {
+        let (send, recv) = oneshot::channel();
+        self.send_window_request(
+            uid,
+            SweepWindowRequest::ActionRun {
+                name,
+                args,
+                resolve: send,
+            },
+        );
+        recv.await.context("action_run")?
     }
 
     /// Suppress rendering to reduce flickering
@@ -551,7 +837,24 @@ where
         W: AsyncWrite + 'a,
         F: FnOnce(RpcPeer),
     {
-        self.serve_seed(PhantomData::<H>, view_cache, read, write, setup)
+        self.serve_framed(view_cache, read, write, RpcFraming::Newline, setup)
+    }
+
+    /// Serve RPC endpoint via read/write with specified framing
+    pub fn serve_framed<'a, R, W, F>(
+        &self,
+        view_cache: Option<Arc<dyn ViewCache>>,
+        read: R,
+        write: W,
+        framing: RpcFraming,
+        setup: F,
+    ) -> impl Future<Output = Result<(), RpcError>> + 'a
+    where
+        R: AsyncRead + 'a,
+        W: AsyncWrite + 'a,
+        F: FnOnce(RpcPeer),
+    {
+        self.serve_seed_framed(PhantomData::<H>, view_cache, read, write, framing, setup)
     }
 }
 
@@ -568,6 +871,29 @@ where
         write: W,
         setup: F,
     ) -> impl Future<Output = Result<(), RpcError>> + 'a
+    where
+        S: DeserializeSeed<'de, Value = H> + Clone + Send + Sync + 'static,
+        R: AsyncRead + 'a,
+        W: AsyncWrite + 'a,
+        F: FnOnce(RpcPeer),
+    {
+        self.serve_seed_framed(seed, view_cache, read, write, RpcFraming::Newline, setup)
+    }
+
+    /// Serve RPC endpoint via read/write with haystack deserialization seed and
+    /// specified framing
+    ///
+    /// With [RpcFraming::Auto] `ready` event is only sent after the client sends
+    /// its first message.
+    pub fn serve_seed_framed<'de, 'a, S, R, W, F>(
+        &self,
+        seed: S,
+        view_cache: Option<Arc<dyn ViewCache>>,
+        read: R,
+        write: W,
+        framing: RpcFraming,
+        setup: F,
+    ) -> impl Future<Output = Result<(), RpcError>> + 'a
     where
         S: DeserializeSeed<'de, Value = H> + Clone + Send + Sync + 'static,
         R: AsyncRead + 'a,
@@ -577,206 +903,485 @@ where
         let peer = RpcPeer::new();
 
         // items extend
-        peer.register("items_extend", {
-            let sweep = self.clone();
-            let seed = seed.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
+        peer.register_desc(
+            RpcMethodDesc::new("items_extend", "Extend list of searchable items")
+                .param_opt("uid", "WindowId")
+                .param("items", "[Item]"),
+            {
+                let sweep = self.clone();
                 let seed = seed.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let items = params.take_seed(VecDeserializeSeed(seed), 1, "items")?;
-                    sweep.items_extend(uid, items);
-                    Ok(Value::Null)
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    let seed = seed.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let items = params.take_seed(VecDeserializeSeed(seed), 1, "items")?;
+                        sweep.items_extend(uid, items);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // item update
-        peer.register("item_update", {
-            let sweep = self.clone();
-            let seed = seed.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
+        peer.register_desc(
+            RpcMethodDesc::new("item_update", "Update item at the index")
+                .param_opt("uid", "WindowId")
+                .param("index", "int")
+                .param("item", "Item"),
+            {
+                let sweep = self.clone();
                 let seed = seed.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let index = params.take(1, "index")?;
-                    let item = params.take_seed(seed, 2, "item")?;
-                    sweep.item_update(uid, index, item);
-                    Ok(Value::Null)
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    let seed = seed.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let index = params.take(1, "index")?;
+                        let item = params.take_seed(seed, 2, "item")?;
+                        sweep.item_update(uid, index, item);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // items clear
-        peer.register("items_clear", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    sweep.items_clear(uid);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new("items_clear", "Clear list of searchable items")
+                .param_opt("uid", "WindowId"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        sweep.items_clear(uid);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
+
+        // items upsert
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "items_upsert",
+                "Replace items with the same key, other items are appended",
+            )
+            .param_opt("uid", "WindowId")
+            .param("items", "[Item]"),
+            {
+                let sweep = self.clone();
+                let seed = seed.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    let seed = seed.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let items = params.take_seed(VecDeserializeSeed(seed), 1, "items")?;
+                        sweep.items_upsert(uid, items);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        // items remove
+        peer.register_desc(
+            RpcMethodDesc::new("items_remove", "Remove items by their keys")
+                .param_opt("uid", "WindowId")
+                .param("keys", "[String]"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let keys = params.take(1, "keys")?;
+                        sweep.items_remove(uid, keys);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
 
         // items current
-        peer.register("items_current", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let current = sweep
-                        .items_current(uid)
-                        .await?
-                        .and_then(|current| serde_json::to_value(current).ok())
-                        .unwrap_or(Value::Null);
-                    Ok(current)
+        peer.register_desc(
+            RpcMethodDesc::new("items_current", "Get currently selected item if any")
+                .param_opt("uid", "WindowId")
+                .result("Item?"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let current = sweep
+                            .items_current(uid)
+                            .await?
+                            .and_then(|current| serde_json::to_value(current).ok())
+                            .unwrap_or(Value::Null);
+                        Ok(current)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // items marked
-        peer.register("items_marked", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let items = serde_json::to_value(sweep.items_marked(uid).await?)?;
-                    Ok(items)
+        peer.register_desc(
+            RpcMethodDesc::new("items_marked", "Get marked items")
+                .param_opt("uid", "WindowId")
+                .result("[Item]"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let items = serde_json::to_value(sweep.items_marked(uid).await?)?;
+                        Ok(items)
+                    }
                 }
-            }
-        });
+            },
+        );
 
-        peer.register("cursor_set", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let position = params.take(1, "position")?;
-                    sweep.cursor_set(uid, position);
-                    Ok(Value::Null)
+        // marks set
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "marks_set",
+                "Mark items by their indices, other items are un-marked",
+            )
+            .param_opt("uid", "WindowId")
+            .param("indices", "[int]"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let indices = params.take(1, "indices")?;
+                        sweep.marks_set(uid, indices);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
+
+        // marks toggle
+        peer.register_desc(
+            RpcMethodDesc::new("marks_toggle", "Toggle marks of items by their indices")
+                .param_opt("uid", "WindowId")
+                .param("indices", "[int]"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let indices = params.take(1, "indices")?;
+                        sweep.marks_toggle(uid, indices);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        // marks clear
+        peer.register_desc(
+            RpcMethodDesc::new("marks_clear", "Un-mark all items").param_opt("uid", "WindowId"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        sweep.marks_clear(uid);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        // marks policy
+        peer.register_desc(
+            RpcMethodDesc::new("marks_policy_set", "Set policy applied to the marked items")
+                .param_opt("uid", "WindowId")
+                .param_opt("limit", "int")
+                .param_opt("keep_on_clear", "bool"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let policy = MarksPolicy {
+                            limit: params.take_opt(1, "limit")?,
+                            keep_on_clear: params.take_opt(2, "keep_on_clear")?.unwrap_or(true),
+                        };
+                        sweep.marks_policy_set(uid, policy);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        peer.register_desc(
+            RpcMethodDesc::new("cursor_set", "Move cursor to the position")
+                .param_opt("uid", "WindowId")
+                .param("position", "int"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let position = params.take(1, "position")?;
+                        sweep.cursor_set(uid, position);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
 
         // query set
-        peer.register("query_set", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let query: String = params.take(1, "query")?;
-                    sweep.query_set(uid, query);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new("query_set", "Set query string")
+                .param_opt("uid", "WindowId")
+                .param("query", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let query: String = params.take(1, "query")?;
+                        sweep.query_set(uid, query);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // query get
-        peer.register("query_get", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    Ok(sweep.query_get(uid).await?)
+        peer.register_desc(
+            RpcMethodDesc::new("query_get", "Get query string")
+                .param_opt("uid", "WindowId")
+                .result("String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        Ok(sweep.query_get(uid).await?)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // terminate
-        peer.register("terminate", {
-            let sweep = self.clone();
-            move |_params: Value| {
-                sweep.send_request(SweepRequest::Terminate);
-                future::ok(Value::Null)
-            }
-        });
+        peer.register_desc(
+            RpcMethodDesc::new("terminate", "Gracefully terminate sweep process"),
+            {
+                let sweep = self.clone();
+                move |_params: Value| {
+                    sweep.send_request(SweepRequest::Terminate);
+                    future::ok(Value::Null)
+                }
+            },
+        );
 
         // prompt set
-        peer.register("prompt_set", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let prompt: Option<String> = params.take_opt(1, "prompt")?;
-                    let icon: Option<Glyph> = params.take_opt(2, "icon")?;
-                    sweep.prompt_set(uid, prompt, icon);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "prompt_set",
+                "Set prompt string (label string before search input)",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("prompt", "String")
+            .param_opt("icon", "Icon"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let prompt: Option<String> = params.take_opt(1, "prompt")?;
+                        let icon: Option<Glyph> = params.take_opt(2, "icon")?;
+                        sweep.prompt_set(uid, prompt, icon);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // footer set
-        peer.register("footer_set", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                let view_cache = view_cache.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let theme = sweep.theme_get(uid.clone()).await?;
-                    let seed = ViewDeserializer::new(Some(&theme.named_colors), view_cache);
-                    let footer: Option<Arc<dyn View>> = params.take_opt_seed(&seed, 1, "footer")?;
-                    sweep.footer_set(uid, footer);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new("footer_set", "Set footer view")
+                .param_opt("uid", "WindowId")
+                .param_opt("footer", "View"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    let view_cache = view_cache.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let theme = sweep.theme_get(uid.clone()).await?;
+                        let seed = ViewDeserializer::new(Some(&theme.named_colors), view_cache);
+                        let footer: Option<Arc<dyn View>> =
+                            params.take_opt_seed(&seed, 1, "footer")?;
+                        sweep.footer_set(uid, footer);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // key binding
-        peer.register("bind", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let chord: KeyChord = params.take(1, "key")?;
-                    let tag: String = params.take(2, "tag")?;
-                    let desc: Option<String> = params.take_opt(3, "desc")?;
-                    sweep.bind(uid, chord, tag, desc.unwrap_or_default());
-                    Ok(Value::Null)
-                }
-            }
-        });
+        peer.register_desc(
+            RpcMethodDesc::new("bind", "Assign new key binding")
+                .param_opt("uid", "WindowId")
+                .param("key", "String")
+                .param("tag", "String | [String]")
+                .param_opt("desc", "String")
+                .param_opt("mode", "\"insert\" | \"normal\""),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let key: String = params.take(1, "key")?;
+                        let tags = match params.take(2, "tag")? {
+                            BindTags::Single(tag) => vec![tag],
+                            BindTags::Chain(tags) => tags,
+                        };
+                        let desc: Option<String> = params.take_opt(3, "desc")?;
+                        let mode = match params.take_opt::<String>(4, "mode")? {
+                            Some(mode) => mode.parse()?,
+                            None => ViMode::Insert,
+                        };
+                        let chord = key_chord_parse_mode(mode, &key)?;
+                        sweep.bind_mode(uid, mode, chord, tags, desc.unwrap_or_default());
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        // query history
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "history_set",
+                "Set file used to store history of submitted queries",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("path", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let path: Option<PathBuf> = params.take_opt(1, "path")?;
+                        let history = match path {
+                            Some(path) => {
+                                tokio::task::spawn_blocking(move || QueryHistory::load(path))
+                                    .await
+                                    .map_err(Error::from)??
+                            }
+                            None => QueryHistory::default(),
+                        };
+                        sweep.history_set(uid, history);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
 
         // preview set
-        peer.register("preview_set", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let value: Option<bool> = params.take_opt(1, "value")?;
-                    sweep.preview_set(uid, value);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "preview_set",
+                "Whether to show preview associated with the current item",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("value", "bool"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let value: Option<bool> = params.take_opt(1, "value")?;
+                        sweep.preview_set(uid, value);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
+
+        // theme reload
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "theme_reload",
+                "Reload themes loaded from theme files for all windows",
+            ),
+            {
+                let sweep = self.clone();
+                move |_params: Value| {
+                    sweep.theme_reload();
+                    future::ok(Value::Null)
+                }
+            },
+        );
+
+        // preview layout set
+        peer.register_desc(
+            RpcMethodDesc::new("preview_layout_set", "Set placement of the large preview")
+                .param_opt("uid", "WindowId")
+                .param_opt("layout", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let layout: Option<String> = params.take_opt(1, "layout")?;
+                        let layout = layout.map(|layout| layout.parse()).transpose()?;
+                        sweep.preview_layout_set(uid, layout);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
 
         // window stack push
-        peer.register("window_switch", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take(0, "uid")?;
-                    let close = params.take_opt(0, "close")?.unwrap_or(false);
-                    Ok(sweep.window_switch(uid, close).await?)
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "window_switch",
+                "Switch to the window, creating it if it does not exist",
+            )
+            .param("uid", "WindowId")
+            .param_opt("close", "bool")
+            .result("bool"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take(0, "uid")?;
+                        let close = params.take_opt(1, "close")?.unwrap_or(false);
+                        Ok(sweep.window_switch(uid, close).await?)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // window stack push
-        peer.register("window_pop", {
+        peer.register_desc(RpcMethodDesc::new("window_pop", "Close current window"), {
             let sweep = self.clone();
             move |_params: Value| {
                 sweep.window_pop();
@@ -785,53 +1390,221 @@ where
         });
 
         // show quick select
-        peer.register("quick_select", {
-            let sweep = self.clone();
-            let seed = seed.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "quick_select",
+                "Select from the list of items in a new window",
+            )
+            .param("items", "[Item]")
+            .param_opt("prompt", "String")
+            .param_opt("prompt_icon", "Icon")
+            .param_opt("keep_order", "bool")
+            .param_opt("theme", "String")
+            .param_opt("scorer", "String")
+            .param("uid", "WindowId")
+            .result("[Item]"),
+            {
+                let sweep = self.clone();
                 let seed = seed.clone();
-                async move {
-                    let items = params.take_seed(VecDeserializeSeed(seed), 0, "items")?;
-                    let mut options = sweep.options.clone();
-                    if let Some(prompt) = params.take_opt(1, "prompt")? {
-                        options.prompt = prompt;
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    let seed = seed.clone();
+                    async move {
+                        let items = params.take_seed(VecDeserializeSeed(seed), 0, "items")?;
+                        let mut options = sweep.options.clone();
+                        if let Some(prompt) = params.take_opt(1, "prompt")? {
+                            options.prompt = prompt;
+                        }
+                        if let Some(prompt_icon) = params.take_opt(2, "prompt_icon")? {
+                            options.prompt_icon = prompt_icon;
+                        }
+                        if let Some(keep_order) = params.take_opt(3, "keep_order")? {
+                            options.keep_order = keep_order;
+                        }
+                        if let Some(theme) = params.take_opt::<String>(4, "theme")? {
+                            options.theme = theme.parse()?;
+                        }
+                        if let Some(scorer) = params.take_opt::<Cow<'_, str>>(5, "scorer")? {
+                            scorer_by_name(&mut options.scorers, Some(scorer.as_ref()));
+                        }
+                        let uid = params.take(6, "uid")?;
+                        let result = sweep
+                            .quick_select(Some(options), uid, sweep.haystack_context.clone(), items)
+                            .await?;
+                        let result_value = serde_json::to_value(result)?;
+                        Ok(result_value)
+                    }
+                }
+            },
+        );
+
+        // scorer set
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "scorer_set",
+                "Switch scorer by name, next scorer is chosen if name is not specified",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("name", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let name: Option<String> = params.take_opt(1, "name")?;
+                        sweep.scorer_by_name(uid, name).await?;
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
+
+        // keep order
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "keep_order",
+                "Whether to keep order of the items, toggles if value is not specified",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("value", "bool"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let value: Option<bool> = params.take_opt(1, "value")?;
+                        sweep.keep_order(uid, value);
+                        Ok(Value::Null)
                     }
-                    if let Some(prompt_icon) = params.take_opt(2, "prompt_icon")? {
-                        options.prompt_icon = prompt_icon;
+                }
+            },
+        );
+
+        // theme set
+        peer.register_desc(
+            RpcMethodDesc::new("theme_set", "Set theme, same format as `--theme` argument")
+                .param_opt("uid", "WindowId")
+                .param("theme", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let theme: String = params.take(1, "theme")?;
+                        sweep.theme_set(uid, theme.parse()?);
+                        Ok(Value::Null)
                     }
-                    if let Some(keep_order) = params.take_opt(3, "keep_order")? {
-                        options.keep_order = keep_order;
+                }
+            },
+        );
+
+        // theme get
+        peer.register_desc(
+            RpcMethodDesc::new("theme_get", "Get current theme")
+                .param_opt("uid", "WindowId")
+                .result("Theme"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        Ok(theme_value(&sweep.theme_get(uid).await?))
                     }
-                    if let Some(theme) = params.take_opt::<String>(4, "theme")? {
-                        options.theme = theme.parse()?;
+                }
+            },
+        );
+
+        // layout set
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "layout_set",
+                "Set window layout, same format as `--layout` argument",
+            )
+            .param_opt("uid", "WindowId")
+            .param_opt("layout", "String"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let layout: Option<String> = params.take_opt(1, "layout")?;
+                        let layout = layout.map(|layout| layout.parse()).transpose()?;
+                        sweep.layout_set(uid, layout);
+                        Ok(Value::Null)
                     }
-                    if let Some(scorer) = params.take_opt::<Cow<'_, str>>(5, "scorer")? {
-                        scorer_by_name(&mut options.scorers, Some(scorer.as_ref()));
+                }
+            },
+        );
+
+        // action run
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "action_run",
+                "Run action by its name as listed in help, same as pressing bound key",
+            )
+            .param_opt("uid", "WindowId")
+            .param("name", "String")
+            .param_opt("args", "Any"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let name: String = params.take(1, "name")?;
+                        let args: Option<Value> = params.take_opt(2, "args")?;
+                        sweep.action_run(uid, name, args).await?;
+                        Ok(Value::Null)
                     }
-                    let uid = params.take(6, "uid")?;
-                    let result = sweep
-                        .quick_select(Some(options), uid, sweep.haystack_context.clone(), items)
-                        .await?;
-                    let result_value = serde_json::to_value(result)?;
-                    Ok(result_value)
                 }
-            }
-        });
+            },
+        );
+
+        // subscribe
+        peer.register_desc(
+            RpcMethodDesc::new(
+                "subscribe",
+                "Subscribe to the window state change notifications, empty list unsubscribes",
+            )
+            .param_opt("uid", "WindowId")
+            .param("events", "[String]"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let events: Vec<String> = params.take(1, "events")?;
+                        sweep.subscribe(uid, SweepSubscription::from_events(events)?);
+                        Ok(Value::Null)
+                    }
+                }
+            },
+        );
 
         // render_suppress
-        peer.register("render_suppress", {
-            let sweep = self.clone();
-            move |mut params: RpcParams| {
-                let sweep = sweep.clone();
-                async move {
-                    let uid = params.take_opt(0, "uid")?;
-                    let suppress = params.take(1, "suppress")?;
-                    sweep.render_suppress(uid, suppress);
-                    Ok(Value::Null)
+        peer.register_desc(
+            RpcMethodDesc::new("render_suppress", "Suppress rendering of the window")
+                .param_opt("uid", "WindowId")
+                .param("suppress", "bool"),
+            {
+                let sweep = self.clone();
+                move |mut params: RpcParams| {
+                    let sweep = sweep.clone();
+                    async move {
+                        let uid = params.take_opt(0, "uid")?;
+                        let suppress = params.take(1, "suppress")?;
+                        sweep.render_suppress(uid, suppress);
+                        Ok(Value::Null)
+                    }
                 }
-            }
-        });
+            },
+        );
 
         // setup
         setup(peer.clone());
@@ -840,7 +1613,7 @@ where
         let sweep = self.clone();
         let sweep_terminate = self.clone();
         async move {
-            let serve = peer.serve(read, write);
+            let serve = peer.serve_framed(read, write, framing);
             let events = async move {
                 // ready event
                 peer.notify_with_value(
@@ -850,12 +1623,79 @@ where
                             env!("CARGO_PKG_VERSION_MAJOR"),
                             env!("CARGO_PKG_VERSION_MINOR"),
                             env!("CARGO_PKG_VERSION_PATCH"),
-                        ]
+                        ],
+                        "capabilities": peer.capabilities(),
                     }),
                 )?;
 
-                while let Some(event) = sweep.next_event().await {
+                // change notifications waiting for throttle deadline
+                let mut changes: HashMap<(WindowId, &'static str), (Instant, Value)> =
+                    HashMap::new();
+                loop {
+                    let deadline = changes.values().map(|(deadline, _)| *deadline).min();
+                    let event = tokio::select! {
+                        event = sweep.next_event() => match event {
+                            Some(event) => event,
+                            None => break,
+                        },
+                        _ = time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                            let now = Instant::now();
+                            let ready: Vec<_> = changes
+                                .iter()
+                                .filter(|(_, (deadline, _))| *deadline <= now)
+                                .map(|(key, _)| key.clone())
+                                .collect();
+                            for key in ready {
+                                if let Some((_, args)) = changes.remove(&key) {
+                                    peer.notify_with_value(key.1, args)?;
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    // deadline is not moved by the consecutive changes, so constant
+                    // stream of changes does not delay notifications indefinitely
+                    let mut change = |uid: WindowId, method, args| {
+                        let key = (uid, method);
+                        let deadline = changes.get(&key).map_or_else(
+                            || Instant::now() + CHANGES_NOTIFY_THROTTLE,
+                            |(deadline, _)| *deadline,
+                        );
+                        changes.insert(key, (deadline, args));
+                    };
                     match event {
+                        SweepEvent::QueryChanged { uid, query } => change(
+                            uid.clone(),
+                            "query_changed",
+                            json!({"uid": uid, "query": query}),
+                        ),
+                        SweepEvent::CursorChanged { uid, item } => change(
+                            uid.clone(),
+                            "cursor_changed",
+                            json!({"uid": uid, "item": item}),
+                        ),
+                        SweepEvent::MarksChanged { uid, items } => change(
+                            uid.clone(),
+                            "marks_changed",
+                            json!({"uid": uid, "items": items}),
+                        ),
+                        SweepEvent::ScorerChanged { uid, name } => peer.notify_with_value(
+                            "scorer_changed",
+                            json!({"uid": uid, "scorer": name}),
+                        )?,
+                        SweepEvent::KeepOrderChanged { uid, keep_order } => peer
+                            .notify_with_value(
+                                "keep_order_changed",
+                                json!({"uid": uid, "keep_order": keep_order}),
+                            )?,
+                        SweepEvent::ThemeChanged { uid, theme } => peer.notify_with_value(
+                            "theme_changed",
+                            json!({"uid": uid, "theme": theme_value(&theme)}),
+                        )?,
+                        SweepEvent::LayoutChanged { uid, layout } => peer.notify_with_value(
+                            "layout_changed",
+                            json!({"uid": uid, "layout": layout.map(|layout| layout.to_string())}),
+                        )?,
                         SweepEvent::Bind { uid, tag, chord } => peer.notify_with_value(
                             "bind",
                             json!({"uid":  uid, "tag": tag, "key": chord}),
@@ -910,6 +1750,25 @@ where
     }
 }
 
+/// Theme representation used by RPC
+fn theme_value(theme: &Theme) -> Value {
+    json!({
+        "fg": theme.fg,
+        "bg": theme.bg,
+        "accent": theme.accent,
+        "file": theme.file,
+        "no_color": theme.no_color,
+    })
+}
+
+/// Tag argument of the `bind` method, either single tag or a sequence of tags
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindTags {
+    Single(String),
+    Chain(Vec<String>),
+}
+
 /// User bindable actions
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum SweepAction {
@@ -924,14 +1783,29 @@ enum SweepAction {
     MarkAll,
     Quit,
     Help,
+    History,
     ScorerNext,
     PreviewToggle,
+    PreviewPositionNext,
+    PreviewMaximize,
     PreviewLineNext,
     PreviewPageNext,
     PreviewLinePrev,
     PreviewPagePrev,
+    PreviewSearch,
+    PreviewSearchNext,
+    PreviewSearchPrev,
+    ListScrollLeft,
+    ListScrollRight,
+    ListWrapToggle,
+    ModeNormal,
+    ModeInsert,
     Input(InputAction),
     List(ListAction),
+    Chain {
+        actions: Vec<SweepAction>,
+        desc: String,
+    },
 }
 
 impl SweepAction {
@@ -1004,6 +1878,14 @@ impl SweepAction {
                 name: "sweep.help".to_owned(),
                 description: "Show help menu".to_owned(),
             },
+            History => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('r'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "sweep.history".to_owned(),
+                description: "Search query history".to_owned(),
+            },
             ScorerNext => ActionDesc {
                 chords: vec![KeyChord::from_iter([Key {
                     name: KeyName::Char('s'),
@@ -1020,6 +1902,22 @@ impl SweepAction {
                 name: "sweep.preview.toggle".to_owned(),
                 description: "Toggle preview for an item".to_owned(),
             },
+            PreviewPositionNext => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('p'),
+                    mode: KeyMod::ALT | KeyMod::SHIFT,
+                }])],
+                name: "sweep.preview.position.next".to_owned(),
+                description: "Move large preview to the next position".to_owned(),
+            },
+            PreviewMaximize => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('f'),
+                    mode: KeyMod::ALT | KeyMod::SHIFT,
+                }])],
+                name: "sweep.preview.maximize".to_owned(),
+                description: "Toggle maximized large preview".to_owned(),
+            },
             PreviewLineNext => ActionDesc {
                 chords: vec![KeyChord::from_iter([Key {
                     name: KeyName::Char('j'),
@@ -1052,8 +1950,91 @@ impl SweepAction {
                 name: "sweep.preview.page.prev".to_owned(),
                 description: "Scroll preview one page up".to_owned(),
             },
+            PreviewSearch => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('/'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "sweep.preview.search".to_owned(),
+                description: "Search within the large preview".to_owned(),
+            },
+            PreviewSearchNext => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('n'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "sweep.preview.search.next".to_owned(),
+                description: "Jump to the next match in the large preview".to_owned(),
+            },
+            PreviewSearchPrev => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('n'),
+                    mode: KeyMod::ALT | KeyMod::SHIFT,
+                }])],
+                name: "sweep.preview.search.prev".to_owned(),
+                description: "Jump to the previous match in the large preview".to_owned(),
+            },
+            ListScrollLeft => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Left,
+                    mode: KeyMod::ALT,
+                }])],
+                name: "list.scroll.left".to_owned(),
+                description: "Scroll list items to the left".to_owned(),
+            },
+            ListScrollRight => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Right,
+                    mode: KeyMod::ALT,
+                }])],
+                name: "list.scroll.right".to_owned(),
+                description: "Scroll list items to the right".to_owned(),
+            },
+            ListWrapToggle => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('w'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "list.wrap.toggle".to_owned(),
+                description: "Toggle wrapping of the item pointed by cursor".to_owned(),
+            },
+            ModeNormal => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Esc,
+                    mode: KeyMod::EMPTY,
+                }])],
+                name: "sweep.mode.normal".to_owned(),
+                description: "Switch to vi normal mode".to_owned(),
+            },
+            ModeInsert => ActionDesc {
+                chords: Vec::new(),
+                name: "sweep.mode.insert".to_owned(),
+                description: "Switch to vi insert mode".to_owned(),
+            },
             Input(input_action) => input_action.description(),
             List(list_action) => list_action.description(),
+            Chain { actions, desc } => {
+                let descs: Vec<_> = actions.iter().map(|action| action.description()).collect();
+                let name = descs
+                    .iter()
+                    .map(|desc| desc.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let description = if desc.is_empty() {
+                    descs
+                        .iter()
+                        .map(|desc| desc.description.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", then ")
+                } else {
+                    desc.clone()
+                };
+                ActionDesc {
+                    chords: Vec::new(),
+                    name,
+                    description,
+                }
+            }
         }
     }
 
@@ -1065,12 +2046,21 @@ impl SweepAction {
             MarkAll,
             Quit,
             Help,
+            History,
             ScorerNext,
             PreviewToggle,
+            PreviewPositionNext,
+            PreviewMaximize,
             PreviewLineNext,
             PreviewPageNext,
             PreviewLinePrev,
             PreviewPagePrev,
+            PreviewSearch,
+            PreviewSearchNext,
+            PreviewSearchPrev,
+            ListScrollLeft,
+            ListScrollRight,
+            ListWrapToggle,
         ]
         .into_iter()
         .chain(InputAction::all().map(Input))
@@ -1078,6 +2068,186 @@ impl SweepAction {
     }
 }
 
+/// Default key bindings of the vi normal mode, as `(chord, actions, description)`
+fn vi_normal_bindings() -> Vec<(KeyChord, Vec<SweepAction>, String)> {
+    use SweepAction::*;
+
+    fn chain(actions: impl IntoIterator<Item = SweepAction>, desc: &str) -> SweepAction {
+        Chain {
+            actions: actions.into_iter().collect(),
+            desc: desc.to_owned(),
+        }
+    }
+
+    let bindings = [
+        ("j", List(ListAction::ItemNext)),
+        ("k", List(ListAction::ItemPrev)),
+        ("ctrl+f", List(ListAction::PageNext)),
+        ("ctrl+b", List(ListAction::PagePrev)),
+        ("g g", List(ListAction::Home)),
+        ("G", List(ListAction::End)),
+        ("shift+g", List(ListAction::End)),
+        ("h", Input(InputAction::CursorBackward)),
+        ("l", Input(InputAction::CursorForward)),
+        ("w", Input(InputAction::CursorNextWord)),
+        ("b", Input(InputAction::CursorPrevWord)),
+        ("0", Input(InputAction::CursorStart)),
+        ("$", Input(InputAction::CursorEnd)),
+        ("x", Input(InputAction::DeleteForward)),
+        ("D", Input(InputAction::DeleteEnd)),
+        ("d w", Input(InputAction::DeleteNextWord)),
+        ("d b", Input(InputAction::DeletePrevWord)),
+        (
+            "d d",
+            chain(
+                [
+                    Input(InputAction::CursorEnd),
+                    Input(InputAction::DeleteStart),
+                ],
+                "Delete the whole query",
+            ),
+        ),
+        (
+            "c w",
+            chain(
+                [Input(InputAction::DeleteNextWord), ModeInsert],
+                "Change word under the cursor",
+            ),
+        ),
+        (
+            "c c",
+            chain(
+                [
+                    Input(InputAction::CursorEnd),
+                    Input(InputAction::DeleteStart),
+                    ModeInsert,
+                ],
+                "Change the whole query",
+            ),
+        ),
+        ("p", Input(InputAction::Yank)),
+        ("u", Input(InputAction::Undo)),
+        ("ctrl+r", Input(InputAction::Redo)),
+        ("/", ModeInsert),
+        ("i", ModeInsert),
+        (
+            "a",
+            chain(
+                [Input(InputAction::CursorForward), ModeInsert],
+                "Insert after the cursor",
+            ),
+        ),
+        (
+            "I",
+            chain(
+                [Input(InputAction::CursorStart), ModeInsert],
+                "Insert at the start of the query",
+            ),
+        ),
+        (
+            "A",
+            chain(
+                [Input(InputAction::CursorEnd), ModeInsert],
+                "Insert at the end of the query",
+            ),
+        ),
+    ];
+    bindings
+        .into_iter()
+        .map(|(chord, action)| {
+            let chord = key_chord_parse(chord).expect("invalid vi key chord");
+            match action {
+                Chain { actions, desc } => (chord, actions, desc),
+                action => (chord, vec![action], String::new()),
+            }
+        })
+        .collect()
+}
+
+/// Parse key chord, single character chords are taken as is, since chord
+/// parser does not preserve case and does not accept punctuation
+fn key_chord_parse(chord: &str) -> Result<KeyChord, Error> {
+    match chord.chars().collect::<Vec<_>>().as_slice() {
+        [c] if !c.is_whitespace() => Ok(KeyChord::from_iter([Key::new(
+            KeyName::Char(*c),
+            KeyMod::EMPTY,
+        )])),
+        _ => Ok(chord.parse()?),
+    }
+}
+
+/// Parse key chord of the binding, normal mode keys are mostly plain characters
+fn key_chord_parse_mode(mode: ViMode, chord: &str) -> Result<KeyChord, Error> {
+    match mode {
+        ViMode::Insert => Ok(chord.parse()?),
+        ViMode::Normal => key_chord_parse(chord),
+    }
+}
+
+/// Search within the large preview
+struct PreviewSearch {
+    // search query
+    input: Input,
+    // scorers used to match preview rows, the first one is active
+    scorers: VecDeque<ScorerBuilder>,
+    // whether keys are sent to the search input
+    editing: bool,
+    // row of the current match
+    current: Option<usize>,
+}
+
+impl PreviewSearch {
+    fn new(theme: Theme) -> Self {
+        Self {
+            input: Input::new(theme),
+            scorers: VecDeque::from([substr_scorer(), regex_scorer()]),
+            editing: true,
+            current: None,
+        }
+    }
+
+    /// Scorer for the current query, `None` if query is empty
+    fn scorer(&self) -> Option<Arc<dyn Scorer>> {
+        let query: String = self.input.get().collect();
+        if query.is_empty() {
+            return None;
+        }
+        Some((self.scorers.front()?)(&query))
+    }
+}
+
+/// Editing mode of the window with vi key bindings
+///
+/// Also selects key map of a binding, insert mode key map is the default one,
+/// which is used when vi key bindings are disabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViMode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+impl std::str::FromStr for ViMode {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "insert" => Ok(ViMode::Insert),
+            "normal" => Ok(ViMode::Normal),
+            _ => anyhow::bail!("invalid vi mode: {}", string),
+        }
+    }
+}
+
+impl fmt::Display for ViMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViMode::Insert => f.write_str("insert"),
+            ViMode::Normal => f.write_str("normal"),
+        }
+    }
+}
+
 type SweepEventHandler<H> = Arc<dyn Fn(SweepEvent<H>) -> Result<WindowAction, Error> + Send + Sync>;
 
 /// Object representing current state of the sweep worker
@@ -1100,12 +2270,18 @@ struct SweepWindow<H: Haystack> {
     footer: Option<Arc<dyn View>>,
     // current state of the key chord
     key_map_state: Vec<Key>,
-    // user action executed on backspace when input is empty
-    key_empty_backspace: Option<Arc<str>>,
+    // action executed on backspace when input is empty
+    key_empty_backspace: Option<SweepAction>,
     // action key map
     key_map: KeyMap<SweepAction>,
     // action name to sweep action
     key_actions: HashMap<String, SweepAction>,
+    // current vi mode, None if vi key bindings are disabled
+    vi_mode: Option<ViMode>,
+    // normal mode key map
+    vi_key_map: KeyMap<SweepAction>,
+    // current state of the normal mode key chord
+    vi_key_map_state: Vec<Key>,
     // theme
     theme: Theme,
     // input widget
@@ -1114,6 +2290,8 @@ struct SweepWindow<H: Haystack> {
     list: List<SweepItems<H>>,
     // this sweep state is used to render help
     is_help: bool,
+    // history of submitted queries
+    history: QueryHistory,
     // marked items (multi-select)
     marked: Arc<RwLock<MarkedItems<H>>>,
     // ranker
@@ -1122,14 +2300,57 @@ struct SweepWindow<H: Haystack> {
     haystack: Vec<H>,
     // haystack keymap
     haystack_keymap: KeyMapHandler<SweepAction>,
+    // haystack index of the items with a key
+    haystack_keys: HashMap<String, usize>,
+    // haystack index the cursor is restored to once the ranker has
+    // processed the rebuilt haystack
+    cursor_pin: Option<(Arc<AtomicBool>, Option<usize>)>,
     // haystack context
     haystack_context: H::Context,
     // cached large preview of the current item
     preview_large: Option<SweepPreview<H::PreviewLarge>>,
+    // placement of the large preview
+    preview_layout: Option<PreviewLayout>,
+    // whether large preview occupies the whole window
+    preview_maximized: bool,
+    // area occupied by the large preview during last render
+    preview_area: Arc<RwLock<Option<(Position, Size)>>>,
+    // search within the large preview
+    preview_search: Option<PreviewSearch>,
+    // horizontal scroll offset of the list items
+    list_offset: usize,
+    // cells cut off on the right side of the visible items during last render
+    list_overflow: HaystackViewOverflow,
+    // haystack index of the item that is wrapped instead of truncated
+    list_wrapped: Option<usize>,
+    // placement of the prompt and direction of the list during last render
+    orientation: WindowOrientation,
+    // position of the large preview derived from the layout during last render
+    preview_position: PreviewPosition,
     // None - rendering is not suppressed
     // Some(false) - resumed but not synchronized
     // Some(true) - resumed and should be converted to None
     render_suppress_sync: Option<Arc<AtomicBool>>,
+    // policy applied to the marked items
+    marks_policy: MarksPolicy,
+    // whether to keep order of the items
+    keep_order: bool,
+    // layout overriding the one specified by options
+    layout: Option<WindowLayout>,
+    // state changes reported to the host
+    subscription: SweepSubscription,
+    // last reported state
+    reported: WindowReported,
+}
+
+/// State of the window last reported with change events
+#[derive(Debug, Default, PartialEq, Eq)]
+struct WindowReported {
+    query: String,
+    // haystack index of the current item
+    cursor: Option<usize>,
+    // generation of the marked items
+    marks: usize,
 }
 
 impl<H> SweepWindow<H>
@@ -1147,8 +2368,7 @@ where
     ) -> Result<Self, Error> {
         let ranker = Ranker::new(ranker_thread)?;
         ranker.scorer_set(options.scorers[0].clone());
-        ranker.keep_order(Some(options.keep_order));
-        Ok(SweepWindow::new(
+        let mut window = SweepWindow::new(
             window_uid,
             options.prompt,
             options.prompt_icon,
@@ -1160,7 +2380,20 @@ where
             requests,
             event_handler,
             false,
-        ))
+        );
+        window.keep_order_set(Some(options.keep_order));
+        window.marks_policy_set(options.marks_policy);
+        if options.vi_mode {
+            window.vi_enable();
+        }
+        window.preview_layout = options.preview_layout;
+        for binding in options.bindings {
+            window.bind(binding.mode, binding.chord, binding.tags, String::new());
+        }
+        if let Some(path) = options.history {
+            window.history_set(QueryHistory::load(path)?);
+        }
+        Ok(window)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -1199,6 +2432,9 @@ where
             key_empty_backspace: None,
             key_map,
             key_actions,
+            vi_mode: None,
+            vi_key_map: KeyMap::new(),
+            vi_key_map_state: Vec::new(),
             theme: theme.clone(),
             input: Input::new(theme.clone()),
             list: List::new(
@@ -1209,10 +2445,27 @@ where
             ranker,
             haystack: Vec::new(),
             haystack_keymap: KeyMapHandler::new(),
+            haystack_keys: HashMap::new(),
+            cursor_pin: None,
             haystack_context,
             preview_large: None,
+            preview_layout: None,
+            preview_maximized: false,
+            preview_area: Default::default(),
+            preview_search: None,
+            list_offset: 0,
+            list_overflow: HaystackViewOverflow::default(),
+            list_wrapped: None,
+            orientation: WindowOrientation::default(),
+            preview_position: PreviewPosition::default(),
             render_suppress_sync: None,
+            marks_policy: MarksPolicy::default(),
+            keep_order: false,
+            layout: None,
+            subscription: SweepSubscription::default(),
+            reported: WindowReported::default(),
             is_help,
+            history: QueryHistory::default(),
         }
     }
 
@@ -1220,56 +2473,475 @@ where
         self.ranker
             .haystack_extend(&self.haystack_context, &haystack);
         let index_offset = self.haystack.len();
-        haystack.iter().enumerate().for_each(|(index, haystack)| {
-            let Some(chord) = haystack.hotkey() else {
-                return;
-            };
-            self.haystack_keymap.register(
-                chord.as_ref(),
-                SweepAction::SelectByIndex(index_offset + index),
-            );
-        });
         self.haystack.extend(haystack);
+        (index_offset..self.haystack.len()).for_each(|index| self.haystack_register(index));
     }
 
-    // get currently pointed item
-    fn current(&self) -> Option<SweepItem<'_, H>> {
-        let sweep_items = self.list.items();
-        self.list.current().and_then(|id| {
-            let score = sweep_items.ranked_items.get(id.rank_index)?;
-            let haystack = self.haystack.get(id.haystack_index)?;
-            Some(SweepItem {
-                id,
-                score,
-                haystack,
-            })
-        })
+    // register hotkey and key of the haystack item
+    fn haystack_register(&mut self, index: usize) {
+        let Some(haystack) = self.haystack.get(index) else {
+            return;
+        };
+        if let Some(key) = haystack.key() {
+            self.haystack_keys.insert(key.to_owned(), index);
+        }
+        if let Some(chord) = haystack.hotkey() {
+            self.haystack_keymap
+                .register(chord.as_ref(), SweepAction::SelectByIndex(index));
+        }
     }
 
-    // get preview of the currently pointed haystack item
-    fn current_preview(&self) -> Option<H::Preview> {
-        let item = self.current()?;
-        item.haystack
-            .preview(&self.haystack_context, item.score.positions, &self.theme)
+    // send whole haystack to the ranker and re-register hotkeys and keys,
+    // used when items are removed
+    fn haystack_rebuild(&mut self) {
+        self.ranker
+            .haystack_set(&self.haystack_context, &self.haystack);
+        self.haystack_keymap.clear();
+        self.haystack_keys.clear();
+        (0..self.haystack.len()).for_each(|index| self.haystack_register(index));
+        self.list_wrapped = None;
+        self.preview_large = None;
+    }
+
+    // remember haystack index of the current item, so the cursor stays on it
+    // after the haystack is rebuilt, `remap` maps old indices to the new ones
+    fn cursor_pin(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        let index = match self.cursor_pin.take() {
+            Some((_, index)) => index,
+            None if self.list.cursor() == 0 => None,
+            None => self.list.current().map(|id| id.haystack_index),
+        };
+        self.cursor_pin = Some((self.ranker.sync(), index.and_then(remap)));
+    }
+
+    // replace items with the same key and append the rest
+    fn haystack_upsert(&mut self, items: Vec<H>) {
+        let mut appended: Vec<H> = Vec::new();
+        let mut replaced = Vec::new();
+        for item in items {
+            let index = item
+                .key()
+                .and_then(|key| self.haystack_keys.get(key))
+                .copied();
+            match index {
+                Some(index) if index < self.haystack.len() => {
+                    self.marked
+                        .with_mut(|marked| marked.update(index, item.clone()));
+                    self.haystack[index] = item;
+                    replaced.push(index);
+                }
+                // duplicate key among the appended items
+                Some(index) => appended[index - self.haystack.len()] = item,
+                None => {
+                    if let Some(key) = item.key() {
+                        let index = self.haystack.len() + appended.len();
+                        self.haystack_keys.insert(key.to_owned(), index);
+                    }
+                    appended.push(item);
+                }
+            }
+        }
+        if !replaced.is_empty() {
+            // only replaced items are sent to the ranker, indices do not change
+            self.cursor_pin(Some);
+            self.ranker.haystack_update(
+                &self.haystack_context,
+                replaced
+                    .iter()
+                    .map(|index| (*index, &self.haystack[*index])),
+            );
+            replaced
+                .into_iter()
+                .for_each(|index| self.haystack_register(index));
+            self.list_wrapped = None;
+            self.preview_large = None;
+        }
+        if !appended.is_empty() {
+            self.haystack_extend(appended);
+        }
+    }
+
+    // remove items by their keys
+    fn haystack_remove(&mut self, keys: Vec<String>) {
+        let removed: HashSet<usize> = keys
+            .iter()
+            .filter_map(|key| self.haystack_keys.get(key).copied())
+            .collect();
+        if removed.is_empty() {
+            return;
+        }
+        let mut remap = Vec::with_capacity(self.haystack.len());
+        let mut offset = 0;
+        for index in 0..self.haystack.len() {
+            if removed.contains(&index) {
+                remap.push(None);
+            } else {
+                remap.push(Some(offset));
+                offset += 1;
+            }
+        }
+        let remap = |index: usize| remap.get(index).copied().flatten();
+        self.marked.with_mut(|marked| marked.remap(remap));
+        self.cursor_pin(remap);
+        let mut index = 0;
+        self.haystack.retain(|_| {
+            index += 1;
+            !removed.contains(&(index - 1))
+        });
+        self.haystack_rebuild();
+    }
+
+    // get currently pointed item
+    fn current(&self) -> Option<SweepItem<'_, H>> {
+        let sweep_items = self.list.items();
+        self.list.current().and_then(|id| {
+            let score = sweep_items.ranked_items.get(id.rank_index)?;
+            let haystack = self.haystack.get(id.haystack_index)?;
+            Some(SweepItem {
+                id,
+                score,
+                haystack,
+            })
+        })
+    }
+
+    // get preview of the currently pointed haystack item
+    fn current_preview(&self) -> Option<H::Preview> {
+        let item = self.current()?;
+        item.haystack
+            .preview(&self.haystack_context, item.score.positions, &self.theme)
     }
 
     // get large preview for currently pointed haystack item
     fn current_preview_large(&mut self) -> Option<SweepPreview<H::PreviewLarge>> {
         let item = self.current()?;
-        if !matches!(&self.preview_large, Some(preview) if preview.id == item.id) {
-            let preview = item.haystack.preview_large(
+        let area = self.preview_area.with(|area| *area);
+        let query: String = self.input.get().collect();
+        if !matches!(
+            &self.preview_large,
+            Some(preview) if preview.id == item.id && preview.area == area && preview.query == query
+        ) {
+            let env = PreviewEnv {
+                area,
+                query: query.clone(),
+                index: item.id.haystack_index,
+            };
+            let preview = item.haystack.preview_large_with_env(
                 &self.haystack_context,
                 item.score.positions,
                 &self.theme,
+                &env,
             )?;
-            self.preview_large = Some(SweepPreview::new(item.id, self.theme.clone(), preview));
+            self.preview_large = Some(SweepPreview::new(
+                item.id,
+                self.theme.clone(),
+                preview,
+                area,
+                query,
+                self.preview_area.clone(),
+                self.term_waker.clone(),
+            ));
+            self.preview_search_apply();
+
+            // prefetch previews of the neighbouring items
+            let cursor = self.list.cursor();
+            let items = self.list.items();
+            for index in [cursor + 1, cursor.wrapping_sub(1)] {
+                let Some(id) = items.get(index) else {
+                    continue;
+                };
+                let Some(haystack) = self.haystack.get(id.haystack_index) else {
+                    continue;
+                };
+                let env = PreviewEnv {
+                    area,
+                    query: self.input.get().collect(),
+                    index: id.haystack_index,
+                };
+                haystack.preview_prefetch(&self.haystack_context, &env);
+            }
         }
         self.preview_large.clone()
     }
 
+    // enable vi-style modal key bindings
+    fn vi_enable(&mut self) {
+        for action in [SweepAction::ModeNormal, SweepAction::ModeInsert] {
+            let desc = action.description();
+            for chord in desc.chords {
+                self.key_map.register(chord, action.clone());
+            }
+            self.key_actions.insert(desc.name, action);
+        }
+        for (chord, actions, desc) in vi_normal_bindings() {
+            let tags = actions
+                .iter()
+                .map(|action| {
+                    let name = action.description().name;
+                    self.key_actions
+                        .entry(name.clone())
+                        .or_insert_with(|| action.clone());
+                    name
+                })
+                .collect();
+            self.bind(ViMode::Normal, chord, tags, desc);
+        }
+        self.vi_mode = Some(ViMode::Insert);
+    }
+
+    /// Find action by its name, as listed in help
+    fn action_by_name(&self, name: &str, args: Option<Value>) -> Result<SweepAction, Error> {
+        if let Some(action) = self.key_actions.get(name) {
+            return Ok(action.clone());
+        }
+        if name == "input.insert.char" {
+            let Some(Value::String(text)) = args else {
+                anyhow::bail!("{} expects string argument", name);
+            };
+            return Ok(SweepAction::Chain {
+                actions: text
+                    .chars()
+                    .map(|c| SweepAction::Input(InputAction::Insert(c)))
+                    .collect(),
+                desc: String::new(),
+            });
+        }
+        if let Some(index) = name
+            .strip_prefix("sweep.select.")
+            .and_then(|index| index.parse().ok())
+        {
+            return Ok(SweepAction::SelectByIndex(index));
+        }
+        anyhow::bail!("unknown action: {}", name)
+    }
+
+    // update query history
+    fn history_set(&mut self, history: QueryHistory) {
+        self.input.history_set(history.entries().to_vec());
+        self.history = history;
+    }
+
+    // record current query in the history
+    fn history_push(&mut self) {
+        if self.is_help {
+            return;
+        }
+        let query: String = self.input.get().collect();
+        if let Err(error) = self.history.push(&query) {
+            tracing::error!(
+                ?error,
+                "[SweepWindow.history_push] failed to update history"
+            );
+        }
+        self.input.history_set(self.history.entries().to_vec());
+    }
+
+    /// Apply current preview search query to the large preview
+    fn preview_search_apply(&mut self) {
+        let Some(preview) = &self.preview_large else {
+            return;
+        };
+        let scorer = self.preview_search.as_mut().and_then(|search| {
+            search.current = None;
+            search.scorer()
+        });
+        preview
+            .preview
+            .search_set(scorer.map(|scorer| (scorer, self.theme.list_highlight)));
+    }
+
+    /// Scroll large preview to the next or previous preview search match
+    fn preview_search_jump(&mut self, forward: bool) {
+        let (Some(preview), Some(search)) = (&self.preview_large, &mut self.preview_search) else {
+            return;
+        };
+        let matches = preview.preview.search_matches();
+        let mut offset = preview.preview.preview_layout().position();
+        let row = if forward {
+            matches
+                .iter()
+                .find(|row| match search.current {
+                    Some(current) => **row > current,
+                    None => **row >= offset.row,
+                })
+                .or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|row| match search.current {
+                    Some(current) => **row < current,
+                    None => **row < offset.row,
+                })
+                .or(matches.last())
+        };
+        let Some(row) = row.copied() else {
+            return;
+        };
+        search.current = Some(row);
+        offset.row = row.saturating_sub(preview.height.load(Ordering::Relaxed) / 2);
+        preview.preview.set_offset(offset);
+    }
+
+    /// Handle key while preview search input is focused
+    fn preview_search_handle_key(&mut self, key: Key) -> Result<WindowAction, Error> {
+        let Some(action) = self
+            .key_map
+            .lookup_state(&mut self.key_map_state, key)
+            .cloned()
+        else {
+            if let Key {
+                name: KeyName::Char(c),
+                mode: KeyMod::EMPTY,
+            } = key
+                && let Some(search) = &mut self.preview_search
+            {
+                search.input.apply(&InputAction::Insert(c));
+                self.preview_search_apply();
+            }
+            return Ok(WindowAction::Nothing);
+        };
+        let Some(search) = &mut self.preview_search else {
+            return Ok(WindowAction::Nothing);
+        };
+        match action {
+            SweepAction::Input(action) => {
+                search.input.apply(&action);
+                self.preview_search_apply();
+            }
+            SweepAction::ScorerNext => {
+                search.scorers.rotate_left(1);
+                self.preview_search_apply();
+            }
+            SweepAction::Select => {
+                search.editing = false;
+                if search.scorer().is_none() {
+                    self.preview_search = None;
+                }
+                self.preview_search_jump(true);
+            }
+            SweepAction::Quit | SweepAction::ModeNormal => {
+                self.preview_search = None;
+                self.preview_search_apply();
+            }
+            SweepAction::List(ListAction::ItemNext) => self.preview_search_jump(true),
+            SweepAction::List(ListAction::ItemPrev) => self.preview_search_jump(false),
+            SweepAction::PreviewSearchNext
+            | SweepAction::PreviewSearchPrev
+            | SweepAction::PreviewLineNext
+            | SweepAction::PreviewLinePrev
+            | SweepAction::PreviewPageNext
+            | SweepAction::PreviewPagePrev
+            | SweepAction::PreviewMaximize
+            | SweepAction::PreviewPositionNext => return self.handle_action(&action),
+            _ => {}
+        }
+        Ok(WindowAction::Nothing)
+    }
+
+    /// Find and run action bound to the key
+    fn key_dispatch(&mut self, key: Key) -> Result<WindowAction, Error> {
+        if self
+            .preview_search
+            .as_ref()
+            .is_some_and(|search| search.editing)
+        {
+            return self.preview_search_handle_key(key);
+        }
+        if self.vi_mode == Some(ViMode::Normal) {
+            self.vi_key_map_state.push(key);
+            match self.vi_key_map.lookup(&self.vi_key_map_state) {
+                KeyMapResult::Continue => return Ok(WindowAction::Nothing),
+                KeyMapResult::Success(action) => {
+                    let action = action.clone();
+                    self.vi_key_map_state.clear();
+                    tracing::debug!(?action, "[SweepState.handle_key] normal mode");
+                    return self.handle_action(&self.list_action_orient(action));
+                }
+                KeyMapResult::Failure => {
+                    // broken chord, retry with the last key alone
+                    if self.vi_key_map_state.len() > 1 {
+                        self.vi_key_map_state.clear();
+                        return self.key_dispatch(key);
+                    }
+                    self.vi_key_map_state.clear();
+                    // plain chars are not inserted in normal mode, everything
+                    // else falls back to the insert mode key map
+                    if matches!(key.name, KeyName::Char(_)) && key.mode == KeyMod::EMPTY {
+                        return Ok(WindowAction::Nothing);
+                    }
+                }
+            }
+        }
+        let is_first_key = self.key_map_state.is_empty();
+        if let Some(action) = self.key_map.lookup_state(&mut self.key_map_state, key) {
+            tracing::debug!(?action, "[SweepState.handle_key]");
+            // do not generate Backspace, when input is not empty
+            let backspace = Key::new(KeyName::Backspace, KeyMod::EMPTY);
+            if is_first_key && key == backspace && self.input.get().count() == 0 {
+                if let Some(action) = self.key_empty_backspace.clone() {
+                    return self.handle_action(&self.list_action_orient(action));
+                }
+            } else {
+                let action = action.clone();
+                return self.handle_action(&self.list_action_orient(action));
+            }
+        } else if let Some(action) = self.haystack_keymap.handle(key).cloned() {
+            return self.handle_action(&self.list_action_orient(action));
+        } else if let Key {
+            name: KeyName::Char(c),
+            mode: KeyMod::EMPTY,
+        } = key
+        {
+            // send plain chars to the input
+            self.input.apply(&InputAction::Insert(c));
+            self.ranker_trigger();
+        }
+        Ok(WindowAction::Nothing)
+    }
+
+    /// Swap next and previous list actions triggered by keys for the reversed
+    /// list, so they move cursor in the visual direction
+    fn list_action_orient(&self, action: SweepAction) -> SweepAction {
+        use ListAction::*;
+        if !self.orientation.list_reverse {
+            return action;
+        }
+        match action {
+            SweepAction::List(action) => SweepAction::List(match action {
+                ItemNext => ItemPrev,
+                ItemPrev => ItemNext,
+                PageNext => PagePrev,
+                PagePrev => PageNext,
+                action => action,
+            }),
+            SweepAction::Chain { actions, desc } => SweepAction::Chain {
+                actions: actions
+                    .into_iter()
+                    .map(|action| self.list_action_orient(action))
+                    .collect(),
+                desc,
+            },
+            action => action,
+        }
+    }
+
+    /// Layout of the large preview, derived from the window layout if not set
+    fn preview_layout_current(&self) -> PreviewLayout {
+        self.preview_layout
+            .clone()
+            .unwrap_or_else(|| PreviewLayout {
+                position: self.preview_position,
+                ..PreviewLayout::default()
+            })
+    }
+
     // update theme
     fn theme_set(&mut self, theme: Theme) {
         self.input.theme_set(theme.clone());
+        if let Some(search) = &mut self.preview_search {
+            search.input.theme_set(theme.clone());
+        }
         self.list.theme_set(theme.clone());
         self.theme = theme;
     }
@@ -1281,13 +2953,108 @@ where
         self.ranker.needle_set(self.input.get().collect());
     }
 
+    /// Pass event to the event handler, resulting window action is ignored
+    ///
+    /// State change events are dropped unless they are subscribed
+    fn event_notify(&self, event: SweepEvent<H>) {
+        if !self.subscription.contains(&event) {
+            return;
+        }
+        if let Err(error) = (self.event_handler)(event) {
+            tracing::error!(?error, "[SweepWindow] failed to handle event");
+        }
+    }
+
+    /// Set scorer used for ranking and notify about the change
+    fn scorer_set(&mut self, scorer: ScorerBuilder) {
+        let name = scorer("").name().to_owned();
+        self.ranker.scorer_set(scorer);
+        self.event_notify(SweepEvent::ScorerChanged {
+            uid: self.window_uid.clone(),
+            name,
+        });
+    }
+
+    /// Set policy applied to the marked items
+    fn marks_policy_set(&mut self, policy: MarksPolicy) {
+        self.marked.with_mut(|marked| marked.limit = policy.limit);
+        self.marks_policy = policy;
+    }
+
+    /// Toggle marks of the items by their haystack indices, unknown indices are ignored
+    fn marks_toggle(&mut self, indices: impl IntoIterator<Item = usize>) {
+        self.marked.with_mut(|marked| {
+            for index in indices {
+                if let Some(haystack) = self.haystack.get(index) {
+                    marked.toggle(index, haystack.clone());
+                }
+            }
+        })
+    }
+
+    /// Set whether to keep order of the items, `None` toggles current value
+    fn keep_order_set(&mut self, toggle: Option<bool>) {
+        self.keep_order = toggle.unwrap_or(!self.keep_order);
+        self.ranker.keep_order(Some(self.keep_order));
+    }
+
+    /// Current state of the window as tracked by change events
+    fn reported_state(&self) -> WindowReported {
+        WindowReported {
+            query: self.input.get().collect(),
+            cursor: self.current().map(|item| item.id.haystack_index),
+            marks: self.marked.with(|marked| marked.generation()),
+        }
+    }
+
+    /// Generate subscribed change events
+    ///
+    /// Called after processing requests and key/mouse events, independently of
+    /// rendering, so events are generated even if rendering is suppressed
+    fn changes_report(&mut self) {
+        if self.subscription.is_empty() {
+            return;
+        }
+        // cursor depends on the latest ranker result
+        self.ranker_refresh();
+        let state = self.reported_state();
+        let reported = mem::replace(&mut self.reported, state);
+        let mut events = Vec::new();
+        if self.subscription.query && reported.query != self.reported.query {
+            events.push(SweepEvent::QueryChanged {
+                uid: self.window_uid.clone(),
+                query: self.reported.query.clone(),
+            });
+        }
+        if self.subscription.cursor && reported.cursor != self.reported.cursor {
+            events.push(SweepEvent::CursorChanged {
+                uid: self.window_uid.clone(),
+                item: self.current().map(|item| item.haystack.clone()),
+            });
+        }
+        if self.subscription.marks && reported.marks != self.reported.marks {
+            events.push(SweepEvent::MarksChanged {
+                uid: self.window_uid.clone(),
+                items: self.marked.with(|marked| marked.items().cloned().collect()),
+            });
+        }
+        for event in events {
+            self.event_notify(event);
+        }
+    }
+
     /// Retrieve latest ranker result and update list view
     fn ranker_refresh(&mut self) -> Arc<RankedItems> {
         // check if list view needs to be updated
         let ranker_result = self.ranker.result();
         if self.list.items().generation() != ranker_result.generation() {
             // find cursor position of currently pointed item in the new result
-            let cursor = if self.list.cursor() == 0 {
+            let pinned = self
+                .cursor_pin
+                .take_if(|(synced, _)| synced.load(Ordering::Acquire));
+            let cursor = if let Some((_, index)) = pinned {
+                index.and_then(|index| ranker_result.find_match_index(index))
+            } else if self.list.cursor() == 0 {
                 None
             } else {
                 self.current()
@@ -1306,6 +3073,68 @@ where
         ranker_result
     }
 
+    /// Bind chord to a sequence of actions, each tag is either a name
+    /// of the action or a user tag. Empty sequence removes the binding.
+    /// Mode selects key map, normal mode key map is used by vi key bindings.
+    fn bind(&mut self, mode: ViMode, chord: KeyChord, tags: Vec<String>, desc: String) {
+        let mut tags: Vec<_> = tags.into_iter().filter(|tag| !tag.is_empty()).collect();
+        let action = match tags.len() {
+            0 => None,
+            1 => {
+                let tag = tags.remove(0);
+                let action = self
+                    .key_actions
+                    .entry(tag.clone())
+                    .or_insert_with(|| SweepAction::User {
+                        chord: chord.clone(),
+                        tag: tag.into(),
+                        desc,
+                    })
+                    .clone();
+                Some(action)
+            }
+            _ => {
+                let actions = tags
+                    .into_iter()
+                    .map(|tag| match self.key_actions.get(&tag) {
+                        Some(action) => action.clone(),
+                        None => SweepAction::User {
+                            chord: chord.clone(),
+                            tag: tag.into(),
+                            desc: String::new(),
+                        },
+                    })
+                    .collect();
+                let action = SweepAction::Chain { actions, desc };
+                // register composite so it can be picked from the help window
+                self.key_actions
+                    .insert(action.description().name, action.clone());
+                Some(action)
+            }
+        };
+        let key_map = match mode {
+            ViMode::Insert => &mut self.key_map,
+            ViMode::Normal => &mut self.vi_key_map,
+        };
+        match *chord.keys() {
+            [
+                Key {
+                    name: KeyName::Backspace,
+                    mode: KeyMod::EMPTY,
+                },
+            ] if mode == ViMode::Insert => self.key_empty_backspace = action,
+            _ => {
+                // empty user action means unbind
+                let action = action.unwrap_or_else(|| SweepAction::User {
+                    chord: KeyChord::new(Vec::new()),
+                    tag: Default::default(),
+                    desc: String::new(),
+                });
+                key_map.register(chord.as_ref(), action);
+            }
+        }
+    }
+
     fn handle_action(&mut self, action: &SweepAction) -> Result<WindowAction, Error> {
         match action {
             SweepAction::Input(action) => {
@@ -1313,6 +3142,14 @@ where
                 self.ranker_trigger();
             }
             SweepAction::List(action) => self.list.apply(action),
+            SweepAction::Chain { actions, .. } => {
+                for action in actions {
+                    let window_action = self.handle_action(action)?;
+                    if !matches!(window_action, WindowAction::Nothing) {
+                        return Ok(window_action);
+                    }
+                }
+            }
             SweepAction::User { tag, chord, .. } => {
                 if !tag.is_empty() {
                     return (self.event_handler)(SweepEvent::Bind {
@@ -1323,7 +3160,18 @@ where
                 }
             }
             SweepAction::Quit => return Ok(WindowAction::Close { uid: None }),
+            SweepAction::ModeNormal | SweepAction::ModeInsert => {
+                if self.vi_mode.is_some() {
+                    self.vi_mode = Some(if matches!(action, SweepAction::ModeNormal) {
+                        ViMode::Normal
+                    } else {
+                        ViMode::Insert
+                    });
+                    self.vi_key_map_state.clear();
+                }
+            }
             SweepAction::Select => {
+                self.history_push();
                 let selected: Vec<H> = if self.marked.with(|marked| !marked.is_empty()) {
                     self.marked.with_mut(|marked| marked.take()).collect()
                 } else {
@@ -1338,17 +3186,19 @@ where
                 });
             }
             SweepAction::SelectByIndex(index) => {
-                if let Some(item) = self.haystack.get(*index) {
+                if let Some(item) = self.haystack.get(*index).cloned() {
+                    self.history_push();
                     return (self.event_handler)(SweepEvent::Select {
                         uid: self.window_uid.clone(),
-                        items: vec![item.clone()],
+                        items: vec![item],
                     });
                 }
             }
             SweepAction::Mark => {
                 if let Some(item) = self.current() {
-                    self.marked
-                        .with_mut(|marked| marked.toggle(item.id, item.haystack.clone()));
+                    self.marked.with_mut(|marked| {
+                        marked.toggle(item.id.haystack_index, item.haystack.clone())
+                    });
                     self.list.apply(&ListAction::ItemNext);
                 }
             }
@@ -1356,21 +3206,13 @@ where
                 self.marked.with_mut(|marked| {
                     if marked.is_empty() {
                         // mark all
-                        let ranked_items = &self.list.items().ranked_items;
-                        let (haystack_gen, rank_gen) = ranked_items.generation();
-                        for score in ranked_items.iter() {
+                        for score in self.list.items().ranked_items.iter() {
                             let Some(haystack) = self.haystack.get(score.haystack_index) else {
                                 continue;
                             };
-                            marked.toggle(
-                                SweepItemId {
-                                    haystack_gen,
-                                    haystack_index: score.haystack_index,
-                                    rank_gen,
-                                    rank_index: score.rank_index,
-                                },
-                                haystack.clone(),
-                            )
+                            if !marked.toggle(score.haystack_index, haystack.clone()) {
+                                break;
+                            }
                         }
                     } else {
                         // un-mark all
@@ -1386,15 +3228,60 @@ where
                     });
                 }
             }
+            SweepAction::History => {
+                if !self.is_help && !self.history.entries().is_empty() {
+                    return Ok(WindowAction::Open {
+                        window: self.history_window()?,
+                        close: false,
+                    });
+                }
+            }
             SweepAction::ScorerNext => {
                 if let Some(scorer) = scorer_by_name(&mut self.scorers, None) {
-                    self.ranker.scorer_set(scorer);
+                    self.scorer_set(scorer);
                 }
             }
             SweepAction::PreviewToggle => self.theme_set(
                 self.theme
                     .modify(|inner| inner.show_preview = !self.theme.show_preview),
             ),
+            SweepAction::PreviewPositionNext => {
+                let layout = self.preview_layout_current();
+                self.preview_layout = Some(PreviewLayout {
+                    position: layout.position.next(),
+                    ..layout
+                });
+                self.preview_maximized = false;
+            }
+            SweepAction::PreviewMaximize => self.preview_maximized = !self.preview_maximized,
+            SweepAction::PreviewSearch => {
+                if self.preview_large.is_some() {
+                    let theme = self.theme.clone();
+                    self.preview_search
+                        .get_or_insert_with(|| PreviewSearch::new(theme))
+                        .editing = true;
+                }
+            }
+            SweepAction::PreviewSearchNext => self.preview_search_jump(true),
+            SweepAction::PreviewSearchPrev => self.preview_search_jump(false),
+            SweepAction::ListScrollLeft => {
+                self.list_offset = self.list_offset.saturating_sub(LIST_SCROLL_STEP);
+            }
+            SweepAction::ListScrollRight => {
+                // do not scroll past the end of the widest visible item
+                let overflow = self.list_overflow.get();
+                let step = LIST_SCROLL_STEP.min(overflow);
+                self.list_offset += step;
+                self.list_overflow.set(overflow - step);
+            }
+            SweepAction::ListWrapToggle => {
+                let current = self.current().map(|item| item.id.haystack_index);
+                self.list_wrapped = if self.list_wrapped == current {
+                    None
+                } else {
+                    current
+                };
+            }
             SweepAction::PreviewLineNext | SweepAction::PreviewPageNext => {
                 if let Some(preview) = self.preview_large.as_ref() {
                     let delta = if matches!(action, SweepAction::PreviewPageNext) {
@@ -1443,7 +3330,27 @@ where
                     desc
                 });
         });
-        let mut entries: Vec<_> = descriptions.into_values().collect();
+        // normal mode bindings are listed separately
+        let mut descriptions_normal: BTreeMap<String, ActionDesc> = BTreeMap::new();
+        self.vi_key_map.for_each(|chord, action| {
+            let mut desc = action.description();
+            if desc.name.is_empty() {
+                return;
+            }
+            descriptions_normal
+                .entry(desc.name.clone())
+                .and_modify(|desc_curr| desc_curr.chords.push(KeyChord::from_iter(chord)))
+                .or_insert_with(|| {
+                    desc.description = format!("[normal] {}", desc.description);
+                    desc.chords.clear();
+                    desc.chords.push(KeyChord::from_iter(chord));
+                    desc
+                });
+        });
+        let mut entries: Vec<_> = descriptions
+            .into_values()
+            .chain(descriptions_normal.into_values())
+            .collect();
         entries.sort_by_key(|desc| self.key_actions.get(&desc.name));
 
         let help_uid = self.uid().with_suffix("help");
@@ -1478,10 +3385,49 @@ where
             }),
             true,
         );
-        window.ranker.keep_order(Some(true));
+        window.keep_order_set(Some(true));
+        window.layout = self.layout.clone();
         window.haystack_extend(entries);
         Ok(Box::new(window))
     }
+
+    /// Create sweep state which is used to pick query from the history
+    fn history_window(&self) -> Result<Box<dyn Window>, Error> {
+        let history_uid = self.uid().with_suffix("history");
+        let parent_uid = self.uid().clone();
+        let mut window = SweepWindow::new(
+            history_uid,
+            "HISTORY".to_owned(),
+            Some(HISTORY_ICON.clone()),
+            Ranker::new(self.ranker.ranker_thread().clone())?,
+            self.theme.clone(),
+            self.scorers.clone(),
+            (),
+            self.term_waker.clone(),
+            None,
+            Arc::new(move |event| {
+                if let SweepEvent::Select { items, .. } = event {
+                    let args = match items.into_iter().next() {
+                        Some(query) => json!({ "query": query }),
+                        None => Value::Null,
+                    };
+                    Ok(WindowAction::Switch {
+                        uid: parent_uid.clone(),
+                        args,
+                        close: true,
+                    })
+                } else {
+                    Ok(WindowAction::Nothing)
+                }
+            }),
+            true,
+        );
+        window.keep_order_set(Some(true));
+        window.layout = self.layout.clone();
+        // the most recent query first
+        window.haystack_extend(self.history.entries().iter().rev().cloned().collect());
+        Ok(Box::new(window))
+    }
 }
 
 impl<H: Haystack> Window for SweepWindow<H> {
@@ -1506,40 +3452,37 @@ impl<H: Haystack> Window for SweepWindow<H> {
                 ThemeGet(resolve) => {
                     mem::drop(resolve.send(self.theme.clone()));
                 }
-                Bind { chord, tag, desc } => match *chord.keys() {
-                    [
-                        Key {
-                            name: KeyName::Backspace,
-                            mode: KeyMod::EMPTY,
-                        },
-                    ] => {
-                        self.key_empty_backspace = if tag.is_empty() {
-                            None
-                        } else {
-                            Some(tag.into())
-                        };
-                    }
-                    _ => {
-                        let action = if tag.is_empty() {
-                            // empty user action means unbind
-                            SweepAction::User {
-                                chord: KeyChord::new(Vec::new()),
-                                tag: Default::default(),
-                                desc: String::new(),
-                            }
-                        } else {
-                            self.key_actions
-                                .entry(tag.clone())
-                                .or_insert_with(|| SweepAction::User {
-                                    chord: chord.clone(),
-                                    tag: tag.into(),
-                                    desc,
-                                })
-                                .clone()
-                        };
-                        self.key_map.register(chord.as_ref(), action);
+                ThemeSet(theme) => {
+                    let theme = theme.modify(|inner| inner.show_preview = self.theme.show_preview);
+                    self.theme_set(theme.clone());
+                    self.event_notify(SweepEvent::ThemeChanged {
+                        uid: self.window_uid.clone(),
+                        theme,
+                    });
+                }
+                ThemeReload => match self.theme.reload() {
+                    Ok(theme) => {
+                        self.theme_set(theme.clone());
+                        self.event_notify(SweepEvent::ThemeChanged {
+                            uid: self.window_uid.clone(),
+                            theme,
+                        });
                     }
+                    Err(error) => tracing::error!(?error, "[SweepWindow] failed to reload theme"),
                 },
+                LayoutSet(layout) => {
+                    self.layout = layout.clone();
+                    self.event_notify(SweepEvent::LayoutChanged {
+                        uid: self.window_uid.clone(),
+                        layout,
+                    });
+                }
+                Bind {
+                    mode,
+                    chord,
+                    tags,
+                    desc,
+                } => self.bind(mode, chord, tags, desc),
                 PromptSet(new_prompt, new_icon) => {
                     if let Some(new_prompt) = new_prompt {
                         self.prompt = new_prompt;
@@ -1553,6 +3496,17 @@ impl<H: Haystack> Window for SweepWindow<H> {
                     let items = self.marked.with_mut(|marked| marked.take()).collect();
                     _ = resolve.send(items);
                 }
+                MarksSet(mut indices) => {
+                    _ = self.marked.with_mut(|marked| marked.take());
+                    let mut seen = HashSet::new();
+                    indices.retain(|index| seen.insert(*index));
+                    self.marks_toggle(indices);
+                }
+                MarksToggle(indices) => self.marks_toggle(indices),
+                MarksClear => {
+                    _ = self.marked.with_mut(|marked| marked.take());
+                }
+                MarksPolicySet(policy) => self.marks_policy_set(policy),
                 CursorSet { position } => {
                     self.list.cursor_set(position);
                 }
@@ -1560,11 +3514,15 @@ impl<H: Haystack> Window for SweepWindow<H> {
                     let _ = match scorer_by_name(&mut self.scorers, name.as_deref()) {
                         None => resolve.send(false),
                         Some(scorer) => {
-                            self.ranker.scorer_set(scorer);
+                            self.scorer_set(scorer);
                             resolve.send(true)
                         }
                     };
                 }
+                PreviewLayoutSet(layout) => {
+                    self.preview_layout = layout;
+                    self.preview_maximized = false;
+                }
                 PreviewSet(value) => {
                     let show_preview = match value {
                         Some(value) => value,
@@ -1573,21 +3531,59 @@ impl<H: Haystack> Window for SweepWindow<H> {
                     self.theme_set(self.theme.modify(|inner| inner.show_preview = show_preview));
                 }
                 FooterSet(view) => self.footer = view,
-                ScorerSet(scorer) => self.ranker.scorer_set(scorer),
+                ScorerSet(scorer) => self.scorer_set(scorer),
                 HaystackExtend(items) => {
-                    self.haystack_extend(items);
+                    // keyed items replace items with the same key
+                    self.haystack_upsert(items);
                 }
                 HaystackUpdate { index, item } => {
-                    if let Some(item_ref) = self.haystack.get_mut(index) {
+                    let duplicate = item
+                        .key()
+                        .and_then(|key| self.haystack_keys.get(key))
+                        .is_some_and(|other| *other != index);
+                    if duplicate {
+                        tracing::warn!(
+                            ?index,
+                            key = ?item.key(),
+                            "[SweepWindow] item_update ignored, key belongs to another item"
+                        );
+                    } else if let Some(item_ref) = self.haystack.get_mut(index) {
+                        self.marked
+                            .with_mut(|marked| marked.update(index, item.clone()));
+                        if let Some(key) = item_ref.key() {
+                            self.haystack_keys.remove(key);
+                        }
+                        if let Some(key) = item.key() {
+                            self.haystack_keys.insert(key.to_owned(), index);
+                        }
                         *item_ref = item;
                     }
                 }
+                HaystackUpsert(items) => self.haystack_upsert(items),
+                HaystackRemove(keys) => self.haystack_remove(keys),
                 HaystackClear => {
+                    let keep = self.marks_policy.keep_on_clear;
+                    self.marked.with_mut(|marked| {
+                        if keep {
+                            marked.detach();
+                        } else {
+                            _ = marked.take();
+                        }
+                    });
                     self.ranker.haystack_clear();
                     self.haystack.clear();
                     self.haystack_keymap.clear();
+                    self.haystack_keys.clear();
+                    self.cursor_pin = None;
+                    self.list_wrapped = None;
+                }
+                RankerKeepOrder(toggle) => {
+                    self.keep_order_set(toggle);
+                    self.event_notify(SweepEvent::KeepOrderChanged {
+                        uid: self.window_uid.clone(),
+                        keep_order: self.keep_order,
+                    });
                 }
-                RankerKeepOrder(toggle) => self.ranker.keep_order(toggle),
                 RenderSuppress(suppress) => {
                     self.render_suppress_sync = if suppress {
                         Some(Arc::new(AtomicBool::new(false)))
@@ -1595,13 +3591,48 @@ impl<H: Haystack> Window for SweepWindow<H> {
                         Some(self.ranker.sync())
                     };
                 }
+                HistorySet(history) => self.history_set(history),
+                ActionRun {
+                    name,
+                    args,
+                    resolve,
+                } => {
+                    let action = match self.action_by_name(&name, args) {
+                        Ok(action) => action,
+                        Err(error) => {
+                            _ = resolve.send(Err(error));
+                            continue;
+                        }
+                    };
+                    let window_action = match self.handle_action(&action) {
+                        Ok(window_action) => window_action,
+                        Err(error) => {
+                            _ = resolve.send(Err(error));
+                            continue;
+                        }
+                    };
+                    _ = resolve.send(Ok(()));
+                    if !matches!(window_action, WindowAction::Nothing) {
+                        // remaining requests are processed on the next iteration
+                        self.term_waker.wake()?;
+                        self.changes_report();
+                        return Ok(window_action);
+                    }
+                }
+                Subscribe(subscription) => {
+                    // only changes made after subscription are reported
+                    self.subscription = subscription;
+                    self.ranker_refresh();
+                    self.reported = self.reported_state();
+                }
             }
         }
+        self.changes_report();
         Ok(WindowAction::Nothing)
     }
 
     fn resume(&mut self, args: Value) -> Result<WindowAction, Error> {
-        match args {
+        let window_action = match args {
             Value::String(action_name) => {
                 // handle action picked by help window
                 if let Some(action) = self.key_actions.get(&action_name) {
@@ -1610,49 +3641,35 @@ impl<H: Haystack> Window for SweepWindow<H> {
                     Ok(WindowAction::Nothing)
                 }
             }
+            Value::Object(mut args) => {
+                // handle query picked by history window
+                if let Some(Value::String(query)) = args.remove("query") {
+                    self.input.set(&query);
+                    self.ranker_trigger();
+                }
+                Ok(WindowAction::Nothing)
+            }
             _ => Ok(WindowAction::Nothing),
-        }
+        };
+        self.changes_report();
+        window_action
     }
 
     fn handle_key(&mut self, key: Key) -> Result<WindowAction, Error> {
-        let is_first_key = self.key_map_state.is_empty();
-        if let Some(action) = self.key_map.lookup_state(&mut self.key_map_state, key) {
-            tracing::debug!(?action, "[SweepState.handle_key]");
-            // do not generate Backspace, when input is not empty
-            let backspace = Key::new(KeyName::Backspace, KeyMod::EMPTY);
-            if is_first_key && key == backspace && self.input.get().count() == 0 {
-                if let Some(ref tag) = self.key_empty_backspace {
-                    return (self.event_handler)(SweepEvent::Bind {
-                        uid: self.window_uid.clone(),
-                        tag: tag.clone(),
-                        chord: KeyChord::from_iter([backspace]),
-                    });
-                }
-            } else {
-                return self.handle_action(&action.clone());
-            }
-        } else if let Some(action) = self.haystack_keymap.handle(key).cloned() {
-            return self.handle_action(&action);
-        } else if let Key {
-            name: KeyName::Char(c),
-            mode: KeyMod::EMPTY,
-        } = key
-        {
-            // send plain chars to the input
-            self.input.apply(&InputAction::Insert(c));
-            self.ranker_trigger();
-        }
-        Ok(WindowAction::Nothing)
+        let window_action = self.key_dispatch(key);
+        self.changes_report();
+        window_action
     }
 
     fn handle_mouse(&mut self, mouse: Mouse, tag: &Value) -> Result<WindowAction, Error> {
         let Value::String(tag) = &tag else {
             return Ok(WindowAction::Nothing);
         };
-        match self.key_actions.get(tag) {
+        let window_action = match self.key_actions.get(tag) {
             Some(action) if mouse.mode == KeyMod::EMPTY => {
                 // trigger state bound actions on release
-                self.handle_action(&action.clone())
+                let action = action.clone();
+                self.handle_action(&self.list_action_orient(action))
             }
             _ if mouse.mode.contains(KeyMod::PRESS) => {
                 // ignore press events, and trigger on release
@@ -1666,10 +3683,21 @@ impl<H: Haystack> Window for SweepWindow<H> {
                     chord: KeyChord::from_iter([key]),
                 })
             }
-        }
+        };
+        self.changes_report();
+        window_action
+    }
+
+    fn layout(&self) -> Option<WindowLayout> {
+        self.layout.clone()
     }
 
-    fn view(&mut self, term_position: Position, sweep_layout: WindowLayout) -> Option<BoxView<'_>> {
+    fn view(
+        &mut self,
+        term_position: Position,
+        sweep_layout: WindowLayout,
+        color_depth: ColorDepth,
+    ) -> Option<BoxView<'_>> {
         if !self
             .render_suppress_sync
             .as_ref()
@@ -1680,13 +3708,39 @@ impl<H: Haystack> Window for SweepWindow<H> {
         self.render_suppress_sync.take();
 
         self.ranker_refresh();
-        let large_preview = self.current_preview_large().map(|v| v.into_view());
+        // themes set at any point (options, RPC, reload) are adapted here
+        if self.theme.depth != Some(color_depth) {
+            self.theme_set(self.theme.quantize(color_depth));
+        }
+        self.orientation = sweep_layout.orientation();
+        self.list.reverse_set(self.orientation.list_reverse);
+        self.preview_position = sweep_layout.preview_position();
+        let preview_layout = if self.preview_maximized {
+            let layout = self.preview_layout_current();
+            let position = match layout.position {
+                PreviewPosition::Hidden => PreviewPosition::default(),
+                position => position,
+            };
+            Some(PreviewLayout {
+                position,
+                size: WindowLayoutSize::Full,
+            })
+        } else {
+            self.preview_layout.clone()
+        };
+        let large_preview = match &preview_layout {
+            Some(layout) if layout.position == PreviewPosition::Hidden => None,
+            _ => self.current_preview_large().map(|v| v.into_view()),
+        };
+        let theme = self.theme.clone();
         let sweep_view = self.into_view();
         let view = SweepLayoutView {
             sweep_view,
             large_preview,
+            preview_layout,
             sweep_layout,
             term_position,
+            theme,
         };
         Some(view.boxed())
     }
@@ -1736,20 +3790,63 @@ impl<'a, H: Haystack> IntoView for &'a mut SweepWindow<H> {
             })
             .put_fmt(&self.prompt, None)
             .with_char(' ')
+            .scope(|text| {
+                // vi mode indicator
+                let Some(mode) = self.vi_mode else {
+                    return;
+                };
+                let (label, face) = match mode {
+                    ViMode::Normal => ("N", self.theme.label.invert()),
+                    ViMode::Insert => ("I", self.theme.label),
+                };
+                text.put_fmt(
+                    &format_args!(" {} ", label),
+                    Some(face.with_attrs(FaceAttrs::BOLD)),
+                );
+            })
             .put_text(&self.theme.separator_right)
             .take();
 
+        // preview search input replaces query input while it is focused
+        let (prompt, input) = match self.preview_search.as_ref().filter(|search| search.editing) {
+            None => (prompt, &self.input),
+            Some(search) => {
+                let matches = self
+                    .preview_large
+                    .as_ref()
+                    .map_or(0, |preview| preview.preview.search_matches().len());
+                let scorer = search
+                    .scorers
+                    .front()
+                    .map(|scorer| scorer("").name().to_owned())
+                    .unwrap_or_default();
+                let prompt = Text::new()
+                    .with_face(self.theme.label)
+                    .with_fmt(&format_args!(" / {scorer} "), None)
+                    .with_fmt(
+                        &format_args!(" {matches} "),
+                        Some(self.theme.label.invert().with_attrs(FaceAttrs::BOLD)),
+                    )
+                    .put_text(&self.theme.separator_right)
+                    .take();
+                (prompt, &search.input)
+            }
+        };
         let header = FlexRef::row((
             prompt.into(),
-            FlexChild::new(&self.input).flex(1.0),
+            FlexChild::new(input).flex(1.0),
             View::tag(stats, Value::String(SWEEP_SCORER_NEXT_TAG.to_string())).into(),
         ));
 
+        self.list_overflow.set(0);
         let body = FlexRef::row((
             // list
             FlexChild::new(self.list.view(SweepItemsContext {
                 haystack_context: self.haystack_context.clone(),
                 haystack: &self.haystack,
+                offset: self.list_offset,
+                overflow: self.list_overflow.clone(),
+                wrapped: self.list_wrapped,
             }))
             .flex(1.0),
             // preview
@@ -1779,16 +3876,22 @@ impl<'a, H: Haystack> IntoView for &'a mut SweepWindow<H> {
             self.list.scroll_bar().into(),
         ));
 
-        let mut view = Flex::column()
-            .add_child(Container::new(header).with_height(1))
-            .add_flex_child(1.0, body);
-        if let Some(footer) = &self.footer {
-            view.push_child_ext(
-                footer.clone(),
-                None,
-                Some(self.theme.list_default),
-                Align::Expand,
-            )
+        // window is mirrored vertically when prompt is at the bottom
+        let header = Container::new(header).with_height(1);
+        let footer = self.footer.clone();
+        let mut view = Flex::column();
+        if self.orientation.prompt_bottom {
+            if let Some(footer) = footer {
+                view.push_child_ext(footer, None, Some(self.theme.list_default), Align::Expand)
+            }
+            view.push_flex_child(1.0, body);
+            view.push_child(header);
+        } else {
+            view.push_child(header);
+            view.push_flex_child(1.0, body);
+            if let Some(footer) = footer {
+                view.push_child_ext(footer, None, Some(self.theme.list_default), Align::Expand)
+            }
         }
         view
     }
@@ -1832,6 +3935,16 @@ impl<H> SweepWindowDispatch<H> {
     fn remove(&self, uid: &WindowId) -> Option<Sender<SweepWindowRequest<H>>> {
         self.channels.with_mut(|channels| channels.remove(uid))
     }
+
+    /// Send request to all windows
+    fn broadcast(&self, request: impl Fn() -> SweepWindowRequest<H>) {
+        let uids: Vec<_> = self
+            .channels
+            .with(|channels| channels.keys().cloned().collect());
+        for uid in uids {
+            self.handle(&uid, request());
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -1916,8 +4029,16 @@ trait Window: Send + Sync {
     /// Handle mouse event
     fn handle_mouse(&mut self, mouse: Mouse, tag: &Value) -> Result<WindowAction, Error>;
 
+    /// Layout of the window, `None` means layout specified by options
+    fn layout(&self) -> Option<WindowLayout>;
+
     /// Window view, `None` means do not update
-    fn view(&mut self, term_position: Position, sweep_layout: WindowLayout) -> Option<BoxView<'_>>;
+    fn view(
+        &mut self,
+        term_position: Position,
+        sweep_layout: WindowLayout,
+        color_depth: ColorDepth,
+    ) -> Option<BoxView<'_>>;
 }
 
 impl<M: Window + ?Sized> Window for Box<M> {
@@ -1941,8 +4062,17 @@ impl<M: Window + ?Sized> Window for Box<M> {
         (**self).handle_mouse(mouse, tag)
     }
 
-    fn view(&mut self, term_position: Position, sweep_layout: WindowLayout) -> Option<BoxView<'_>> {
-        (**self).view(term_position, sweep_layout)
+    fn layout(&self) -> Option<WindowLayout> {
+        (**self).layout()
+    }
+
+    fn view(
+        &mut self,
+        term_position: Position,
+        sweep_layout: WindowLayout,
+        color_depth: ColorDepth,
+    ) -> Option<BoxView<'_>> {
+        (**self).view(term_position, sweep_layout, color_depth)
     }
 }
 
@@ -2121,10 +4251,6 @@ where
         TerminalCommand::Title(options.title.clone()),
     ])?;
     term.execute_many(TerminalCommand::mouse_events_set(true, false))?;
-    // force dumb four color theme for dumb terminal
-    if ColorDepth::Gray == term.capabilities().depth {
-        options.theme = Theme::dumb().modify(|inner| inner.show_preview = true);
-    }
 
     // prepare terminal based on layout
     let term_size = term.size()?;
@@ -2137,6 +4263,10 @@ where
     if options.layout.is_altscreen() {
         term.execute(TerminalCommand::altscreen_set(true))?;
     }
+    // layout terminal is prepared for, and whether previous frame has been
+    // cleared because layout of the current window is different
+    let mut layout_active = options.layout.clone();
+    let mut layout_cleared = false;
     // report size
     events.send(SweepEvent::Resize(term_size))?;
 
@@ -2236,6 +4366,16 @@ where
                     window_dispatch.handle(&uid, request);
                     WindowAction::Nothing
                 }
+                ThemeReload => {
+                    match options.theme.reload() {
+                        Ok(theme) => options.theme = theme,
+                        Err(error) => {
+                            tracing::error!(?error, "[sweep_ui_worker] failed to reload theme")
+                        }
+                    }
+                    window_dispatch.broadcast(|| SweepWindowRequest::ThemeReload);
+                    WindowAction::Nothing
+                }
             };
             if !window_stack.handle_action(window_event)? {
                 return Ok(TerminalAction::Quit(()));
@@ -2301,7 +4441,27 @@ where
             };
             return Ok(action);
         };
-        let Some(view) = window.view(term_position, options.layout.clone()) else {
+        let layout = window.layout().unwrap_or_else(|| options.layout.clone());
+        if layout != layout_active {
+            // render empty frame first, so the renderer state still matches
+            // the screen after switching alt screen or scrolling
+            if !layout_cleared {
+                layout_cleared = true;
+                term.waker().wake()?;
+                return Ok(TerminalAction::Wait);
+            }
+            layout_cleared = false;
+            if layout.is_altscreen() != layout_active.is_altscreen() {
+                term.execute(TerminalCommand::altscreen_set(layout.is_altscreen()))?;
+            }
+            let term_scroll = layout.scroll(term_position, term.size()?.cells);
+            if term_scroll > 0 {
+                term_position.row -= term_scroll;
+                term.execute(TerminalCommand::Scroll(term_scroll as i32))?;
+            }
+            layout_active = layout.clone();
+        }
+        let Some(view) = window.view(term_position, layout, term.capabilities().depth) else {
             return Ok(TerminalAction::WaitNoFrame);
         };
         let ctx = ViewContext::new(term)?;
@@ -2317,7 +4477,7 @@ where
         row: term_position.row,
         col: 0,
     }))?;
-    if options.layout.is_altscreen() {
+    if layout_active.is_altscreen() {
         term.execute(TerminalCommand::altscreen_set(false))?;
     }
     term.poll(Some(Duration::new(0, 0)))?;
@@ -2347,6 +4507,12 @@ impl<H: Haystack> SweepItems<H> {
 struct SweepItemsContext<'a, H: Haystack> {
     haystack_context: H::Context,
     haystack: &'a [H],
+    // horizontal scroll offset of the items
+    offset: usize,
+    // cells cut off on the right side of the items
+    overflow: HaystackViewOverflow,
+    // haystack index of the wrapped item
+    wrapped: Option<usize>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -2391,7 +4557,12 @@ impl<H: Haystack> ListItems for SweepItems<H> {
     ) -> Option<Self::ItemView> {
         let score = self.ranked_items.get(item.rank_index)?;
         let haystack = ctx.haystack.get(item.haystack_index)?;
-        Some(haystack.view(&ctx.haystack_context, score.positions, &theme))
+        let options = HaystackViewOptions {
+            offset: ctx.offset,
+            wrap: ctx.wrapped == Some(item.haystack_index),
+            overflow: ctx.overflow.clone(),
+        };
+        Some(haystack.view_with_options(&ctx.haystack_context, score.positions, &theme, options))
     }
 
     fn is_marked(&self, item: &Self::Item) -> bool {
@@ -2404,6 +4575,10 @@ struct MarkedItems<H> {
     order_to_haystack: BTreeMap<usize, H>,
     haystack_index_to_order: HashMap<usize, usize>,
     order: usize,
+    // incremented on every change
+    generation: usize,
+    // maximum number of marked items
+    limit: Option<usize>,
 }
 
 impl<H> Default for MarkedItems<H> {
@@ -2418,53 +4593,236 @@ impl<H> MarkedItems<H> {
             order_to_haystack: Default::default(),
             haystack_index_to_order: Default::default(),
             order: 0,
+            generation: 0,
+            limit: None,
         }
     }
 
     fn len(&self) -> usize {
-        self.haystack_index_to_order.len()
+        self.order_to_haystack.len()
     }
 
     fn is_empty(&self) -> bool {
-        self.haystack_index_to_order.is_empty()
+        self.order_to_haystack.is_empty()
     }
 
-    fn toggle(&mut self, id: SweepItemId, haystack: H) {
-        match self.haystack_index_to_order.get(&id.haystack_index) {
+    fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.len() >= limit)
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Marked items in the order they were marked
+    fn items(&self) -> impl Iterator<Item = &H> {
+        self.order_to_haystack.values()
+    }
+
+    /// Toggle mark of the item, returns `false` if the limit has been reached
+    fn toggle(&mut self, haystack_index: usize, haystack: H) -> bool {
+        match self.haystack_index_to_order.get(&haystack_index) {
             Some(index) => {
                 self.order_to_haystack.remove(index);
-                self.haystack_index_to_order.remove(&id.haystack_index);
+                self.haystack_index_to_order.remove(&haystack_index);
             }
             None => {
+                if self.is_full() {
+                    return false;
+                }
                 self.haystack_index_to_order
-                    .insert(id.haystack_index, self.order);
+                    .insert(haystack_index, self.order);
                 self.order_to_haystack.insert(self.order, haystack);
                 self.order += 1;
             }
         }
+        self.generation += 1;
+        true
+    }
+
+    /// Update marked item if it is marked
+    fn update(&mut self, haystack_index: usize, haystack: H) {
+        if let Some(index) = self.haystack_index_to_order.get(&haystack_index) {
+            self.order_to_haystack.insert(*index, haystack);
+            self.generation += 1;
+        }
+    }
+
+    /// Keep marked items but forget their haystack indices, used when
+    /// haystack is cleared and indices are reused by the new items
+    fn detach(&mut self) {
+        if !self.haystack_index_to_order.is_empty() {
+            self.generation += 1;
+        }
+        self.haystack_index_to_order.clear();
     }
 
     fn take(&mut self) -> impl Iterator<Item = H> + use<H> {
+        if !self.is_empty() {
+            self.generation += 1;
+        }
         self.haystack_index_to_order.clear();
         std::mem::take(&mut self.order_to_haystack).into_values()
     }
 
+    /// Move marks to the new haystack indices, items mapped to `None`
+    /// have been removed and are unmarked
+    fn remap(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        let mut haystack_index_to_order = HashMap::new();
+        for (haystack_index, order) in self.haystack_index_to_order.drain() {
+            match remap(haystack_index) {
+                Some(haystack_index) => {
+                    haystack_index_to_order.insert(haystack_index, order);
+                }
+                None => {
+                    self.order_to_haystack.remove(&order);
+                }
+            }
+        }
+        self.haystack_index_to_order = haystack_index_to_order;
+        self.generation += 1;
+    }
+
     fn contains_id(&self, id: SweepItemId) -> bool {
         self.haystack_index_to_order
             .contains_key(&id.haystack_index)
     }
 }
 
-#[derive(Debug, Clone)]
+/// Policy applied to the marked (multi-select) items
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarksPolicy {
+    /// Maximum number of marked items, marking more items is ignored
+    pub limit: Option<usize>,
+    /// Marked items are kept (and returned on select) when items are cleared,
+    /// enabled by default
+    pub keep_on_clear: bool,
+}
+
+impl Default for MarksPolicy {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            keep_on_clear: true,
+        }
+    }
+}
+
+/// Placement of the prompt and direction of the items list
+///
+/// Specified with `prompt=top|bottom` and `list=down|up` layout attributes,
+/// `reverse` attribute places prompt at the bottom with the best match being
+/// the nearest to the prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowOrientation {
+    /// Prompt is rendered below the list
+    pub prompt_bottom: bool,
+    /// List is rendered bottom-up, that is the best match is at the bottom
+    pub list_reverse: bool,
+}
+
+impl WindowOrientation {
+    /// Prompt at the bottom and the best match is the nearest to the prompt
+    pub fn reverse() -> Self {
+        Self {
+            prompt_bottom: true,
+            list_reverse: true,
+        }
+    }
+
+    /// Update orientation from layout attribute, unknown attributes are ignored
+    fn attr_apply(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key {
+            "prompt" | "p" => {
+                self.prompt_bottom = match value {
+                    "top" => false,
+                    "bottom" => true,
+                    _ => anyhow::bail!("invalid prompt position: {}", value),
+                }
+            }
+            "list" | "l" => {
+                self.list_reverse = match value {
+                    "down" => false,
+                    "up" => true,
+                    _ => anyhow::bail!("invalid list direction: {}", value),
+                }
+            }
+            "reverse" => *self = Self::reverse(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Decorations of the float window
+///
+/// Specified with `border=rounded|sharp|double|none`, `padding=<margins>`,
+/// `margin=<margins>` and `label=<text>` layout attributes, where margins are
+/// `all`, `vertical:horizontal` or `top:right:bottom:left` (i.e `padding=0:1`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowFrame {
+    /// Style of the border drawn around the window
+    pub border: BorderStyle,
+    /// Space between the border and the window content
+    pub padding: Margins,
+    /// Space around the border
+    pub margin: Margins,
+    /// Label drawn on the top side of the border
+    pub label: Option<String>,
+}
+
+impl WindowFrame {
+    /// Update frame from layout attribute, unknown attributes are ignored
+    fn attr_apply(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key {
+            "border" | "b" if value.is_empty() => self.border = BorderStyle::Rounded,
+            "border" | "b" => self.border = value.parse()?,
+            "padding" | "pad" => self.padding = margins_parse(value)?,
+            "margin" => self.margin = margins_parse(value)?,
+            "label" => self.label = Some(value.to_owned()).filter(|label| !label.is_empty()),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parse margins specified as `all`, `vertical:horizontal` or `top:right:bottom:left`
+fn margins_parse(string: &str) -> Result<Margins, Error> {
+    let values = string
+        .split(':')
+        .map(|value| value.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid margins: {}", string))?;
+    let [top, right, bottom, left] = match values[..] {
+        [all] => [all; 4],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => anyhow::bail!("invalid margins: {}", string),
+    };
+    Ok(Margins {
+        left,
+        right,
+        top,
+        bottom,
+    })
+}
+
+/// Layout attributes that can be specified without value
+const LAYOUT_FLAGS: &[&str] = &["reverse", "border", "b"];
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum WindowLayout {
     Float {
         height: WindowLayoutSize,
         width: WindowLayoutSize,
         row: WindowLayoutSize,
         column: WindowLayoutSize,
+        orientation: WindowOrientation,
+        frame: WindowFrame,
     },
     Full {
         height: WindowLayoutSize,
+        orientation: WindowOrientation,
     },
 }
 
@@ -2474,6 +4832,24 @@ impl WindowLayout {
         matches!(self, WindowLayout::Full { .. })
     }
 
+    /// Placement of the prompt and direction of the list
+    pub fn orientation(&self) -> WindowOrientation {
+        match self {
+            WindowLayout::Float { orientation, .. } | WindowLayout::Full { orientation, .. } => {
+                *orientation
+            }
+        }
+    }
+
+    /// Position of the large preview when preview layout is not specified
+    fn preview_position(&self) -> PreviewPosition {
+        match self {
+            WindowLayout::Full { height, .. } if height.is_positive() => PreviewPosition::Bottom,
+            WindowLayout::Full { .. } => PreviewPosition::Top,
+            WindowLayout::Float { .. } => PreviewPosition::default(),
+        }
+    }
+
     /// Whether we need to scroll terminal
     fn scroll(&self, term_position: Position, term_size: Size) -> usize {
         let WindowLayout::Float { row, height, .. } = self else {
@@ -2497,7 +4873,64 @@ impl Default for WindowLayout {
             width: Full,
             column: Absolute(0),
             row: Full,
+            orientation: WindowOrientation::default(),
+            frame: WindowFrame::default(),
+        }
+    }
+}
+
+impl fmt::Display for WindowLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let orientation = match self {
+            WindowLayout::Float {
+                height,
+                width,
+                row,
+                column,
+                orientation,
+                ..
+            } => {
+                write!(f, "float,h={},w={},r={},c={}", height, width, row, column)?;
+                orientation
+            }
+            WindowLayout::Full {
+                height,
+                orientation,
+            } => {
+                write!(f, "full,h={}", height)?;
+                orientation
+            }
+        };
+        if orientation.prompt_bottom {
+            f.write_str(",prompt=bottom")?;
         }
+        if orientation.list_reverse {
+            f.write_str(",list=up")?;
+        }
+        if let WindowLayout::Float { frame, .. } = self {
+            let margins = |margins: &Margins| {
+                let Margins {
+                    top,
+                    right,
+                    bottom,
+                    left,
+                } = margins;
+                format!("{top}:{right}:{bottom}:{left}")
+            };
+            if frame.border != BorderStyle::None {
+                write!(f, ",border={}", frame.border)?;
+            }
+            if frame.padding != Margins::default() {
+                write!(f, ",padding={}", margins(&frame.padding))?;
+            }
+            if frame.margin != Margins::default() {
+                write!(f, ",margin={}", margins(&frame.margin))?;
+            }
+            if let Some(label) = &frame.label {
+                write!(f, ",label={}", label)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -2505,30 +4938,38 @@ impl std::str::FromStr for WindowLayout {
     type Err = Error;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        // name(,attr=value)*
+        // name(,attr(=value)?)*
         let mut iter = string.trim().split(',');
         let Some(name) = iter.next() else {
             anyhow::bail!("invalid layout: {} (expected `name(,attr=value)`)", string);
         };
+        // attributes without value are only accepted for flags, and ignored otherwise
         let kvs = iter.filter_map(|kv| {
             let mut kv = kv.splitn(2, '=');
             let key = kv.next()?.trim();
-            let value = kv.next()?.trim();
-            Some((key, value))
+            match kv.next() {
+                Some(value) => Some((key, value.trim())),
+                None => LAYOUT_FLAGS.contains(&key).then_some((key, "")),
+            }
         });
-        match name {
+        let mut orientation = WindowOrientation::default();
+        match name.trim() {
             "float" => {
                 let mut height = WindowLayoutSize::Absolute(11);
                 let mut width = WindowLayoutSize::Full;
                 let mut column = WindowLayoutSize::Absolute(0);
                 let mut row = WindowLayoutSize::Full;
+                let mut frame = WindowFrame::default();
                 for (key, value) in kvs {
                     match key {
                         "height" | "h" => height = value.parse()?,
                         "width" | "w" => width = value.parse()?,
                         "column" | "c" => column = value.parse()?,
                         "row" | "r" => row = value.parse()?,
-                        _ => {}
+                        _ => {
+                            orientation.attr_apply(key, value)?;
+                            frame.attr_apply(key, value)?;
+                        }
                     }
                 }
                 Ok(WindowLayout::Float {
@@ -2536,6 +4977,8 @@ impl std::str::FromStr for WindowLayout {
                     width,
                     row,
                     column,
+                    orientation,
+                    frame,
                 })
             }
             "full" => {
@@ -2543,21 +4986,187 @@ impl std::str::FromStr for WindowLayout {
                 for (key, value) in kvs {
                     match key {
                         "height" | "h" => height = value.parse()?,
-                        _ => {}
+                        _ => orientation.attr_apply(key, value)?,
                     }
                 }
-                Ok(WindowLayout::Full { height })
+                Ok(WindowLayout::Full {
+                    height,
+                    orientation,
+                })
             }
             _ => Err(anyhow::anyhow!("invalid layout name: {}", name)),
         }
     }
 }
 
+/// Position of the large preview relative to the sweep window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreviewPosition {
+    #[default]
+    Right,
+    Bottom,
+    Left,
+    Top,
+    Hidden,
+}
+
+impl PreviewPosition {
+    /// Next position in the cycle right -> bottom -> left -> top -> hidden
+    pub fn next(self) -> Self {
+        use PreviewPosition::*;
+        match self {
+            Right => Bottom,
+            Bottom => Left,
+            Left => Top,
+            Top => Hidden,
+            Hidden => Right,
+        }
+    }
+}
+
+impl std::str::FromStr for PreviewPosition {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        use PreviewPosition::*;
+        match string.trim() {
+            "right" => Ok(Right),
+            "bottom" | "down" => Ok(Bottom),
+            "left" => Ok(Left),
+            "top" | "up" => Ok(Top),
+            "hidden" => Ok(Hidden),
+            _ => Err(anyhow::anyhow!("invalid preview position: {}", string)),
+        }
+    }
+}
+
+impl fmt::Display for PreviewPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PreviewPosition::*;
+        let name = match self {
+            Right => "right",
+            Bottom => "bottom",
+            Left => "left",
+            Top => "top",
+            Hidden => "hidden",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Placement of the large preview specified as `position(,size)`
+///
+/// Position is one of `right`, `bottom`, `left`, `top` or `hidden`, and size
+/// of the preview is parsed as [WindowLayoutSize] (i.e `right,50%` or `top,10`).
+/// Size is a width for `right` and `left`, and a height for `top` and `bottom`.
+/// Large preview is only shown with [WindowLayout::Full], so placement is not
+/// used with [WindowLayout::Float].
+#[derive(Debug, Clone)]
+pub struct PreviewLayout {
+    pub position: PreviewPosition,
+    pub size: WindowLayoutSize,
+}
+
+impl Default for PreviewLayout {
+    fn default() -> Self {
+        Self {
+            position: PreviewPosition::default(),
+            size: WindowLayoutSize::Fraction(0.5),
+        }
+    }
+}
+
+impl std::str::FromStr for PreviewLayout {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut iter = string.trim().split(',');
+        let position = iter.next().unwrap_or_default().parse()?;
+        let mut layout = PreviewLayout {
+            position,
+            ..Default::default()
+        };
+        for attr in iter {
+            let value = match attr.split_once('=') {
+                Some((key, value)) if matches!(key.trim(), "size" | "s") => value,
+                Some((key, _)) => anyhow::bail!("invalid preview layout attribute: {}", key),
+                None => attr,
+            };
+            layout.size = value.parse()?;
+        }
+        Ok(layout)
+    }
+}
+
+impl fmt::Display for PreviewLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.position, self.size)
+    }
+}
+
 pub struct SweepLayoutView<V, P> {
     sweep_view: V,
     large_preview: Option<P>,
+    preview_layout: Option<PreviewLayout>,
     sweep_layout: WindowLayout,
     term_position: Position,
+    theme: Theme,
+}
+
+impl<V, P> SweepLayoutView<V, P>
+where
+    V: View,
+    P: View,
+{
+    /// Sweep view decorated with the float window frame
+    fn frame_view<'a>(&'a self, frame: &WindowFrame) -> Border<&'a V> {
+        let border = Border::new(&self.sweep_view, frame.border, self.theme.border)
+            .with_padding(frame.padding);
+        match &frame.label {
+            Some(label) => border.with_label(label, self.theme.label),
+            None => border,
+        }
+    }
+
+    /// Split area of the specified size between sweep view and large preview
+    fn preview_split(&self, preview_layout: &PreviewLayout, size: Size) -> Flex<'_> {
+        use PreviewPosition::*;
+        let (axis, total) = match preview_layout.position {
+            Left | Right | Hidden => (Axis::Horizontal, size.width),
+            Top | Bottom => (Axis::Vertical, size.height),
+        };
+        let preview_size = match (&self.large_preview, preview_layout.position) {
+            (None, _) | (_, Hidden) => 0,
+            _ => preview_layout.size.calc(total),
+        };
+        let sized = |total| match axis {
+            Axis::Horizontal => Size {
+                width: total,
+                height: size.height,
+            },
+            Axis::Vertical => Size {
+                width: size.width,
+                height: total,
+            },
+        };
+        let sweep = (preview_size < total)
+            .then(|| Container::new(&self.sweep_view).with_size(sized(total - preview_size)));
+        let preview = (preview_size > 0).then(|| {
+            Container::new(self.large_preview.as_ref())
+                .with_size(sized(preview_size))
+                .with_horizontal(Align::Expand)
+                .with_vertical(Align::Expand)
+        });
+        let mut flex = Flex::new(axis);
+        if matches!(preview_layout.position, Left | Top) {
+            flex.push_child(preview);
+            flex.push_child(sweep);
+        } else {
+            flex.push_child(sweep);
+            flex.push_child(preview);
+        }
+        flex
+    }
 }
 
 impl<V, P> View for SweepLayoutView<V, P>
@@ -2573,15 +5182,20 @@ where
     ) -> Result<(), surf_n_term::Error> {
         // only structure and order is important for rendering
         match &self.sweep_layout {
-            WindowLayout::Float { .. } => {
+            WindowLayout::Float { frame, .. } => {
                 let surf = layout.apply_to(surf);
                 let child_layout = layout
                     .children()
                     .next()
                     .ok_or(surf_n_term::Error::InvalidLayout)?;
-                self.sweep_view.render(ctx, surf, child_layout)
+                self.frame_view(frame).render(ctx, surf, child_layout)
+            }
+            WindowLayout::Full { .. } if self.preview_layout.is_some() => {
+                let preview_layout = self.preview_layout.as_ref().expect("checked");
+                self.preview_split(preview_layout, layout.size())
+                    .render(ctx, surf, layout)
             }
-            WindowLayout::Full { height } => {
+            WindowLayout::Full { height, .. } => {
                 let main = Container::new(&self.sweep_view);
                 let preview = Container::new(self.large_preview.as_ref());
                 if height.is_positive() {
@@ -2607,6 +5221,8 @@ where
                 width,
                 row,
                 column,
+                frame,
+                ..
             } => {
                 let mut pos = self.term_position;
                 if !row.is_full() {
@@ -2618,13 +5234,28 @@ where
                     width: width.calc(ct.max().width).min(ct.max().width - pos.col),
                 };
                 pos.row = pos.row.min(ct.max().height - size.height);
+                // margin is excluded from the area occupied by the frame
+                let margin = frame.margin;
+                let pos = Position::new(pos.row + margin.top, pos.col + margin.left);
+                let size = Size {
+                    height: size.height.saturating_sub(margin.top + margin.bottom),
+                    width: size.width.saturating_sub(margin.left + margin.right),
+                };
                 let mut child_layout = layout.push_default();
-                self.sweep_view
-                    .layout(ctx, BoxConstraint::tight(size), child_layout.view_mut())?;
+                self.frame_view(frame).layout(
+                    ctx,
+                    BoxConstraint::tight(size),
+                    child_layout.view_mut(),
+                )?;
                 child_layout.set_position(pos);
                 layout.set_size(ct.max());
             }
-            WindowLayout::Full { height } => {
+            WindowLayout::Full { .. } if self.preview_layout.is_some() => {
+                let preview_layout = self.preview_layout.as_ref().expect("checked");
+                self.preview_split(preview_layout, ct.max())
+                    .layout(ctx, ct, layout)?;
+            }
+            WindowLayout::Full { height, .. } => {
                 let sweep_height = height.calc(ct.max().height);
                 let sweep = Container::new(&self.sweep_view);
                 let preview =
@@ -2649,7 +5280,7 @@ where
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum WindowLayoutSize {
     Absolute(i32),
     Fraction(f32),
@@ -2690,6 +5321,24 @@ impl WindowLayoutSize {
     }
 }
 
+impl fmt::Display for WindowLayoutSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowLayoutSize::Absolute(size) => write!(f, "{}", size),
+            WindowLayoutSize::Fraction(frac) => {
+                // shortest percentage that is parsed back to the same fraction
+                let perc = *frac as f64 * 100.0;
+                let perc = (0..=9)
+                    .map(|precision| format!("{perc:.precision$}"))
+                    .find(|perc| perc.parse::<f32>().is_ok_and(|perc| perc / 100.0 == *frac))
+                    .unwrap_or_else(|| perc.to_string());
+                write!(f, "{}%", perc)
+            }
+            WindowLayoutSize::Full => f.write_str("full"),
+        }
+    }
+}
+
 impl std::str::FromStr for WindowLayoutSize {
     type Err = Error;
 
@@ -2706,22 +5355,167 @@ impl std::str::FromStr for WindowLayoutSize {
     }
 }
 
+/// Key binding specified as `[mode:]chord:action(,action)*`
+///
+/// Action is either a name of the action as shown in help window (i.e
+/// `sweep.select`), or a user tag prefixed with `@` (i.e `@open`), which is
+/// reported with [SweepEvent::Bind] without the prefix. Unknown action names are
+/// rejected. Multiple actions are executed in order. Empty action unbinds the chord.
+/// Optional `normal:` prefix binds the chord in the vi normal mode key map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub mode: ViMode,
+    pub chord: KeyChord,
+    pub tags: Vec<String>,
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        // chord itself might contain `:` so split on the last one
+        let Some((chord, tag)) = string.rsplit_once(':') else {
+            anyhow::bail!(
+                "invalid key binding: {} (expected `[mode:]chord:action(,action)*`)",
+                string
+            );
+        };
+        let (mode, chord) = match chord.split_once(':') {
+            Some((mode, chord)) if mode.trim().parse::<ViMode>().is_ok() => {
+                (mode.trim().parse()?, chord)
+            }
+            _ => (ViMode::Insert, chord),
+        };
+        let tags = tag
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| match tag.strip_prefix('@') {
+                Some(tag) if !tag.is_empty() => Ok(tag.to_owned()),
+                _ if action_name_known(tag) => Ok(tag.to_owned()),
+                _ => Err(anyhow::anyhow!(
+                    "unknown action: {} (user tags must be prefixed with `@`)",
+                    tag
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            mode,
+            chord: key_chord_parse_mode(mode, chord.trim())?,
+            tags,
+        })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mode != ViMode::Insert {
+            write!(f, "{}:", self.mode)?;
+        }
+        write!(f, "{}:", self.chord)?;
+        for (index, tag) in self.tags.iter().enumerate() {
+            if index != 0 {
+                f.write_str(",")?;
+            }
+            if !action_name_known(tag) {
+                f.write_str("@")?;
+            }
+            f.write_str(tag)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the name is a name of the bindable action as shown in help window
+fn action_name_known(name: &str) -> bool {
+    SweepAction::all()
+        .chain([SweepAction::ModeNormal, SweepAction::ModeInsert])
+        .chain(
+            vi_normal_bindings()
+                .into_iter()
+                .flat_map(|(_, actions, _)| actions),
+        )
+        .any(|action| action.description().name == name)
+}
+
 #[derive(Clone)]
 struct SweepPreview<P> {
     id: SweepItemId,
     theme: Theme,
     preview: P,
     height: Arc<AtomicUsize>,
+    // area used to create the preview
+    area: Option<(Position, Size)>,
+    // query used to create the preview
+    query: String,
+    // area where preview is actually rendered
+    area_rendered: Arc<RwLock<Option<(Position, Size)>>>,
+    term_waker: TerminalWaker,
 }
 
 impl<P> SweepPreview<P> {
-    fn new(id: SweepItemId, theme: Theme, preview: P) -> Self {
+    fn new(
+        id: SweepItemId,
+        theme: Theme,
+        preview: P,
+        area: Option<(Position, Size)>,
+        query: String,
+        area_rendered: Arc<RwLock<Option<(Position, Size)>>>,
+        term_waker: TerminalWaker,
+    ) -> Self {
         Self {
             id,
             preview,
             theme,
             height: Arc::new(AtomicUsize::new(0)),
+            area,
+            query,
+            area_rendered,
+            term_waker,
+        }
+    }
+}
+
+/// View that records area of the terminal occupied by the child view
+///
+/// If area differs from the one preview was created with, terminal is woken
+/// up so the preview would be re-created for the new area.
+struct PreviewAreaTrace<V> {
+    view: V,
+    area: Option<(Position, Size)>,
+    area_rendered: Arc<RwLock<Option<(Position, Size)>>>,
+    term_waker: TerminalWaker,
+}
+
+impl<V: View> View for PreviewAreaTrace<V> {
+    fn render(
+        &self,
+        ctx: &ViewContext,
+        mut surf: TerminalSurface<'_>,
+        layout: ViewLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        // absolute position is recovered from the offset into terminal surface
+        let shape = layout.apply_to(surf.as_mut()).shape();
+        let position = match shape.row_stride {
+            0 => Position::origin(),
+            stride => Position::new(shape.start / stride, shape.start % stride),
+        };
+        let area = Some((position, Size::new(shape.height, shape.width)));
+        self.area_rendered
+            .with_mut(|area_rendered| *area_rendered = area);
+        if area != self.area {
+            self.term_waker.wake()?;
         }
+        self.view.render(ctx, surf, layout)
+    }
+
+    fn layout(
+        &self,
+        ctx: &ViewContext,
+        ct: BoxConstraint,
+        layout: ViewMutLayout<'_>,
+    ) -> Result<(), surf_n_term::Error> {
+        self.view.layout(ctx, ct, layout)
     }
 }
 
@@ -2732,15 +5526,24 @@ where
     type View = Flex<'static>;
 
     fn into_view(self) -> Self::View {
-        let preview = self.preview.clone().trace_layout({
-            let height = self.height.clone();
-            move |_, layout| {
-                height.store(layout.size().height, Ordering::Relaxed);
-            }
-        });
+        let preview = PreviewAreaTrace {
+            view: self.preview.clone().trace_layout({
+                let height = self.height.clone();
+                move |_, layout| {
+                    height.store(layout.size().height, Ordering::Relaxed);
+                }
+            }),
+            area: self.area,
+            area_rendered: self.area_rendered.clone(),
+            term_waker: self.term_waker.clone(),
+        };
         let scrollbar = ScrollBarFn::new(
             Axis::Vertical,
-            Face::new(Some(self.theme.accent), None, FaceAttrs::default()),
+            if self.theme.no_color {
+                self.theme.scrollbar
+            } else {
+                Face::new(Some(self.theme.accent), None, FaceAttrs::default())
+            },
             {
                 let layout = self.preview.preview_layout();
                 let height = self.height.clone();
@@ -2763,9 +5566,659 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Candidate, CandidateContext};
 
     #[test]
     fn test_icons_parsing() {
         let _ = ICONS.len();
     }
+
+    #[test]
+    fn test_key_binding_parse() -> Result<(), Error> {
+        let binding: KeyBinding = "ctrl+x ctrl+k:input.delete.end".parse()?;
+        assert_eq!(binding.chord, "ctrl+x ctrl+k".parse()?);
+        assert_eq!(binding.tags, vec!["input.delete.end".to_owned()]);
+
+        let binding: KeyBinding = "alt+m: sweep.mark.current , list.item.next".parse()?;
+        assert_eq!(binding.tags, vec!["sweep.mark.current", "list.item.next"]);
+        assert_eq!(
+            binding.to_string(),
+            "alt+m:sweep.mark.current,list.item.next"
+        );
+
+        let binding: KeyBinding = "ctrl+j:".parse()?;
+        assert!(binding.tags.is_empty());
+
+        assert!("ctrl+j".parse::<KeyBinding>().is_err());
+
+        // unknown actions are rejected unless marked as user tags
+        assert!("ctrl+q:sweep.qiut".parse::<KeyBinding>().is_err());
+        assert!("ctrl+q:@".parse::<KeyBinding>().is_err());
+        let binding: KeyBinding = "ctrl+o:@open,sweep.quit".parse()?;
+        assert_eq!(binding.tags, vec!["open", "sweep.quit"]);
+        assert_eq!(binding.to_string(), "ctrl+o:@open,sweep.quit");
+        assert_eq!(binding.to_string().parse::<KeyBinding>()?, binding);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vi_normal_key_map() -> Result<(), Error> {
+        let (mut window, _requests, _events) = test_window(&[])?;
+        window.vi_enable();
+        let key_map = &window.vi_key_map;
+        let chord: KeyChord = "g g".parse()?;
+        assert!(matches!(
+            key_map.lookup(&chord.keys()[..1]),
+            KeyMapResult::Continue
+        ));
+        assert!(matches!(
+            key_map.lookup(chord.keys()),
+            KeyMapResult::Success(SweepAction::List(ListAction::Home))
+        ));
+        let upper_g = [Key::new(KeyName::Char('G'), KeyMod::EMPTY)];
+        assert!(matches!(
+            key_map.lookup(&upper_g),
+            KeyMapResult::Success(SweepAction::List(ListAction::End))
+        ));
+        let chord: KeyChord = "c w".parse()?;
+        let KeyMapResult::Success(SweepAction::Chain { actions, .. }) =
+            key_map.lookup(chord.keys())
+        else {
+            panic!("change word must be bound to a chain");
+        };
+        assert!(matches!(actions.last(), Some(SweepAction::ModeInsert)));
+
+        // normal mode keys can be rebound
+        let binding: KeyBinding = "normal:G:list.home".parse()?;
+        assert_eq!(binding.mode, ViMode::Normal);
+        window.bind(binding.mode, binding.chord, binding.tags, String::new());
+        assert!(matches!(
+            window.vi_key_map.lookup(&upper_g),
+            KeyMapResult::Success(SweepAction::List(ListAction::Home))
+        ));
+        assert!(matches!(
+            window.key_map.lookup(&upper_g),
+            KeyMapResult::Failure
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_layout_parse() -> Result<(), Error> {
+        let layout: PreviewLayout = "top,10".parse()?;
+        assert_eq!(layout.position, PreviewPosition::Top);
+        assert_eq!(layout.size.calc(100), 10);
+
+        let layout: PreviewLayout = "left, size=-30%".parse()?;
+        assert_eq!(layout.position, PreviewPosition::Left);
+        assert_eq!(layout.size.calc(100), 70);
+
+        let layout: PreviewLayout = "hidden".parse()?;
+        assert_eq!(layout.position, PreviewPosition::Hidden);
+        assert_eq!(layout.to_string(), "hidden,50%");
+
+        let layout: PreviewLayout = "bottom,33.3333%".parse()?;
+        assert_eq!(layout.to_string(), "bottom,33.3333%");
+        for size in ["33.3%", "-12.5%", "0.1%", "100%"] {
+            let size_parsed: WindowLayoutSize = size.parse()?;
+            assert_eq!(size_parsed.to_string(), size);
+            assert_eq!(
+                size_parsed.to_string().parse::<WindowLayoutSize>()?,
+                size_parsed
+            );
+        }
+
+        assert!("middle".parse::<PreviewLayout>().is_err());
+        assert!("right,width=10".parse::<PreviewLayout>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_position_next() -> Result<(), Error> {
+        let (mut window, _requests, _events) = test_window(&[])?;
+        // full layout with negative height places preview on the top
+        let layout: WindowLayout = "full,h=-30%".parse()?;
+        window.preview_position = layout.preview_position();
+        assert_eq!(window.preview_position, PreviewPosition::Top);
+        window.handle_action(&SweepAction::PreviewPositionNext)?;
+        assert_eq!(
+            window.preview_layout.as_ref().map(|layout| layout.position),
+            Some(PreviewPosition::Hidden)
+        );
+        window.handle_action(&SweepAction::PreviewPositionNext)?;
+        assert_eq!(
+            window.preview_layout.as_ref().map(|layout| layout.position),
+            Some(PreviewPosition::Right)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rpc_methods_documented() -> Result<(), Error> {
+        // sweep without terminal and ui worker, only used to register methods
+        let (requests, _requests_recv) = unbounded();
+        let (_events_send, events) = mpsc::unbounded_channel();
+        let ctx = CandidateContext::new();
+        let sweep = Sweep::<Candidate> {
+            inner: Arc::new(SweepInner {
+                options: SweepOptions::default(),
+                haystack_context: ctx.clone(),
+                term_waker: TerminalWaker::new(|| Ok(())),
+                ui_worker: None,
+                ranker_thread: RankerThread::new(|_, _| true),
+                requests,
+                events: Mutex::new(events),
+                terminated: std::sync::Mutex::new(None),
+            }),
+        };
+        let mut peer = None;
+        let (read, write) = tokio::io::split(tokio::io::duplex(16).0);
+        let _serve = sweep.serve_seed(ctx, None, read, write, |rpc_peer| {
+            peer.replace(rpc_peer);
+        });
+        let desc = peer.expect("setup was not called").discover();
+
+        // methods that do not accept any parameters
+        let no_params = ["terminate", "theme_reload", "window_pop"];
+        let methods = desc["methods"].as_array().expect("methods list");
+        assert!(!methods.is_empty());
+        for method in methods {
+            let name = method["name"].as_str().unwrap_or_default();
+            let description = method["description"].as_str().unwrap_or_default();
+            assert!(!description.is_empty(), "{name}: missing description");
+            let params = method["params"].as_array().expect("params list");
+            assert_eq!(
+                params.is_empty(),
+                no_params.contains(&name),
+                "{name}: params are not described"
+            );
+            for param in params {
+                assert!(param["name"].as_str().is_some_and(|name| !name.is_empty()));
+                assert!(param["schema"].is_object(), "{name}: {param}");
+            }
+        }
+        Ok(())
+    }
+
+    type TestEvents<H = String> = Arc<std::sync::Mutex<Vec<SweepEvent<H>>>>;
+    type TestWindow<H = String> = (SweepWindow<H>, Sender<SweepWindowRequest<H>>, TestEvents<H>);
+
+    /// Create window with the items, requests are sent with the returned sender
+    fn test_window(items: &[&str]) -> Result<TestWindow, Error> {
+        test_window_with((), items.iter().map(|item| item.to_string()).collect())
+    }
+
+    /// Create window with the haystack context and items
+    fn test_window_with<H: Haystack>(
+        haystack_context: H::Context,
+        items: Vec<H>,
+    ) -> Result<TestWindow<H>, Error> {
+        let events: TestEvents<H> = Default::default();
+        let (send, recv) = unbounded();
+        let mut window = SweepWindow::new_from_options(
+            SweepOptions::default(),
+            WindowId::Number(0),
+            haystack_context,
+            TerminalWaker::new(|| Ok(())),
+            Some(recv),
+            Arc::new({
+                let events = events.clone();
+                move |event| {
+                    events.with_mut(|events| events.push(event));
+                    Ok(WindowAction::Nothing)
+                }
+            }),
+            RankerThread::new(|_, _| true),
+        )?;
+        window.haystack_extend(items);
+        test_window_sync(&mut window);
+        Ok((window, send, events))
+    }
+
+    /// Wait for the ranker to process all pending requests
+    fn test_window_sync<H: Haystack>(window: &mut SweepWindow<H>) {
+        let synced = window.ranker.sync();
+        while !synced.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        window.ranker_refresh();
+    }
+
+    fn test_window_action(
+        window: &mut SweepWindow<String>,
+        requests: &Sender<SweepWindowRequest<String>>,
+        name: &str,
+        args: Option<Value>,
+    ) -> Result<WindowAction, Error> {
+        let (send, mut recv) = oneshot::channel();
+        requests.send(SweepWindowRequest::ActionRun {
+            name: name.to_owned(),
+            args,
+            resolve: send,
+        })?;
+        let window_action = window.process()?;
+        recv.try_recv()?.expect("action_run is not resolved")?;
+        Ok(window_action)
+    }
+
+    #[test]
+    fn test_list_reverse_keys() -> Result<(), Error> {
+        let (mut window, requests, _events) = test_window(&["one", "two", "three"])?;
+        window.orientation = WindowOrientation::reverse();
+        // keys move cursor in the visual direction of the reversed list
+        window.handle_key(Key::new(KeyName::Up, KeyMod::EMPTY))?;
+        let current = window.current().map(|item| item.haystack.clone());
+        assert_eq!(current.as_deref(), Some("two"));
+        window.handle_key(Key::new(KeyName::Down, KeyMod::EMPTY))?;
+        let current = window.current().map(|item| item.haystack.clone());
+        assert_eq!(current.as_deref(), Some("one"));
+        // actions run by name are not affected
+        test_window_action(&mut window, &requests, "list.item.next", None)?;
+        let current = window.current().map(|item| item.haystack.clone());
+        assert_eq!(current.as_deref(), Some("two"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_scroll() -> Result<(), Error> {
+        let (mut window, _requests, _events) = test_window(&["one", "two", "three"])?;
+        // nothing is cut off, scroll is not possible
+        window.handle_action(&SweepAction::ListScrollRight)?;
+        assert_eq!(window.list_offset, 0);
+        // scroll stops at the end of the widest visible item
+        window.list_overflow.set(LIST_SCROLL_STEP + 3);
+        window.handle_action(&SweepAction::ListScrollRight)?;
+        assert_eq!(window.list_offset, LIST_SCROLL_STEP);
+        window.handle_action(&SweepAction::ListScrollRight)?;
+        assert_eq!(window.list_offset, LIST_SCROLL_STEP + 3);
+        window.handle_action(&SweepAction::ListScrollRight)?;
+        assert_eq!(window.list_offset, LIST_SCROLL_STEP + 3);
+        window.handle_action(&SweepAction::ListScrollLeft)?;
+        assert_eq!(window.list_offset, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_action_run() -> Result<(), Error> {
+        let (mut window, requests, events) = test_window(&["one", "two", "three"])?;
+        test_window_action(&mut window, &requests, "list.item.next", None)?;
+        let current = window.current().map(|item| item.haystack.clone());
+        assert_eq!(current.as_deref(), Some("two"));
+
+        test_window_action(
+            &mut window,
+            &requests,
+            "input.insert.char",
+            Some("th".into()),
+        )?;
+        assert_eq!(window.input.get().collect::<String>(), "th");
+        assert!(test_window_action(&mut window, &requests, "input.insert.char", None).is_err());
+        assert!(test_window_action(&mut window, &requests, "no.such.action", None).is_err());
+
+        test_window_action(&mut window, &requests, "sweep.select.2", None)?;
+        let selected = events.with_mut(mem::take);
+        assert!(matches!(
+            selected.as_slice(),
+            [SweepEvent::Select { items, .. }] if items == &["three".to_owned()]
+        ));
+
+        // failure of the action is reported to the caller
+        window.event_handler = Arc::new(|_| Err(anyhow::anyhow!("handler failed")));
+        window.bind(
+            ViMode::Insert,
+            KeyChord::from_iter([Key::new(KeyName::Char('x'), KeyMod::CTRL)]),
+            vec!["custom".to_owned()],
+            String::new(),
+        );
+        let (send, mut recv) = oneshot::channel();
+        requests.send(SweepWindowRequest::ActionRun {
+            name: "custom".to_owned(),
+            args: None,
+            resolve: send,
+        })?;
+        window.process()?;
+        let result = recv.try_recv()?;
+        assert!(matches!(result, Some(Err(error)) if error.to_string() == "handler failed"));
+
+        let window_action = test_window_action(&mut window, &requests, "sweep.quit", None)?;
+        assert!(matches!(window_action, WindowAction::Close { uid: None }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_settings() -> Result<(), Error> {
+        let (mut window, requests, events) = test_window(&["one", "two"])?;
+
+        // changes are not reported unless subscribed
+        requests.send(SweepWindowRequest::RankerKeepOrder(None))?;
+        window.process()?;
+        assert!(window.keep_order);
+        assert!(events.with(|events| events.is_empty()));
+        requests.send(SweepWindowRequest::Subscribe(
+            SweepSubscription::from_events([
+                "scorer_changed",
+                "keep_order_changed",
+                "theme_changed",
+                "layout_changed",
+            ])?,
+        ))?;
+
+        let (send, mut recv) = oneshot::channel();
+        requests.send(SweepWindowRequest::ScorerByName(
+            Some("substr".to_owned()),
+            send,
+        ))?;
+        requests.send(SweepWindowRequest::RankerKeepOrder(None))?;
+        requests.send(SweepWindowRequest::LayoutSet(Some("full,reverse".parse()?)))?;
+        requests.send(SweepWindowRequest::ThemeSet("dark".parse()?))?;
+        window.process()?;
+        assert_eq!(recv.try_recv()?, Some(true));
+        assert!(!window.keep_order);
+        assert_eq!(window.layout(), Some("full,reverse".parse()?));
+
+        let events = events.with_mut(mem::take);
+        assert!(matches!(
+            events.as_slice(),
+            [
+                SweepEvent::ScorerChanged { name, .. },
+                SweepEvent::KeepOrderChanged {
+                    keep_order: false,
+                    ..
+                },
+                SweepEvent::LayoutChanged {
+                    layout: Some(WindowLayout::Full { .. }),
+                    ..
+                },
+                SweepEvent::ThemeChanged { theme, .. },
+            ] if name == "substr" && theme.bg == Theme::dark().bg
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_marks() -> Result<(), Error> {
+        let (mut window, requests, _events) = test_window(&["one", "two", "three"])?;
+        let marked = |window: &SweepWindow<String>| -> Vec<String> {
+            window
+                .marked
+                .with(|marked| marked.items().cloned().collect())
+        };
+
+        requests.send(SweepWindowRequest::MarksSet(vec![2, 0, 2, 7]))?;
+        window.process()?;
+        assert_eq!(marked(&window), ["three", "one"]);
+
+        requests.send(SweepWindowRequest::MarksToggle(vec![0, 1]))?;
+        requests.send(SweepWindowRequest::HaystackUpdate {
+            index: 1,
+            item: "TWO".to_owned(),
+        })?;
+        window.process()?;
+        assert_eq!(marked(&window), ["three", "TWO"]);
+
+        requests.send(SweepWindowRequest::MarksClear)?;
+        requests.send(SweepWindowRequest::MarksPolicySet(MarksPolicy {
+            limit: Some(1),
+            keep_on_clear: true,
+        }))?;
+        requests.send(SweepWindowRequest::MarksToggle(vec![1, 0]))?;
+        window.process()?;
+        assert_eq!(marked(&window), ["TWO"]);
+
+        // marks survive clear, but are not attached to the new items
+        requests.send(SweepWindowRequest::HaystackClear)?;
+        requests.send(SweepWindowRequest::HaystackExtend(vec!["four".to_owned()]))?;
+        window.process()?;
+        test_window_sync(&mut window);
+        assert_eq!(marked(&window), ["TWO"]);
+        let current = window.current().expect("current item");
+        assert!(!window.marked.with(|marked| marked.contains_id(current.id)));
+
+        requests.send(SweepWindowRequest::MarksPolicySet(MarksPolicy {
+            limit: None,
+            keep_on_clear: false,
+        }))?;
+        requests.send(SweepWindowRequest::HaystackClear)?;
+        window.process()?;
+        assert!(marked(&window).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_items_keyed() -> Result<(), Error> {
+        let ctx = CandidateContext::new();
+        let item = |key: &str, target: &str| {
+            ctx.candidate_from_value(json!({"key": key, "target": [target]}))
+        };
+        let items = vec![item("1", "one")?, item("2", "two")?, item("3", "three")?];
+        let (mut window, requests, _events) = test_window_with(ctx.clone(), items)?;
+        let current = |window: &SweepWindow<Candidate>| -> Option<String> {
+            Some(window.current()?.haystack.to_string())
+        };
+        let marked = |window: &SweepWindow<Candidate>| -> Vec<String> {
+            window
+                .marked
+                .with(|marked| marked.items().map(|item| item.to_string()).collect())
+        };
+
+        window.list.cursor_set(2);
+        requests.send(SweepWindowRequest::MarksSet(vec![1, 2]))?;
+        requests.send(SweepWindowRequest::HaystackUpsert(vec![
+            item("2", "TWO")?,
+            item("4", "four")?,
+            item("4", "FOUR")?,
+        ]))?;
+        window.process()?;
+        test_window_sync(&mut window);
+        assert_eq!(window.haystack.len(), 4);
+        assert_eq!(current(&window).as_deref(), Some("three"));
+        assert_eq!(marked(&window), ["TWO", "three"]);
+
+        // cursor and marks follow items when preceding items are removed
+        requests.send(SweepWindowRequest::HaystackRemove(vec![
+            "1".to_owned(),
+            "5".to_owned(),
+        ]))?;
+        window.process()?;
+        test_window_sync(&mut window);
+        assert_eq!(current(&window).as_deref(), Some("three"));
+        assert_eq!(marked(&window), ["TWO", "three"]);
+        let current_id = window.current().expect("current item").id;
+        assert!(window.marked.with(|marked| marked.contains_id(current_id)));
+
+        // search string is updated by upsert
+        requests.send(SweepWindowRequest::HaystackRemove(vec!["3".to_owned()]))?;
+        requests.send(SweepWindowRequest::HaystackUpsert(vec![item("4", "fox")?]))?;
+        requests.send(SweepWindowRequest::NeedleSet("fox".to_owned()))?;
+        window.process()?;
+        test_window_sync(&mut window);
+        assert_eq!(marked(&window), ["TWO"]);
+        assert_eq!(current(&window).as_deref(), Some("fox"));
+        assert_eq!(window.haystack_keys.get("4"), Some(&1));
+
+        // extend replaces items with the same key
+        requests.send(SweepWindowRequest::NeedleSet(String::new()))?;
+        requests.send(SweepWindowRequest::HaystackExtend(vec![
+            item("2", "deux")?,
+            item("5", "five")?,
+        ]))?;
+        window.process()?;
+        test_window_sync(&mut window);
+        let haystack: Vec<_> = window
+            .haystack
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        assert_eq!(haystack, ["deux", "fox", "five"]);
+        assert_eq!(marked(&window), ["deux"]);
+
+        // update with the key of another item is ignored
+        requests.send(SweepWindowRequest::HaystackUpdate {
+            index: 0,
+            item: item("4", "quatre")?,
+        })?;
+        window.process()?;
+        assert_eq!(window.haystack[0].to_string(), "deux");
+        assert_eq!(window.haystack_keys.get("4"), Some(&1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscription() -> Result<(), Error> {
+        let subscription = SweepSubscription::from_events(["query_changed", "marks_changed"])?;
+        assert_eq!(
+            subscription,
+            SweepSubscription {
+                query: true,
+                marks: true,
+                ..Default::default()
+            }
+        );
+        assert!(SweepSubscription::from_events(Vec::<String>::new())?.is_empty());
+        assert!(SweepSubscription::from_events(["selected"]).is_err());
+
+        let mut marked = MarkedItems::new();
+        marked.toggle(3, "three");
+        assert_eq!(marked.generation(), 1);
+        assert_eq!(marked.items().collect::<Vec<_>>(), vec![&"three"]);
+        assert_eq!(marked.take().collect::<Vec<_>>(), vec!["three"]);
+        assert_eq!(marked.generation(), 2);
+        _ = marked.take();
+        assert_eq!(marked.generation(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscription_events() -> Result<(), Error> {
+        let (mut window, requests, events) = test_window(&["one", "two", "three"])?;
+        // only change events, formatted as `name:value`
+        let changes = || {
+            events.with_mut(|events| {
+                mem::take(events)
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        SweepEvent::QueryChanged { query, .. } => Some(format!("query:{query}")),
+                        SweepEvent::CursorChanged { item, .. } => {
+                            Some(format!("cursor:{}", item.unwrap_or_default()))
+                        }
+                        SweepEvent::MarksChanged { items, .. } => {
+                            Some(format!("marks:{}", items.join(",")))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // nothing is reported before subscription
+        window.handle_key(Key::new(KeyName::Char('o'), KeyMod::EMPTY))?;
+        test_window_sync(&mut window);
+        test_window_action(&mut window, &requests, "list.item.next", None)?;
+        test_window_action(&mut window, &requests, "sweep.mark.current", None)?;
+        assert!(changes().is_empty());
+
+        requests.send(SweepWindowRequest::Subscribe(SweepSubscription {
+            query: true,
+            cursor: true,
+            marks: true,
+            ..Default::default()
+        }))?;
+        window.process()?;
+        assert!(changes().is_empty());
+
+        // query is reported right away, cursor once ranking is done
+        window.handle_key(Key::new(KeyName::Char('n'), KeyMod::EMPTY))?;
+        assert_eq!(changes(), ["query:on"]);
+        test_window_sync(&mut window);
+        window.process()?;
+        assert_eq!(changes(), ["cursor:one"]);
+
+        // cursor and marks changed by actions
+        test_window_action(&mut window, &requests, "sweep.mark.current", None)?;
+        assert_eq!(changes(), ["marks:two,one"]);
+        window.handle_key(Key::new(KeyName::Backspace, KeyMod::EMPTY))?;
+        test_window_sync(&mut window);
+        window.process()?;
+        test_window_action(&mut window, &requests, "list.item.next", None)?;
+        assert_eq!(changes(), ["query:o", "cursor:two"]);
+
+        // no changes, nothing is reported
+        window.process()?;
+        assert!(changes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_layout_parse() -> Result<(), Error> {
+        let layout: WindowLayout = "float,h=15".parse()?;
+        assert_eq!(layout.orientation(), WindowOrientation::default());
+
+        let layout: WindowLayout = "float,h=15,reverse".parse()?;
+        assert_eq!(layout.orientation(), WindowOrientation::reverse());
+
+        // attributes without value are ignored unless they are flags
+        let layout: WindowLayout = "float,h".parse()?;
+        assert_eq!(layout, WindowLayout::default());
+        let layout: WindowLayout = "float,unknown,b".parse()?;
+        let WindowLayout::Float { frame, .. } = layout else {
+            panic!("float layout expected");
+        };
+        assert_eq!(frame.border, BorderStyle::Rounded);
+
+        let layout: WindowLayout = "full,prompt=bottom,list=down".parse()?;
+        assert!(matches!(layout, WindowLayout::Full { .. }));
+        assert_eq!(
+            layout.orientation(),
+            WindowOrientation {
+                prompt_bottom: true,
+                list_reverse: false,
+            }
+        );
+
+        for layout in [
+            "full,h=full",
+            "full,h=50%,prompt=bottom,list=up",
+            "float,h=15,w=-10,r=full,c=0,list=up,border=double,padding=0:1:0:1,label=Files",
+        ] {
+            assert_eq!(layout.parse::<WindowLayout>()?.to_string(), layout);
+        }
+
+        let layout: WindowLayout = "float,h=15,border=double,padding=0:1,label=Files".parse()?;
+        let WindowLayout::Float { frame, .. } = layout else {
+            panic!("float layout expected");
+        };
+        assert_eq!(frame.border, BorderStyle::Double);
+        assert_eq!(frame.label.as_deref(), Some("Files"));
+        assert_eq!(
+            frame.padding,
+            Margins {
+                left: 1,
+                right: 1,
+                top: 0,
+                bottom: 0,
+            }
+        );
+        assert_eq!(frame.margin, Margins::default());
+
+        let layout: WindowLayout = "float,border,margin=1:2:3:4".parse()?;
+        let WindowLayout::Float { frame, .. } = layout else {
+            panic!("float layout expected");
+        };
+        assert_eq!(frame.border, BorderStyle::Rounded);
+        assert_eq!(
+            frame.margin,
+            Margins {
+                left: 4,
+                right: 2,
+                top: 1,
+                bottom: 3,
+            }
+        );
+
+        assert!("float,border=dotted".parse::<WindowLayout>().is_err());
+        assert!("float,padding=1:2:3".parse::<WindowLayout>().is_err());
+        assert!("float,prompt=middle".parse::<WindowLayout>().is_err());
+        assert!("full,list=left".parse::<WindowLayout>().is_err());
+        Ok(())
+    }
 }
@@ -1,27 +1,34 @@
 use crate::{
     FieldSelector, Haystack, HaystackBasicPreview, HaystackDefaultView, HaystackPreview, Positions,
+    PreviewEnv, Score, Scorer,
     common::{AbortJoinHandle, LockExt},
 };
 use anyhow::Context;
-use futures::{FutureExt, TryFutureExt, future};
+use futures::TryFutureExt;
+use serde::Deserialize;
 use std::{
     borrow::Cow,
     cmp::max,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, VecDeque},
     io::Write,
     ops::Deref,
+    path::PathBuf,
     process::Stdio,
     str::FromStr,
     sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 use surf_n_term::{
     BBox, Cell, CellWrite, Color, Error, Face, FaceAttrs, Glyph, Key, KeyChord, KeyMod, KeyName,
     Position, RGBA, Size, SurfaceMut, TerminalEvent, TerminalSurface, TerminalSurfaceExt,
     TerminalWaker,
+    encoder::ColorDepth,
     rasterize::{PathBuilder, SVG_COLORS, StrokeStyle},
+    render::CellKind,
     view::{
-        Axis, BoxConstraint, BoxView, Container, Flex, IntoView, Justify, Layout, ScrollBarFn,
-        ScrollBarPosition, Text, Tree, TreeMut, View, ViewContext, ViewLayout, ViewMutLayout,
+        Axis, BoxConstraint, BoxView, Container, Flex, IntoView, Justify, Layout, Margins,
+        ScrollBarFn, ScrollBarPosition, Text, Tree, TreeMut, View, ViewContext, ViewLayout,
+        ViewMutLayout,
     },
 };
 use tokio::{io::AsyncReadExt, process::Command, sync::mpsc};
@@ -46,8 +53,15 @@ pub struct ThemeInner {
     pub separator: Face,
     pub separator_right: Text,
     pub separator_left: Text,
+    pub border: Face,
     pub show_preview: bool,
     pub named_colors: Arc<HashMap<String, RGBA>>,
+    /// Theme file this theme was loaded from, used to reload the theme
+    pub file: Option<PathBuf>,
+    /// Color depth this theme was adapted to with [Theme::quantize]
+    pub depth: Option<ColorDepth>,
+    /// Theme only uses attributes, previews are not colored either
+    pub no_color: bool,
 }
 
 #[derive(Clone)]
@@ -136,6 +150,7 @@ impl Theme {
         let separator = Face::new(Some(accent), input.bg, FaceAttrs::EMPTY);
         let separator_right = Text::new().with_fmt(" ", Some(separator)).take();
         let separator_left = Text::new().with_fmt("", Some(separator)).take();
+        let border = Face::new(Some(accent), Some(bg), FaceAttrs::EMPTY);
         let mut named_colors = SVG_COLORS.clone();
         named_colors.insert("fg".to_owned(), fg);
         named_colors.insert("bg".to_owned(), bg);
@@ -160,8 +175,12 @@ impl Theme {
             separator,
             separator_right,
             separator_left,
+            border,
             show_preview: true,
             named_colors: Arc::new(named_colors),
+            file: None,
+            depth: None,
+            no_color: false,
         };
         Self {
             inner: Arc::new(inner),
@@ -230,8 +249,44 @@ impl Theme {
             separator: Face::new(Some(accent), input.bg, FaceAttrs::EMPTY),
             separator_right: Text::new().with_fmt(" ", Some(default)),
             separator_left: Text::new(),
+            border: Face::new(Some(color2), Some(bg), FaceAttrs::EMPTY),
             show_preview: true,
             named_colors: Arc::new(named_colors),
+            file: None,
+            depth: None,
+            no_color: false,
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Theme without any colors, only uses bold, underline and reverse attributes
+    ///
+    /// Used when `NO_COLOR` environment variable is set, see <https://no-color.org>
+    pub fn no_color() -> Self {
+        let default = Face::default();
+        let reverse = default.with_attrs(FaceAttrs::REVERSE);
+        let theme = Theme::light();
+        let inner = ThemeInner {
+            cursor: reverse,
+            input: default,
+            list_default: default,
+            list_selected: default.with_attrs(FaceAttrs::BOLD),
+            list_selected_indicator: Text::new().with_fmt(" > ", Some(default)).take(),
+            list_marked_indicator: Text::new().with_fmt(" * ", Some(default)).take(),
+            list_text: default,
+            list_highlight: default.with_attrs(FaceAttrs::UNDERLINE),
+            list_inactive: default,
+            scrollbar: reverse,
+            stats: reverse,
+            label: default.with_attrs(FaceAttrs::REVERSE | FaceAttrs::BOLD),
+            separator: default,
+            separator_right: Text::new().with_fmt(" ", Some(default)).take(),
+            separator_left: Text::new(),
+            border: default,
+            no_color: true,
+            ..theme.inner.deref().clone()
         };
         Self {
             inner: Arc::new(inner),
@@ -248,6 +303,144 @@ impl Theme {
         }
     }
 
+    /// Load theme from TOML or JSON (if extension is `.json`) theme file
+    ///
+    /// See [ThemeFile] for the description of the format.
+    #[cfg(feature = "theme-file")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read theme: {}", path.display()))?;
+        let theme_file: ThemeFile = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&data)?
+        } else {
+            toml::from_str(&data)?
+        };
+        let theme = theme_file
+            .build()
+            .with_context(|| format!("invalid theme: {}", path.display()))?;
+        Ok(theme.modify(|inner| inner.file = Some(path.to_owned())))
+    }
+
+    /// Reload theme from the file it was loaded from, current value of
+    /// `show_preview` is preserved
+    pub fn reload(&self) -> Result<Self, anyhow::Error> {
+        let Some(file) = &self.file else {
+            return Ok(self.clone());
+        };
+        #[cfg(feature = "theme-file")]
+        {
+            let show_preview = self.show_preview;
+            Ok(Self::from_file(file)?.modify(|inner| inner.show_preview = show_preview))
+        }
+        #[cfg(not(feature = "theme-file"))]
+        anyhow::bail!("theme files are not supported: {}", file.display())
+    }
+
+    /// Adapt theme to the color depth supported by the terminal
+    ///
+    /// Colors are snapped to the 256 color palette for [ColorDepth::EightBit], and to
+    /// the four grays of the basic 16 ANSI colors for [ColorDepth::Gray]. Faces that
+    /// become unreadable are fixed up, in particular `list_selected` stays distinct
+    /// from `list_default` and `list_highlight` stays distinct from both of them,
+    /// falling back to bold and underline if there are not enough colors.
+    pub fn quantize(&self, depth: ColorDepth) -> Theme {
+        if self.depth == Some(depth) {
+            return self.clone();
+        }
+        if depth == ColorDepth::TrueColor || self.no_color {
+            return self.modify(|inner| inner.depth = Some(depth));
+        }
+
+        let snap = |color: RGBA| color_quantize(depth, self.bg.blend_over(color));
+        let fg = snap(self.fg);
+        let bg = snap(self.bg);
+        // first color on the way from `bg` to `fg` that is not in `avoid`
+        let step = |avoid: &[RGBA]| {
+            (1..=10)
+                .map(|index| snap(self.bg.blend_over(self.fg.with_alpha(index as f64 / 10.0))))
+                .find(|color| !avoid.contains(color))
+        };
+        // make sure foreground is distinguishable from background
+        let readable = |face: Face| match (face.fg, face.bg) {
+            (Some(face_fg), Some(face_bg)) if face_fg == face_bg => {
+                face.with_fg(Some(face_bg.best_contrast(bg, fg)))
+            }
+            _ => face,
+        };
+        let face =
+            |face: Face| readable(Face::new(face.fg.map(snap), face.bg.map(snap), face.attrs));
+        let emphasis = FaceAttrs::BOLD | FaceAttrs::UNDERLINE;
+
+        let input = face(self.input);
+        let input_bg = input.bg.unwrap_or(bg);
+        let mut cursor = face(self.cursor);
+        if cursor.bg.is_some_and(|cursor_bg| cursor_bg == input_bg) {
+            cursor = match step(&[input_bg]) {
+                Some(cursor_bg) => readable(cursor.with_bg(Some(cursor_bg)).with_fg(cursor.bg)),
+                None => cursor.with_attrs(FaceAttrs::REVERSE),
+            };
+        }
+
+        let list_default = face(self.list_default);
+        let default_bg = list_default.bg.unwrap_or(bg);
+        let mut list_selected = face(self.list_selected);
+        if list_selected.bg == Some(default_bg) {
+            list_selected = match step(&[default_bg]) {
+                Some(selected_bg) => readable(list_selected.with_bg(Some(selected_bg))),
+                None => list_selected.with_attrs(FaceAttrs::BOLD),
+            };
+        }
+        let selected_bg = list_selected.bg.unwrap_or(default_bg);
+
+        let list_text = face(self.list_text);
+        let mut list_highlight = face(self.list_highlight);
+        let avoid = [default_bg, selected_bg];
+        match list_highlight.bg {
+            Some(highlight_bg) if avoid.contains(&highlight_bg) => {
+                let accent = snap(self.accent);
+                let highlight_bg = (!avoid.contains(&accent))
+                    .then_some(accent)
+                    .or_else(|| step(&avoid));
+                list_highlight = match highlight_bg {
+                    Some(highlight_bg) => Face::new(
+                        Some(highlight_bg.best_contrast(bg, fg)),
+                        Some(highlight_bg),
+                        list_highlight.attrs,
+                    ),
+                    None => Face::new(list_highlight.fg, None, list_highlight.attrs | emphasis),
+                };
+            }
+            Some(_) => {}
+            None => {
+                let highlight_fg = list_highlight.fg.or(list_text.fg).unwrap_or(fg);
+                let text_fg = list_text.fg.unwrap_or(fg);
+                if highlight_fg == text_fg || avoid.contains(&highlight_fg) {
+                    list_highlight = list_highlight.with_attrs(list_highlight.attrs | emphasis);
+                }
+            }
+        }
+
+        self.modify(|inner| {
+            inner.fg = fg;
+            inner.bg = bg;
+            inner.accent = snap(inner.accent);
+            inner.cursor = cursor;
+            inner.input = input;
+            inner.list_default = list_default;
+            inner.list_selected = list_selected;
+            inner.list_text = list_text;
+            inner.list_highlight = list_highlight;
+            inner.list_inactive = face(inner.list_inactive);
+            inner.scrollbar = face(inner.scrollbar);
+            inner.stats = face(inner.stats);
+            inner.label = face(inner.label);
+            inner.separator = face(inner.separator);
+            inner.border = face(inner.border);
+            inner.depth = Some(depth);
+        })
+    }
+
     pub fn modify(&self, modify: impl FnOnce(&mut ThemeInner)) -> Theme {
         let mut inner = self.inner.deref().clone();
         modify(&mut inner);
@@ -260,7 +453,13 @@ impl Theme {
 impl FromStr for Theme {
     type Err = Error;
 
+    /// Parse theme from `attr(=value)?(,attr(=value)?)*` or load it from the
+    /// theme file if specified as `@path`
     fn from_str(string: &str) -> Result<Self, Self::Err> {
+        #[cfg(feature = "theme-file")]
+        if let Some(path) = string.trim().strip_prefix('@') {
+            return Theme::from_file(theme_path(path)).map_err(theme_file_error);
+        }
         string.split(',').try_fold(Theme::light(), |theme, attrs| {
             let mut iter = attrs.splitn(2, '=');
             let key = iter.next().unwrap_or_default().trim().to_lowercase();
@@ -269,9 +468,14 @@ impl FromStr for Theme {
                 "fg" => Theme::from_palette(value.parse()?, theme.bg, theme.accent),
                 "bg" => Theme::from_palette(theme.fg, value.parse()?, theme.accent),
                 "accent" | "base" => Theme::from_palette(theme.fg, theme.bg, value.parse()?),
+                "border" => {
+                    let color = value.parse()?;
+                    theme.modify(|inner| inner.border = inner.border.with_fg(Some(color)))
+                }
                 "light" => Theme::light(),
                 "dark" => Theme::dark(),
                 "dumb" => Theme::dumb(),
+                "nocolor" | "no_color" => Theme::no_color(),
                 _ => return Err(Error::ParseError("Theme", string.to_string())),
             };
             Ok(theme)
@@ -279,6 +483,256 @@ impl FromStr for Theme {
     }
 }
 
+/// Convert theme file loading error, IO errors are preserved and the rest
+/// (i.e TOML and JSON errors) are reported with their context
+#[cfg(feature = "theme-file")]
+fn theme_file_error(error: anyhow::Error) -> Error {
+    let message = format!("{error:#}");
+    match error.root_cause().downcast_ref::<std::io::Error>() {
+        Some(io_error) => Error::IOError(std::io::Error::new(io_error.kind(), message)),
+        None => Error::Other(message.into()),
+    }
+}
+
+/// Snap opaque color to the nearest color available at the color depth
+fn color_quantize(depth: ColorDepth, color: RGBA) -> RGBA {
+    match depth {
+        ColorDepth::TrueColor => color,
+        ColorDepth::EightBit => {
+            const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+            fn nearest(value: u8, values: impl IntoIterator<Item = u8>) -> u8 {
+                values
+                    .into_iter()
+                    .min_by_key(|other| value.abs_diff(*other))
+                    .unwrap_or(value)
+            }
+            let [r, g, b, _] = color.to_rgba();
+            let cube = RGBA::new(nearest(r, CUBE), nearest(g, CUBE), nearest(b, CUBE), 255);
+            let gray_value = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+            let gray_value = nearest(gray_value, (0..24).map(|index| 8 + 10 * index));
+            let gray = RGBA::new(gray_value, gray_value, gray_value, 255);
+            let distance = |other: RGBA| {
+                let [or, og, ob, _] = other.to_rgba();
+                [(r, or), (g, og), (b, ob)]
+                    .into_iter()
+                    .map(|(c, o)| (c.abs_diff(o) as u32).pow(2))
+                    .sum::<u32>()
+            };
+            if distance(gray) < distance(cube) {
+                gray
+            } else {
+                cube
+            }
+        }
+        ColorDepth::Gray => {
+            // black, bright black, white and bright white, same as used by encoder
+            let luma = color.luma();
+            let value = [0u8, 84, 168, 255]
+                .into_iter()
+                .min_by(|a, b| {
+                    let a = (*a as f32 / 255.0 - luma).abs();
+                    let b = (*b as f32 / 255.0 - luma).abs();
+                    a.total_cmp(&b)
+                })
+                .unwrap_or(0);
+            RGBA::new(value, value, value, 255)
+        }
+    }
+}
+
+/// Resolve theme path, relative paths that do not exist are looked up in
+/// the `themes` sub-directory of the configuration directory with optional
+/// `.toml` or `.json` extension
+#[cfg(feature = "theme-file")]
+fn theme_path(path: &str) -> PathBuf {
+    let path = PathBuf::from(path.trim());
+    if path.is_absolute() || path.exists() {
+        return path;
+    }
+    let Some(themes_dir) = crate::common::config_dir().map(|dir| dir.join("themes")) else {
+        return path;
+    };
+    ["", "toml", "json"]
+        .into_iter()
+        .map(|ext| themes_dir.join(&path).with_added_extension(ext))
+        .find(|path| path.exists())
+        .unwrap_or(path)
+}
+
+/// Theme file, all fields are optional and override corresponding fields of
+/// the `base` theme (which is parsed as a theme string, `light` by default)
+///
+/// ```toml
+/// base = "dark"
+/// accent = "#8ec07c"
+/// show_preview = false
+/// cursor = "fg=bg,bg=orange"
+/// list_highlight = "fg=orange,bold"
+/// list_selected_indicator = { text = " > ", face = "fg=accent,bold" }
+/// separator_right = " "
+///
+/// [colors]
+/// orange = "#fe8019"
+/// ```
+///
+/// Palette colors `fg`, `bg` and `accent` rebuild the theme from the palette,
+/// `colors` table defines additional named colors, and faces may refer to
+/// any named color (including `fg`, `bg` and `accent`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeFile {
+    base: Option<String>,
+    fg: Option<String>,
+    bg: Option<String>,
+    accent: Option<String>,
+    colors: BTreeMap<String, String>,
+    cursor: Option<String>,
+    input: Option<String>,
+    list_default: Option<String>,
+    list_selected: Option<String>,
+    list_text: Option<String>,
+    list_highlight: Option<String>,
+    list_inactive: Option<String>,
+    scrollbar: Option<String>,
+    stats: Option<String>,
+    label: Option<String>,
+    separator: Option<String>,
+    border: Option<String>,
+    list_selected_indicator: Option<ThemeText>,
+    list_marked_indicator: Option<ThemeText>,
+    separator_right: Option<ThemeText>,
+    separator_left: Option<ThemeText>,
+    show_preview: Option<bool>,
+}
+
+/// Text element of the theme, either a string or a string with a face
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ThemeText {
+    Text(String),
+    Face { text: String, face: Option<String> },
+}
+
+impl ThemeText {
+    fn build(&self, face: Face, colors: &HashMap<String, RGBA>) -> Result<Text, anyhow::Error> {
+        let (text, face) = match self {
+            ThemeText::Text(text) => (text, face),
+            ThemeText::Face { text, face: None } => (text, face),
+            ThemeText::Face {
+                text,
+                face: Some(face),
+            } => (text, Face::from_str_named(face, colors)?),
+        };
+        Ok(Text::new().with_fmt(text, Some(face)).take())
+    }
+}
+
+impl ThemeFile {
+    /// Build theme described by the theme file
+    pub fn build(&self) -> Result<Theme, anyhow::Error> {
+        let mut theme = match &self.base {
+            Some(base) => base.parse()?,
+            None => Theme::light(),
+        };
+        if self.fg.is_some() || self.bg.is_some() || self.accent.is_some() {
+            let color = |name, value: &Option<String>, default| match value {
+                Some(value) => RGBA::from_str_named(value, &SVG_COLORS)
+                    .with_context(|| format!("invalid color {name}: {value}")),
+                None => Ok(default),
+            };
+            theme = Theme::from_palette(
+                color("fg", &self.fg, theme.fg)?,
+                color("bg", &self.bg, theme.bg)?,
+                color("accent", &self.accent, theme.accent)?,
+            );
+        }
+
+        let mut inner = theme.inner.deref().clone();
+        let mut colors = inner.named_colors.deref().clone();
+        for (name, value) in self.colors.iter() {
+            let color = RGBA::from_str_named(value, &colors)
+                .with_context(|| format!("invalid color {name}: {value}"))?;
+            colors.insert(name.clone(), color);
+        }
+
+        let faces = [
+            ("cursor", &self.cursor, &mut inner.cursor),
+            ("input", &self.input, &mut inner.input),
+            ("list_default", &self.list_default, &mut inner.list_default),
+            (
+                "list_selected",
+                &self.list_selected,
+                &mut inner.list_selected,
+            ),
+            ("list_text", &self.list_text, &mut inner.list_text),
+            (
+                "list_highlight",
+                &self.list_highlight,
+                &mut inner.list_highlight,
+            ),
+            (
+                "list_inactive",
+                &self.list_inactive,
+                &mut inner.list_inactive,
+            ),
+            ("scrollbar", &self.scrollbar, &mut inner.scrollbar),
+            ("stats", &self.stats, &mut inner.stats),
+            ("label", &self.label, &mut inner.label),
+            ("separator", &self.separator, &mut inner.separator),
+            ("border", &self.border, &mut inner.border),
+        ];
+        for (name, value, face) in faces {
+            if let Some(value) = value {
+                *face = Face::from_str_named(value, &colors)
+                    .with_context(|| format!("invalid face {name}: {value}"))?;
+            }
+        }
+
+        let indicator_face = Face::default().with_fg(Some(inner.accent));
+        let texts = [
+            (
+                "list_selected_indicator",
+                &self.list_selected_indicator,
+                indicator_face,
+                &mut inner.list_selected_indicator,
+            ),
+            (
+                "list_marked_indicator",
+                &self.list_marked_indicator,
+                indicator_face,
+                &mut inner.list_marked_indicator,
+            ),
+            (
+                "separator_right",
+                &self.separator_right,
+                inner.separator,
+                &mut inner.separator_right,
+            ),
+            (
+                "separator_left",
+                &self.separator_left,
+                inner.separator,
+                &mut inner.separator_left,
+            ),
+        ];
+        for (name, value, face, text) in texts {
+            if let Some(value) = value {
+                *text = value
+                    .build(face, &colors)
+                    .with_context(|| format!("invalid text {name}"))?;
+            }
+        }
+
+        if let Some(show_preview) = self.show_preview {
+            inner.show_preview = show_preview;
+        }
+        inner.named_colors = Arc::new(colors);
+        Ok(Theme {
+            inner: Arc::new(inner),
+        })
+    }
+}
+
 /// Action description with default binding
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ActionDesc {
@@ -342,6 +796,16 @@ pub enum InputAction {
     DeleteBackward,
     DeleteForward,
     DeleteEnd,
+    DeleteStart,
+    DeleteNextWord,
+    DeletePrevWord,
+    Yank,
+    YankPop,
+    Transpose,
+    Undo,
+    Redo,
+    HistoryPrev,
+    HistoryNext,
 }
 
 impl InputAction {
@@ -425,6 +889,86 @@ impl InputAction {
                 name: "input.delete.end".to_owned(),
                 description: "Delete all input after cursor".to_owned(),
             },
+            DeleteStart => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('u'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.delete.start".to_owned(),
+                description: "Delete all input before cursor".to_owned(),
+            },
+            DeleteNextWord => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('d'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "input.delete.next_word".to_owned(),
+                description: "Delete until the end of the current word".to_owned(),
+            },
+            DeletePrevWord => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('w'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.delete.prev_word".to_owned(),
+                description: "Delete until the start of the word".to_owned(),
+            },
+            Yank => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('y'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.yank".to_owned(),
+                description: "Insert last deleted text".to_owned(),
+            },
+            YankPop => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('y'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "input.yank.pop".to_owned(),
+                description: "Replace inserted text with previously deleted text".to_owned(),
+            },
+            Transpose => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('t'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.transpose".to_owned(),
+                description: "Swap characters around cursor".to_owned(),
+            },
+            Undo => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('z'),
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.undo".to_owned(),
+                description: "Undo last input change".to_owned(),
+            },
+            Redo => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Char('z'),
+                    mode: KeyMod::ALT,
+                }])],
+                name: "input.redo".to_owned(),
+                description: "Redo last undone input change".to_owned(),
+            },
+            HistoryPrev => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Up,
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.history.prev".to_owned(),
+                description: "Replace input with previous query from the history".to_owned(),
+            },
+            HistoryNext => ActionDesc {
+                chords: vec![KeyChord::from_iter([Key {
+                    name: KeyName::Down,
+                    mode: KeyMod::CTRL,
+                }])],
+                name: "input.history.next".to_owned(),
+                description: "Replace input with next query from the history".to_owned(),
+            },
         }
     }
     pub fn all() -> impl Iterator<Item = InputAction> {
@@ -439,6 +983,16 @@ impl InputAction {
             DeleteBackward,
             DeleteForward,
             DeleteEnd,
+            DeleteStart,
+            DeleteNextWord,
+            DeletePrevWord,
+            Yank,
+            YankPop,
+            Transpose,
+            Undo,
+            Redo,
+            HistoryPrev,
+            HistoryNext,
         ]
         .into_iter()
     }
@@ -449,6 +1003,29 @@ struct InputState {
     offset: usize,
 }
 
+/// Maximum number of entries in the kill ring
+const INPUT_KILL_RING_SIZE: usize = 32;
+/// Maximum number of entries in the undo history
+const INPUT_UNDO_SIZE: usize = 256;
+
+/// Snapshot of the input used by undo/redo history
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct InputSnapshot {
+    before: Vec<char>,
+    after: Vec<char>,
+}
+
+impl InputSnapshot {
+    fn text_eq(&self, other: &InputSnapshot) -> bool {
+        self.before.len() + self.after.len() == other.before.len() + other.after.len()
+            && self
+                .before
+                .iter()
+                .chain(self.after.iter().rev())
+                .eq(other.before.iter().chain(other.after.iter().rev()))
+    }
+}
+
 pub struct Input {
     /// string before cursor
     before: Vec<char>,
@@ -458,6 +1035,20 @@ pub struct Input {
     theme: Theme,
     /// Input view state
     view_state: Arc<Mutex<InputState>>,
+    /// deleted text, the most recent entry is the last one
+    kill_ring: VecDeque<String>,
+    /// size of the last yanked text and its index in the kill ring
+    yank: Option<(usize, usize)>,
+    /// undo history
+    undo: Vec<InputSnapshot>,
+    /// redo history, cleared on any input change
+    redo: Vec<InputSnapshot>,
+    /// last applied action, used to merge kills and inserts
+    last_action: Option<InputAction>,
+    /// query history, the oldest entry first
+    history: Vec<String>,
+    /// current position in the history and input before history navigation
+    history_cursor: Option<(usize, String)>,
 }
 
 impl Input {
@@ -467,63 +1058,243 @@ impl Input {
             after: Default::default(),
             theme,
             view_state: Default::default(),
+            kill_ring: Default::default(),
+            yank: None,
+            undo: Default::default(),
+            redo: Default::default(),
+            last_action: None,
+            history: Vec::new(),
+            history_cursor: None,
         }
     }
 
+    /// Set query history used by history navigation actions
+    pub fn history_set(&mut self, history: Vec<String>) {
+        self.history = history;
+        self.history_cursor = None;
+    }
+
     pub fn theme_set(&mut self, theme: Theme) {
         self.theme = theme;
     }
 
     pub fn apply(&mut self, action: &InputAction) {
         use InputAction::*;
+        let snapshot = self.snapshot();
         match action {
             Insert(c) => self.before.push(*c),
             CursorForward => self.before.extend(self.after.pop()),
             CursorBackward => self.after.extend(self.before.pop()),
             CursorEnd => self.before.extend(self.after.drain(..).rev()),
             CursorStart => self.after.extend(self.before.drain(..).rev()),
-            CursorNextWord => {
-                while let Some(c) = self.after.pop() {
-                    if is_word_separator(c) {
-                        self.before.push(c);
-                    } else {
-                        self.after.push(c);
-                        break;
-                    }
+            CursorNextWord => self.cursor_next_word(),
+            CursorPrevWord => self.cursor_prev_word(),
+            DeleteEnd => {
+                let killed = self.after.drain(..).rev().collect();
+                self.kill(killed, false);
+            }
+            DeleteStart => {
+                let killed = self.before.drain(..).collect();
+                self.kill(killed, true);
+            }
+            DeleteNextWord => {
+                let offset = self.before.len();
+                self.cursor_next_word();
+                let killed = self.before.drain(offset..).collect();
+                self.kill(killed, false);
+            }
+            DeletePrevWord => {
+                let offset = self.after.len();
+                self.cursor_prev_word();
+                let killed = self.after.drain(offset..).rev().collect();
+                self.kill(killed, true);
+            }
+            DeleteBackward => {
+                self.before.pop();
+            }
+            DeleteForward => {
+                self.after.pop();
+            }
+            Yank => {
+                if let Some(text) = self.kill_ring.back() {
+                    self.before.extend(text.chars());
+                    self.yank = Some((text.chars().count(), self.kill_ring.len() - 1));
                 }
-                while let Some(c) = self.after.pop() {
-                    if is_word_separator(c) {
-                        self.after.push(c);
-                        break;
+            }
+            YankPop => {
+                // only valid right after yank
+                if let (Some(Yank | YankPop), Some((size, index))) = (self.last_action, self.yank) {
+                    let index = if index == 0 {
+                        self.kill_ring.len() - 1
                     } else {
-                        self.before.push(c);
-                    }
+                        index - 1
+                    };
+                    let text = &self.kill_ring[index];
+                    self.before.truncate(self.before.len().saturating_sub(size));
+                    self.before.extend(text.chars());
+                    self.yank = Some((text.chars().count(), index));
                 }
             }
-            CursorPrevWord => {
-                while let Some(c) = self.before.pop() {
-                    if is_word_separator(c) {
-                        self.after.push(c);
-                    } else {
-                        self.before.push(c);
-                        break;
-                    }
+            Transpose => {
+                // at the end of the input transpose two last characters
+                if self.after.is_empty() && self.before.len() > 1 {
+                    self.after.extend(self.before.pop());
                 }
-                while let Some(c) = self.before.pop() {
-                    if is_word_separator(c) {
-                        self.before.push(c);
-                        break;
-                    } else {
-                        self.after.push(c);
+                if !self.before.is_empty()
+                    && let Some(next) = self.after.pop()
+                {
+                    let prev = self.before.pop();
+                    self.before.push(next);
+                    self.before.extend(prev);
+                }
+            }
+            Undo => {
+                if let Some(prev) = self.undo.pop() {
+                    self.redo.push(snapshot.clone());
+                    self.restore(prev);
+                }
+            }
+            Redo => {
+                if let Some(next) = self.redo.pop() {
+                    self.undo.push(snapshot.clone());
+                    self.restore(next);
+                }
+            }
+            HistoryPrev => {
+                let index = match self.history_cursor.take() {
+                    None if !self.history.is_empty() => {
+                        Some((self.history.len() - 1, self.get().collect()))
                     }
+                    None => None,
+                    Some((index, draft)) => Some((index.saturating_sub(1), draft)),
+                };
+                if let Some((index, draft)) = index {
+                    self.text_replace(&self.history[index].clone());
+                    self.history_cursor = Some((index, draft));
                 }
             }
-            DeleteEnd => self.after.clear(),
-            DeleteBackward => {
-                self.before.pop();
+            HistoryNext => match self.history_cursor.take() {
+                None => {}
+                Some((index, draft)) if index + 1 >= self.history.len() => {
+                    self.text_replace(&draft);
+                }
+                Some((index, draft)) => {
+                    self.text_replace(&self.history[index + 1].clone());
+                    self.history_cursor = Some((index + 1, draft));
+                }
+            },
+        }
+        // editing recalled query starts new history navigation
+        if !matches!(action, HistoryPrev | HistoryNext) && !snapshot.text_eq(&self.snapshot()) {
+            self.history_cursor = None;
+        }
+        if !matches!(action, Undo | Redo) {
+            self.history_record(snapshot, Some(*action));
+        }
+        self.last_action = Some(*action);
+    }
+
+    /// Replace input text and move cursor to the end
+    fn text_replace(&mut self, text: &str) {
+        self.before.clear();
+        self.after.clear();
+        self.before.extend(text.chars());
+    }
+
+    /// Record snapshot taken before the action in the undo history
+    fn history_record(&mut self, snapshot: InputSnapshot, action: Option<InputAction>) {
+        if snapshot.text_eq(&self.snapshot()) {
+            return;
+        }
+        self.redo.clear();
+        // consecutive inserts are merged until the word separator is typed
+        let merge = matches!(
+            (self.last_action, action),
+            (Some(InputAction::Insert(_)), Some(InputAction::Insert(c))) if !is_word_separator(c)
+        );
+        if !merge {
+            if self.undo.len() >= INPUT_UNDO_SIZE {
+                self.undo.remove(0);
             }
-            DeleteForward => {
-                self.after.pop();
+            self.undo.push(snapshot);
+        }
+    }
+
+    fn snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            before: self.before.clone(),
+            after: self.after.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: InputSnapshot) {
+        self.before = snapshot.before;
+        self.after = snapshot.after;
+    }
+
+    /// Add deleted text to the kill ring, consecutive kills are merged
+    fn kill(&mut self, text: String, backward: bool) {
+        if text.is_empty() {
+            return;
+        }
+        let is_kill = |action| {
+            use InputAction::*;
+            matches!(
+                action,
+                Some(DeleteEnd | DeleteStart | DeleteNextWord | DeletePrevWord)
+            )
+        };
+        match self.kill_ring.back_mut() {
+            Some(last) if is_kill(self.last_action) => {
+                if backward {
+                    last.insert_str(0, &text);
+                } else {
+                    last.push_str(&text);
+                }
+            }
+            _ => {
+                if self.kill_ring.len() >= INPUT_KILL_RING_SIZE {
+                    self.kill_ring.pop_front();
+                }
+                self.kill_ring.push_back(text);
+            }
+        }
+    }
+
+    fn cursor_next_word(&mut self) {
+        while let Some(c) = self.after.pop() {
+            if is_word_separator(c) {
+                self.before.push(c);
+            } else {
+                self.after.push(c);
+                break;
+            }
+        }
+        while let Some(c) = self.after.pop() {
+            if is_word_separator(c) {
+                self.after.push(c);
+                break;
+            } else {
+                self.before.push(c);
+            }
+        }
+    }
+
+    fn cursor_prev_word(&mut self) {
+        while let Some(c) = self.before.pop() {
+            if is_word_separator(c) {
+                self.after.push(c);
+            } else {
+                self.before.push(c);
+                break;
+            }
+        }
+        while let Some(c) = self.before.pop() {
+            if is_word_separator(c) {
+                self.before.push(c);
+                break;
+            } else {
+                self.after.push(c);
             }
         }
     }
@@ -544,11 +1315,19 @@ impl Input {
                     KeyName::Char('e') => self.apply(&InputAction::CursorEnd),
                     KeyName::Char('a') => self.apply(&InputAction::CursorStart),
                     KeyName::Char('k') => self.apply(&InputAction::DeleteEnd),
+                    KeyName::Char('u') => self.apply(&InputAction::DeleteStart),
+                    KeyName::Char('w') => self.apply(&InputAction::DeletePrevWord),
+                    KeyName::Char('y') => self.apply(&InputAction::Yank),
+                    KeyName::Char('t') => self.apply(&InputAction::Transpose),
+                    KeyName::Char('z') => self.apply(&InputAction::Undo),
                     _ => {}
                 },
                 KeyMod::ALT => match name {
                     KeyName::Char('f') => self.apply(&InputAction::CursorNextWord),
                     KeyName::Char('b') => self.apply(&InputAction::CursorPrevWord),
+                    KeyName::Char('d') => self.apply(&InputAction::DeleteNextWord),
+                    KeyName::Char('y') => self.apply(&InputAction::YankPop),
+                    KeyName::Char('z') => self.apply(&InputAction::Redo),
                     _ => {}
                 },
                 _ => {}
@@ -563,10 +1342,12 @@ impl Input {
     }
 
     pub fn set(&mut self, text: &str) {
-        self.before.clear();
-        self.after.clear();
-        self.before.extend(text.chars());
+        let snapshot = self.snapshot();
+        self.text_replace(text);
         self.view_state.with_mut(|st| st.offset = 0);
+        self.history_record(snapshot, None);
+        self.last_action = None;
+        self.history_cursor = None;
     }
 
     fn offset(&self) -> usize {
@@ -740,6 +1521,7 @@ pub struct List<T> {
     items: T,
     cursor: usize,
     theme: Theme,
+    reverse: bool,
     view_state: Arc<Mutex<ListState>>,
 }
 
@@ -758,10 +1540,16 @@ impl<T: ListItems> List<T> {
             items,
             cursor: 0,
             theme,
+            reverse: false,
             view_state: Default::default(),
         }
     }
 
+    /// Render list bottom-up, with the first item at the bottom
+    pub fn reverse_set(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
     /// Reference to list items
     pub fn items(&self) -> &T {
         &self.items
@@ -836,9 +1624,15 @@ impl<T: ListItems> List<T> {
     pub fn scroll_bar(&self) -> impl View {
         let state = self.view_state.clone();
         let total = self.items.len();
+        let reverse = self.reverse;
         ScrollBarFn::new(Axis::Vertical, self.theme.scrollbar, move || {
             let state = state.with(|state| *state);
-            ScrollBarPosition::from_counts(total, state.cursor, state.visible_count)
+            let offset = if reverse {
+                total.saturating_sub(state.cursor + state.visible_count)
+            } else {
+                state.cursor
+            };
+            ScrollBarPosition::from_counts(total, offset, state.visible_count)
         })
     }
 
@@ -1025,12 +1819,18 @@ where
             }
         });
 
-        // compute view offsets
+        // compute view offsets, reversed list is stacked from the bottom
         let mut view_offset = 0;
         let mut child_layout_opt = layout.child_mut();
         while let Some(mut child_layout) = child_layout_opt.take() {
-            child_layout.set_position(Position::new(view_offset, indicator_width));
-            view_offset += child_layout.size().height;
+            let child_height = child_layout.size().height;
+            let row = if self.list.reverse {
+                height.saturating_sub(view_offset + child_height)
+            } else {
+                view_offset
+            };
+            child_layout.set_position(Position::new(row, indicator_width));
+            view_offset += child_height;
             child_layout_opt = child_layout.sibling();
         }
 
@@ -1039,86 +1839,487 @@ where
     }
 }
 
+/// Style of the window border
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BorderStyle {
+    #[default]
+    None,
+    Rounded,
+    Sharp,
+    Double,
+}
+
+impl BorderStyle {
+    /// Border characters `[top-left, top-right, bottom-left, bottom-right, horizontal, vertical]`
+    fn chars(self) -> Option<[char; 6]> {
+        use BorderStyle::*;
+        match self {
+            None => Option::None,
+            Rounded => Some(['╭', '╮', '╰', '╯', '─', '│']),
+            Sharp => Some(['┌', '┐', '└', '┘', '─', '│']),
+            Double => Some(['╔', '╗', '╚', '╝', '═', '║']),
+        }
+    }
+}
+
+impl FromStr for BorderStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        use BorderStyle::*;
+        match string.trim() {
+            "none" => Ok(None),
+            "rounded" => Ok(Rounded),
+            "sharp" => Ok(Sharp),
+            "double" => Ok(Double),
+            _ => Err(anyhow::anyhow!("invalid border style: {}", string)),
+        }
+    }
+}
+
+impl std::fmt::Display for BorderStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use BorderStyle::*;
+        let name = match self {
+            None => "none",
+            Rounded => "rounded",
+            Sharp => "sharp",
+            Double => "double",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Border with an optional label drawn around the view
+pub struct Border<V> {
+    view: V,
+    style: BorderStyle,
+    face: Face,
+    padding: Margins,
+    label: Option<(String, Face)>,
+}
+
+impl<V: View> Border<V> {
+    pub fn new(view: V, style: BorderStyle, face: Face) -> Self {
+        Self {
+            view,
+            style,
+            face,
+            padding: Margins::default(),
+            label: None,
+        }
+    }
+
+    /// Space between the border and the view
+    pub fn with_padding(self, padding: Margins) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Label drawn on the top side of the border
+    pub fn with_label(self, label: impl Into<String>, face: Face) -> Self {
+        Self {
+            label: Some((label.into(), face)),
+            ..self
+        }
+    }
+
+    /// Space occupied by the border and padding on each side
+    fn insets(&self) -> Margins {
+        let border = usize::from(self.style.chars().is_some());
+        Margins {
+            left: self.padding.left + border,
+            right: self.padding.right + border,
+            top: self.padding.top + border,
+            bottom: self.padding.bottom + border,
+        }
+    }
+}
+
+impl<V: View> View for Border<V> {
+    fn render(
+        &self,
+        ctx: &ViewContext,
+        surf: TerminalSurface<'_>,
+        layout: ViewLayout<'_>,
+    ) -> Result<(), Error> {
+        let size = layout.size();
+        if size.is_empty() {
+            return Ok(());
+        }
+        let mut surf = layout.apply_to(surf);
+        surf.erase(self.face);
+
+        if let Some([tl, tr, bl, br, h, v]) = self.style.chars() {
+            let (bottom, right) = (size.height - 1, size.width - 1);
+            for col in 0..size.width {
+                surf.set(Position::new(0, col), Cell::new_char(self.face, h));
+                surf.set(Position::new(bottom, col), Cell::new_char(self.face, h));
+            }
+            for row in 0..size.height {
+                surf.set(Position::new(row, 0), Cell::new_char(self.face, v));
+                surf.set(Position::new(row, right), Cell::new_char(self.face, v));
+            }
+            surf.set(Position::new(0, 0), Cell::new_char(self.face, tl));
+            surf.set(Position::new(0, right), Cell::new_char(self.face, tr));
+            surf.set(Position::new(bottom, 0), Cell::new_char(self.face, bl));
+            surf.set(Position::new(bottom, right), Cell::new_char(self.face, br));
+
+            if let Some((label, face)) = &self.label
+                && size.width > 4
+            {
+                let mut surf = surf.view_mut(0..1, 2..size.width - 2);
+                let mut writer = surf.writer(ctx).with_face(*face).with_wraps(false);
+                writer.put_fmt(&format_args!(" {label} "), None);
+            }
+        }
+
+        let child_layout = layout.children().next().ok_or(Error::InvalidLayout)?;
+        self.view.render(ctx, surf.as_mut(), child_layout)
+    }
+
+    fn layout(
+        &self,
+        ctx: &ViewContext,
+        ct: BoxConstraint,
+        mut layout: ViewMutLayout<'_>,
+    ) -> Result<(), Error> {
+        let insets = self.insets();
+        let inset = |size: Size| Size {
+            height: size.height.saturating_sub(insets.top + insets.bottom),
+            width: size.width.saturating_sub(insets.left + insets.right),
+        };
+        let child_ct = BoxConstraint::new(inset(ct.min()), inset(ct.max()));
+        let mut child_layout = layout.push_default();
+        self.view.layout(ctx, child_ct, child_layout.view_mut())?;
+        child_layout.set_position(Position::new(insets.top, insets.left));
+        let child_size = child_layout.size();
+        let size = ct.clamp(Size {
+            height: child_size.height + insets.top + insets.bottom,
+            width: child_size.width + insets.left + insets.right,
+        });
+        layout.set_size(size);
+        Ok(())
+    }
+}
+
+/// Options of the [Process] spawner
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessOptions {
+    /// Maximum number of cached process outputs
+    pub cache_size: usize,
+    /// Delay before the process is spawned, requests issued during this delay
+    /// replace the pending one
+    pub debounce: Duration,
+    /// Maximum number of concurrently running processes
+    pub concurrency: usize,
+    /// Whether to prefetch outputs of the neighbouring items
+    pub prefetch: bool,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            cache_size: 32,
+            debounce: Duration::ZERO,
+            concurrency: 2,
+            prefetch: false,
+        }
+    }
+}
+
+impl FromStr for ProcessOptions {
+    type Err = anyhow::Error;
+
+    /// Parse options specified as `attr=value(,attr=value)*`, where attr is one
+    /// of `cache`, `debounce` (milliseconds), `jobs` or `prefetch`
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut options = Self::default();
+        for attr in string.split(',') {
+            let attr = attr.trim();
+            if attr.is_empty() {
+                continue;
+            }
+            let (key, value) = attr.split_once('=').unwrap_or((attr, "true"));
+            match key.trim() {
+                "cache" => options.cache_size = value.trim().parse()?,
+                "debounce" => options.debounce = Duration::from_millis(value.trim().parse()?),
+                "jobs" => options.concurrency = value.trim().parse::<usize>()?.max(1),
+                "prefetch" => options.prefetch = value.trim().parse()?,
+                key => anyhow::bail!("invalid process option: {}", key),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Key used to identify process output in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcessKey {
+    args: Vec<String>,
+    env: Vec<(&'static str, String)>,
+}
+
+/// Least recently used cache of process outputs
+///
+/// Number of entries is small so linear search is good enough
+struct ProcessCache {
+    capacity: usize,
+    entries: VecDeque<(ProcessKey, ProcessOutput)>,
+}
+
+impl ProcessCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: &ProcessKey) -> Option<ProcessOutput> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index)?;
+        let output = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(output)
+    }
+
+    fn insert(&mut self, key: ProcessKey, output: ProcessOutput) {
+        self.remove(&key);
+        self.entries.push_back((key, output));
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    fn remove(&mut self, key: &ProcessKey) {
+        self.entries.retain(|(k, _)| k != key);
+    }
+}
+
+struct ProcessRequest {
+    key: ProcessKey,
+    command: Command,
+    output: ProcessOutput,
+}
+
+/// Message sent to the process spawner
+enum ProcessMessage {
+    /// Spawn process for the current item once debounce delay expires
+    Spawn(ProcessRequest),
+    /// Spawn process when there is spare capacity
+    Prefetch(ProcessRequest),
+    /// Current item output is cached, pending requests are stale
+    Cancel,
+}
+
 /// Widget that can run system command and show its output
+///
+/// Outputs are cached by the command arguments and environment, spawning is
+/// debounced and the number of concurrently running processes is limited.
 pub struct Process {
-    spawn_channel: mpsc::Sender<Option<Command>>,
-    output: ProcessOutput,
+    spawn_channel: mpsc::UnboundedSender<ProcessMessage>,
+    output: Mutex<ProcessOutput>,
+    cache: Arc<Mutex<ProcessCache>>,
     command_builder: Option<ProcessCommandBuilder>,
+    options: ProcessOptions,
     _spawner_handle: AbortJoinHandle<()>,
 }
 
 impl Process {
     pub fn new(command_builder: Option<ProcessCommandBuilder>, waker: TerminalWaker) -> Self {
-        let (spawn_channel, recv) = mpsc::channel(3);
-        let output = ProcessOutput::new();
+        Self::new_with_options(command_builder, ProcessOptions::default(), waker)
+    }
+
+    /// Create process widget with custom cache, debounce and concurrency options
+    pub fn new_with_options(
+        command_builder: Option<ProcessCommandBuilder>,
+        options: ProcessOptions,
+        waker: TerminalWaker,
+    ) -> Self {
+        let (spawn_channel, recv) = mpsc::unbounded_channel();
+        let cache = Arc::new(Mutex::new(ProcessCache::new(options.cache_size)));
         let _spawner_handle = tokio::spawn(
-            Self::spawner(recv, output.clone(), waker)
+            Self::spawner(recv, cache.clone(), options.clone(), waker)
                 .unwrap_or_else(|error| tracing::error!(?error, "[Process] spawner failed")),
         )
         .into();
         Self {
             spawn_channel,
-            output,
+            output: Mutex::new(ProcessOutput::new()),
+            cache,
             command_builder,
+            options,
             _spawner_handle,
         }
     }
 
-    /// Spawn new process killing existing process and replacing its output
+    /// Spawner options
+    pub fn options(&self) -> &ProcessOptions {
+        &self.options
+    }
+
+    /// Spawn new process (or reuse cached output) replacing the shown output
     pub fn spawn(&self, args: &[impl ProcessCommandArg]) {
-        let args = match &self.command_builder {
-            None => either::Left(args.iter().map(|arg| Cow::Borrowed(arg.as_command_arg()))),
-            Some(command_builder) => either::Right(command_builder.build(args)),
+        self.spawn_with_env(args, &PreviewEnv::default());
+    }
+
+    /// Spawn new process (or reuse cached output) and return its output
+    ///
+    /// Preview environment is passed to the process as environment variables,
+    /// the query is only passed if the command references `SWEEP_QUERY`.
+    pub fn spawn_with_env(
+        &self,
+        args: &[impl ProcessCommandArg],
+        env: &PreviewEnv,
+    ) -> ProcessOutput {
+        let output = self.request(args, env, false).unwrap_or_default();
+        *self.output.lock().expect("lock poisoned") = output.clone();
+        output
+    }
+
+    /// Spawn process in background when there is spare capacity, so its output
+    /// is cached when it is actually requested with [Process::spawn_with_env]
+    pub fn prefetch(&self, args: &[impl ProcessCommandArg], env: &PreviewEnv) {
+        self.request(args, env, true);
+    }
+
+    fn request(
+        &self,
+        args: &[impl ProcessCommandArg],
+        env: &PreviewEnv,
+        prefetch: bool,
+    ) -> Option<ProcessOutput> {
+        let args: Vec<String> = match &self.command_builder {
+            None => args
+                .iter()
+                .map(|arg| arg.as_command_arg().to_owned())
+                .collect(),
+            Some(command_builder) => command_builder.build(args).map(Cow::into_owned).collect(),
         };
-        let mut args = args.into_iter();
-        let command = args.next().map(|prog| {
-            let mut command = Command::new(prog.as_command_arg());
-            for arg in args {
-                command.arg(arg.as_command_arg());
+        let mut env = env.vars();
+        // query changes on every key press, so it is only passed (and used as
+        // part of the cache key) if the command references it
+        if !args.iter().any(|arg| arg.contains("SWEEP_QUERY")) {
+            env.retain(|(name, _)| *name != "SWEEP_QUERY");
+        }
+        let key = ProcessKey { args, env };
+        let mut cache = self.cache.lock().expect("lock poisoned");
+        if let Some(output) = cache.get(&key) {
+            if !prefetch {
+                // debounced request for the previous item must not be spawned
+                self.spawn_channel
+                    .send(ProcessMessage::Cancel)
+                    .unwrap_or_else(|error| {
+                        tracing::error!(?error, "[Process] failed to cancel");
+                    });
             }
-            command
-        });
+            return Some(output);
+        }
 
-        self.spawn_channel
-            .try_send(command)
-            .unwrap_or_else(|error| {
-                tracing::error!(?error, "[Process] faield to spawn");
-            });
+        let mut args = key.args.iter();
+        let mut command = Command::new(args.next()?);
+        command.args(args);
+        command.envs(key.env.iter().map(|(name, value)| (name, value)));
+        let output = ProcessOutput::new();
+        cache.insert(key.clone(), output.clone());
+        let request = ProcessRequest {
+            key,
+            command,
+            output: output.clone(),
+        };
+        let message = if prefetch {
+            ProcessMessage::Prefetch(request)
+        } else {
+            ProcessMessage::Spawn(request)
+        };
+        self.spawn_channel.send(message).unwrap_or_else(|error| {
+            tracing::error!(?error, "[Process] failed to spawn");
+        });
+        Some(output)
     }
 
     async fn spawner(
-        mut spawn_channel: mpsc::Receiver<Option<Command>>,
-        output: ProcessOutput,
+        mut spawn_channel: mpsc::UnboundedReceiver<ProcessMessage>,
+        cache: Arc<Mutex<ProcessCache>>,
+        options: ProcessOptions,
         waker: TerminalWaker,
     ) -> Result<(), Error> {
-        let mut command: Option<Command> = None;
-        let mut command_running: bool;
-        loop {
-            let command_fut = match command.take() {
-                Some(command) => {
-                    command_running = true;
-                    output.clear();
-                    Self::command_run(command, output.clone(), waker.clone()).right_future()
-                }
-                None => {
-                    command_running = false;
-                    future::pending().left_future()
+        let cache_remove = |key: &ProcessKey| {
+            cache.lock().expect("lock poisoned").remove(key);
+        };
+        let (done_send, mut done_recv) = mpsc::unbounded_channel::<ProcessKey>();
+        // running processes, the oldest first
+        let mut running: VecDeque<(ProcessKey, AbortJoinHandle<()>)> = VecDeque::new();
+        // request waiting for debounce delay to expire
+        let mut pending: Option<ProcessRequest> = None;
+        let mut pending_deadline = tokio::time::Instant::now();
+        // prefetch requests waiting for spare capacity
+        let mut prefetch: VecDeque<ProcessRequest> = VecDeque::new();
+
+        let run = |request: ProcessRequest| {
+            let ProcessRequest {
+                key,
+                command,
+                output,
+            } = request;
+            let done_send = done_send.clone();
+            let waker = waker.clone();
+            let handle = tokio::spawn({
+                let key = key.clone();
+                async move {
+                    if let Err(error) = Self::command_run(command, output, waker.clone()).await {
+                        tracing::error!(?error, "[Process] command failed with error");
+                    }
+                    let _ = waker.wake();
+                    let _ = done_send.send(key);
                 }
-            };
+            });
+            (key, AbortJoinHandle::from(handle))
+        };
+
+        loop {
+            // use spare capacity to run prefetch requests
+            while pending.is_none() && running.len() < options.concurrency {
+                let Some(request) = prefetch.pop_front() else {
+                    break;
+                };
+                running.push_back(run(request));
+            }
+
             tokio::select! {
-                Some(command_next) = spawn_channel.recv() => {
-                    command = command_next;
-                    while let Ok(command_next) = spawn_channel.try_recv() {
-                        command = command_next;
+                message = spawn_channel.recv() => {
+                    let request = match message {
+                        None => break,
+                        Some(ProcessMessage::Prefetch(request)) => {
+                            prefetch.push_back(request);
+                            continue;
+                        }
+                        Some(ProcessMessage::Spawn(request)) => Some(request),
+                        Some(ProcessMessage::Cancel) => None,
+                    };
+                    // current item has changed, pending and prefetch requests are stale
+                    for request in pending.take().into_iter().chain(prefetch.drain(..)) {
+                        cache_remove(&request.key);
                     }
+                    pending = request;
+                    pending_deadline = tokio::time::Instant::now() + options.debounce;
                 }
-                command_res = command_fut, if command_running => {
-                    let _ = waker.wake();
-                    if let Err(error) = command_res {
-                        tracing::error!(?error, "[Process] command faield with error");
+                _ = tokio::time::sleep_until(pending_deadline), if pending.is_some() => {
+                    let Some(request) = pending.take() else {
+                        continue;
+                    };
+                    // kill the oldest processes to free capacity, their output is incomplete
+                    while running.len() >= options.concurrency {
+                        let Some((key, _handle)) = running.pop_front() else {
+                            break;
+                        };
+                        cache_remove(&key);
                     }
+                    running.push_back(run(request));
+                }
+                Some(key) = done_recv.recv() => {
+                    running.retain(|(running_key, _)| running_key != &key);
                 }
-                else => { break; }
             }
         }
         Ok(())
@@ -1130,7 +2331,6 @@ impl Process {
         waker: TerminalWaker,
     ) -> Result<(), Error> {
         let mut child = command
-            // TODO: pass `FZF_PREVIEW_(TOP|LEFT|LINES|COLUMNS)` and `LINES|COLUMNS`
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -1176,8 +2376,67 @@ impl Process {
 impl IntoView for &Process {
     type View = ProcessOutput;
 
+    /// Output of the last spawned process
     fn into_view(self) -> Self::View {
-        self.output.clone()
+        self.output.lock().expect("lock poisoned").clone()
+    }
+}
+
+/// Search within the process output
+struct OutputSearch {
+    scorer: Arc<dyn Scorer>,
+    face: Face,
+    /// number of complete rows already searched
+    searched: usize,
+    /// complete rows containing matches, and matched cells
+    matches: Vec<(usize, Positions<Vec<u8>>)>,
+    /// matches of the last incomplete row
+    partial: Option<(usize, Positions<Vec<u8>>)>,
+}
+
+impl OutputSearch {
+    fn new(scorer: Arc<dyn Scorer>, face: Face) -> Self {
+        Self {
+            scorer,
+            face,
+            searched: 0,
+            matches: Vec::new(),
+            partial: None,
+        }
+    }
+
+    /// Search row cells, returns matched cells
+    fn search_row(&self, row: &[Cell]) -> Option<Positions<Vec<u8>>> {
+        let row = match row.split_last() {
+            Some((last, row)) if matches!(last.kind(), CellKind::Char('\n')) => row,
+            _ => row,
+        };
+        let haystack: Vec<char> = row
+            .iter()
+            .map(|cell| match cell.kind() {
+                CellKind::Char(c) => c.to_lowercase().next().unwrap_or(*c),
+                _ => ' ',
+            })
+            .collect();
+        let mut score = Score::MIN;
+        let mut positions = Positions::new_owned(haystack.len());
+        self.scorer
+            .score_ref(&haystack, &mut score, positions.as_mut())
+            .then_some(positions)
+    }
+
+    /// Matched cells of the row
+    fn row_matches(&self, row: usize) -> Option<&Positions<Vec<u8>>> {
+        if let Some((partial_row, positions)) = &self.partial
+            && *partial_row == row
+        {
+            return Some(positions);
+        }
+        let index = self
+            .matches
+            .binary_search_by_key(&row, |(row, _)| *row)
+            .ok()?;
+        Some(&self.matches[index].1)
     }
 }
 
@@ -1188,9 +2447,35 @@ struct ProcessOutputInner {
     cells: Vec<Cell>,
     lines: Vec<usize>,
     offset: Position,
+    search: Option<OutputSearch>,
 }
 
 impl ProcessOutputInner {
+    /// Search rows that were added since the last update
+    fn search_update(&mut self) {
+        let Some(mut search) = self.search.take() else {
+            return;
+        };
+        while search.searched < self.lines.len() {
+            let row = search.searched;
+            search.searched += 1;
+            let start = self.cells_offset(row).unwrap_or(0);
+            let end = self.lines[row];
+            if let Some(positions) = search.search_row(&self.cells[start..end]) {
+                search.matches.push((row, positions));
+            }
+        }
+        let start = self.cells_offset(self.lines.len()).unwrap_or(0);
+        search.partial = if start < self.cells.len() {
+            search
+                .search_row(&self.cells[start..])
+                .map(|positions| (self.lines.len(), positions))
+        } else {
+            None
+        };
+        self.search.replace(search);
+    }
+
     fn rows(&self) -> impl Iterator<Item = &[Cell]> {
         let mut row = self.offset.row;
         let col = self.offset.col;
@@ -1246,6 +2531,32 @@ impl ProcessOutput {
             inner.cells.clear();
             inner.lines.clear();
             inner.offset = Position::origin();
+            if let Some(search) = inner.search.take() {
+                inner.search = Some(OutputSearch::new(search.scorer, search.face));
+            }
+        })
+    }
+
+    /// Highlight cells matched by the scorer with the face, `None` disables search
+    pub fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        self.inner.with_mut(|inner| {
+            inner.search = search.map(|(scorer, face)| OutputSearch::new(scorer, face));
+        })
+    }
+
+    /// Rows containing matches of the current search
+    pub fn search_matches(&self) -> Vec<usize> {
+        self.inner.with_mut(|inner| {
+            inner.search_update();
+            let Some(search) = &inner.search else {
+                return Vec::new();
+            };
+            search
+                .matches
+                .iter()
+                .chain(search.partial.iter())
+                .map(|(row, _)| *row)
+                .collect()
         })
     }
 }
@@ -1295,16 +2606,25 @@ impl View for ProcessOutput {
     ) -> Result<(), Error> {
         let mut surf = layout.apply_to(surf);
         let mut writer = surf.writer(ctx).with_wraps(false);
-        self.inner.with(|inner| {
-            let mut cells = inner.rows().flat_map(|row| row.iter()).cloned();
-            loop {
-                let Some(cell) = cells.next() else {
-                    // no more cells
-                    break;
-                };
-                if !writer.put_cell(cell) {
-                    // failed to put cell
-                    break;
+        self.inner.with_mut(|inner| {
+            inner.search_update();
+            let offset = inner.offset;
+            'rows: for (index, row) in inner.rows().enumerate() {
+                let matches = inner.search.as_ref().and_then(|search| {
+                    Some((search.row_matches(offset.row + index)?, search.face))
+                });
+                for (col, cell) in row.iter().enumerate() {
+                    let cell = match matches {
+                        Some((positions, face)) if positions.get(offset.col + col) => {
+                            let face = cell.face().overlay(&face);
+                            cell.clone().with_face(face)
+                        }
+                        _ => cell.clone(),
+                    };
+                    if !writer.put_cell(cell) {
+                        // failed to put cell
+                        break 'rows;
+                    }
                 }
             }
         });
@@ -1338,6 +2658,14 @@ impl HaystackPreview for ProcessOutput {
     fn set_offset(&self, offset: Position) -> Position {
         ProcessOutput::set_offset(self, offset)
     }
+
+    fn search_set(&self, search: Option<(Arc<dyn Scorer>, Face)>) {
+        ProcessOutput::search_set(self, search)
+    }
+
+    fn search_matches(&self) -> Vec<usize> {
+        ProcessOutput::search_matches(self)
+    }
 }
 
 /// Builder that used to create argument list from a pattern and a list of fields
@@ -1502,6 +2830,236 @@ mod tests {
         }
     }
 
+    fn input_text(input: &Input) -> (String, String) {
+        (
+            input.before.iter().collect(),
+            input.after.iter().rev().collect(),
+        )
+    }
+
+    #[test]
+    fn test_input_kill_yank() {
+        use InputAction::*;
+        let mut input = Input::new(Theme::light());
+        input.set("one two three");
+
+        input.apply(&DeletePrevWord);
+        input.apply(&DeletePrevWord);
+        assert_eq!(input_text(&input), ("one ".to_owned(), String::new()));
+        // consecutive kills are merged
+        assert_eq!(
+            input.kill_ring.back().map(String::as_str),
+            Some("two three")
+        );
+
+        input.apply(&CursorStart);
+        input.apply(&DeleteNextWord);
+        assert_eq!(input_text(&input), (String::new(), " ".to_owned()));
+        assert_eq!(input.kill_ring.len(), 2);
+
+        input.apply(&Yank);
+        assert_eq!(input_text(&input), ("one".to_owned(), " ".to_owned()));
+        input.apply(&YankPop);
+        assert_eq!(input_text(&input), ("two three".to_owned(), " ".to_owned()));
+        input.apply(&YankPop);
+        assert_eq!(input_text(&input), ("one".to_owned(), " ".to_owned()));
+
+        input.apply(&DeleteStart);
+        assert_eq!(input_text(&input), (String::new(), " ".to_owned()));
+        input.apply(&CursorEnd);
+        input.apply(&Yank);
+        assert_eq!(input_text(&input), (" one".to_owned(), String::new()));
+    }
+
+    #[test]
+    fn test_input_transpose() {
+        use InputAction::*;
+        let mut input = Input::new(Theme::light());
+        input.set("abc");
+        input.apply(&Transpose);
+        assert_eq!(input_text(&input), ("acb".to_owned(), String::new()));
+        input.apply(&CursorStart);
+        input.apply(&CursorForward);
+        input.apply(&Transpose);
+        assert_eq!(input_text(&input), ("ca".to_owned(), "b".to_owned()));
+        input.apply(&CursorStart);
+        input.apply(&Transpose);
+        assert_eq!(input_text(&input), (String::new(), "cab".to_owned()));
+    }
+
+    #[test]
+    fn test_input_history() {
+        use InputAction::*;
+        let mut input = Input::new(Theme::light());
+        input.apply(&HistoryPrev);
+        assert_eq!(input.get().collect::<String>(), "");
+
+        input.history_set(vec!["one".to_owned(), "two".to_owned()]);
+        input.set("draft");
+        input.apply(&HistoryPrev);
+        assert_eq!(input.get().collect::<String>(), "two");
+        input.apply(&HistoryPrev);
+        input.apply(&HistoryPrev);
+        assert_eq!(input.get().collect::<String>(), "one");
+        input.apply(&HistoryNext);
+        assert_eq!(input.get().collect::<String>(), "two");
+        input.apply(&HistoryNext);
+        assert_eq!(input.get().collect::<String>(), "draft");
+        input.apply(&HistoryNext);
+        assert_eq!(input.get().collect::<String>(), "draft");
+
+        // editing recalled entry resets navigation
+        input.apply(&HistoryPrev);
+        input.apply(&Insert('!'));
+        input.apply(&HistoryPrev);
+        input.apply(&HistoryNext);
+        assert_eq!(input.get().collect::<String>(), "two!");
+    }
+
+    #[test]
+    fn test_input_undo_redo() {
+        use InputAction::*;
+        let mut input = Input::new(Theme::light());
+        for c in "one two".chars() {
+            input.apply(&Insert(c));
+        }
+        input.apply(&DeletePrevWord);
+        assert_eq!(input.get().collect::<String>(), "one ");
+
+        input.apply(&Undo);
+        assert_eq!(input.get().collect::<String>(), "one two");
+        input.apply(&Undo);
+        assert_eq!(input.get().collect::<String>(), "one");
+        input.apply(&Undo);
+        assert_eq!(input.get().collect::<String>(), "");
+        input.apply(&Undo);
+        assert_eq!(input.get().collect::<String>(), "");
+
+        input.apply(&Redo);
+        input.apply(&Redo);
+        assert_eq!(input.get().collect::<String>(), "one two");
+
+        // any change clears redo history
+        input.apply(&Undo);
+        input.apply(&Insert('!'));
+        input.apply(&Redo);
+        assert_eq!(input.get().collect::<String>(), "one!");
+    }
+
+    #[test]
+    #[cfg(feature = "theme-file")]
+    fn test_theme_file() -> Result<(), anyhow::Error> {
+        let theme_file: ThemeFile = toml::from_str(
+            r##"
+            base = "dark"
+            accent = "#8ec07c"
+            show_preview = false
+            cursor = "fg=bg,bg=orange"
+            list_selected_indicator = { text = " > ", face = "fg=orange,bold" }
+            separator_right = " "
+
+            [colors]
+            orange = "#fe8019"
+            "##,
+        )?;
+        let theme = theme_file.build()?;
+        let dark = Theme::dark();
+        let orange: RGBA = "#fe8019".parse()?;
+        assert_eq!(theme.fg, dark.fg);
+        assert_eq!(theme.accent, "#8ec07c".parse()?);
+        assert!(!theme.show_preview);
+        assert_eq!(
+            theme.cursor,
+            Face::new(Some(dark.bg), Some(orange), FaceAttrs::EMPTY)
+        );
+        assert_eq!(theme.named_colors.get("orange"), Some(&orange));
+        assert!(theme.file.is_none());
+
+        // theme file is reloaded from the disk
+        let path = std::env::temp_dir().join(format!("sweep-theme-{}.toml", std::process::id()));
+        std::fs::write(&path, "base = \"dark\"")?;
+        let theme: Theme = format!("@{}", path.display()).parse()?;
+        assert_eq!(theme.file.as_deref(), Some(path.as_path()));
+        assert_eq!(theme.bg, dark.bg);
+        std::fs::write(&path, "base = \"light\"")?;
+        let theme = theme.reload();
+        std::fs::remove_file(&path)?;
+        assert_eq!(theme?.bg, Theme::light().bg);
+
+        // json format, and reload of the theme without file is a noop
+        let theme_file: ThemeFile = serde_json::from_str(r#"{"label": "fg=accent,italic"}"#)?;
+        let theme = theme_file.build()?.reload()?;
+        assert_eq!(theme.label.attrs, FaceAttrs::ITALIC);
+
+        // errors
+        let theme_file: ThemeFile = toml::from_str(r#"cursor = "fg=no-such-color""#)?;
+        assert!(theme_file.build().is_err());
+        assert!(toml::from_str::<ThemeFile>(r#"no_such_field = "fg=red""#).is_err());
+        assert!("@/no/such/theme.toml".parse::<Theme>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_theme_quantize() -> Result<(), anyhow::Error> {
+        let themes = [
+            Theme::light(),
+            Theme::dark(),
+            "bg=#303030,fg=#d0d0d0,accent=#404040".parse()?,
+        ];
+        for theme in themes {
+            for depth in [ColorDepth::EightBit, ColorDepth::Gray] {
+                let quantized = theme.quantize(depth);
+                assert_eq!(quantized.depth, Some(depth));
+
+                // all colors are from the palette
+                let faces = [
+                    quantized.cursor,
+                    quantized.list_default,
+                    quantized.list_selected,
+                    quantized.list_highlight,
+                    quantized.stats,
+                ];
+                for face in faces {
+                    for color in face.fg.into_iter().chain(face.bg) {
+                        assert_eq!(color_quantize(depth, color), color);
+                    }
+                    if let (Some(fg), Some(bg)) = (face.fg, face.bg) {
+                        assert_ne!(fg, bg, "{depth:?} {face:?}");
+                    }
+                }
+
+                // selected row and highlight are distinguishable
+                let default_bg = quantized.list_default.bg;
+                let selected_bg = quantized.list_selected.bg;
+                assert_ne!(selected_bg, default_bg, "{depth:?}");
+                let highlight = quantized.list_highlight;
+                match highlight.bg {
+                    Some(bg) => {
+                        assert_ne!(Some(bg), default_bg, "{depth:?}");
+                        assert_ne!(Some(bg), selected_bg, "{depth:?}");
+                    }
+                    None => assert!(highlight.attrs.contains(FaceAttrs::UNDERLINE)),
+                }
+
+                // quantization is idempotent
+                let quantized_again = quantized.modify(|inner| inner.depth = None).quantize(depth);
+                assert_eq!(quantized_again.list_selected, quantized.list_selected);
+                assert_eq!(quantized_again.list_highlight, quantized.list_highlight);
+            }
+        }
+
+        // no color theme is unaffected by the color depth
+        let theme = Theme::no_color().quantize(ColorDepth::Gray);
+        assert!(theme.no_color);
+        for face in [theme.cursor, theme.list_selected, theme.list_highlight] {
+            assert_eq!((face.fg, face.bg), (None, None));
+        }
+        assert_ne!(theme.list_selected.attrs, theme.list_highlight.attrs);
+
+        Ok(())
+    }
+
     #[test]
     fn test_list_basic() -> Result<(), Error> {
         let list_selected_bg = Some("#8ec07c".parse()?);
@@ -1527,6 +3085,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_list_reverse() -> Result<(), Error> {
+        use surf_n_term::view::ViewLayoutStore;
+
+        let items = VecItems((0..3).collect());
+        let mut list = List::new(items, Theme::light());
+        list.reverse_set(true);
+
+        // first item is at the bottom
+        let ctx = ViewContext::dummy();
+        let ct = BoxConstraint::loose(Size::new(5, 50));
+        let mut store = ViewLayoutStore::new();
+        let layout = list.view(()).layout_new(&ctx, ct, &mut store)?;
+        let rows: Vec<_> = layout
+            .children()
+            .map(|child| child.position().row)
+            .collect();
+        assert_eq!(rows, vec![4, 3, 2]);
+
+        // actions are not affected by the direction
+        list.apply(&ListAction::ItemNext);
+        assert_eq!(list.cursor(), 1);
+        list.apply(&ListAction::ItemPrev);
+        assert_eq!(list.cursor(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_border() -> Result<(), Error> {
+        use surf_n_term::view::ViewLayoutStore;
+
+        let theme = Theme::light();
+        let border = Border::new(
+            Text::new().with_fmt("text", None),
+            BorderStyle::Rounded,
+            theme.border,
+        )
+        .with_padding(Margins {
+            left: 1,
+            right: 1,
+            top: 0,
+            bottom: 0,
+        })
+        .with_label("label", theme.label);
+
+        let ctx = ViewContext::dummy();
+        let ct = BoxConstraint::tight(Size::new(5, 20));
+        let mut store = ViewLayoutStore::new();
+        let layout = border.layout_new(&ctx, ct, &mut store)?;
+        assert_eq!(layout.size(), Size::new(5, 20));
+        let child = layout.children().next().ok_or(Error::InvalidLayout)?;
+        assert_eq!(child.position(), Position::new(1, 2));
+        assert_eq!(child.size(), Size::new(3, 16));
+
+        Ok(())
+    }
+
     #[test]
     fn test_list_multiline() -> Result<(), Error> {
         let list_selected_bg = Some("#8ec07c".parse()?);
@@ -1634,4 +3250,146 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_process_env() -> Result<(), anyhow::Error> {
+        let process = Process::new(None, TerminalWaker::new(|| Ok(())));
+        let env = PreviewEnv {
+            area: Some((Position::new(1, 2), Size::new(10, 40))),
+            query: "query".to_owned(),
+            index: 3,
+        };
+        let output = process.spawn_with_env(
+            &[
+                "sh",
+                "-c",
+                "echo $SWEEP_QUERY $SWEEP_INDEX $FZF_PREVIEW_TOP $FZF_PREVIEW_LEFT $LINES $COLUMNS",
+            ],
+            &env,
+        );
+
+        let output_text = || {
+            output.inner.with(|inner| {
+                inner
+                    .cells
+                    .iter()
+                    .filter_map(|cell| match cell.kind() {
+                        surf_n_term::render::CellKind::Char(c) => Some(*c),
+                        _ => None,
+                    })
+                    .collect::<String>()
+            })
+        };
+        for _ in 0..100 {
+            if output_text().contains('\n') {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(output_text().trim(), "query 3 1 2 10 40");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_output_search() {
+        let mut output = ProcessOutput::new();
+        output.put_fmt("one\ntwo One\nthree", None);
+        assert!(output.search_matches().is_empty());
+
+        let face = Face::default().with_attrs(FaceAttrs::BOLD);
+        output.search_set(Some((crate::substr_scorer()("one"), face)));
+        assert_eq!(output.search_matches(), vec![0, 1]);
+        let positions = output.inner.with(|inner| {
+            let search = inner.search.as_ref().unwrap();
+            let positions = search.row_matches(1).unwrap();
+            (0..positions.len())
+                .filter(|index| positions.get(*index))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(positions, vec![4, 5, 6]);
+
+        // last incomplete row is searched and updated as output grows
+        output.search_set(Some((crate::regex_scorer()("^th.*s$"), face)));
+        assert!(output.search_matches().is_empty());
+        output.put_fmt("s\n", None);
+        assert_eq!(output.search_matches(), vec![2]);
+
+        output.search_set(None);
+        assert!(output.search_matches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_cache() -> Result<(), anyhow::Error> {
+        let options: ProcessOptions = "cache=2,debounce=0,jobs=1".parse()?;
+        assert_eq!(options.cache_size, 2);
+        assert_eq!(options.concurrency, 1);
+        assert!(!options.prefetch);
+        assert!("unknown=1".parse::<ProcessOptions>().is_err());
+
+        let process = Process::new_with_options(None, options, TerminalWaker::new(|| Ok(())));
+        let env = PreviewEnv::default();
+        let one = process.spawn_with_env(&["echo", "one"], &env);
+        assert!(Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&["echo", "one"], &env).inner
+        ));
+        // different preview area is not cached
+        let env_area = PreviewEnv {
+            area: Some((Position::origin(), Size::new(1, 1))),
+            ..Default::default()
+        };
+        assert!(!Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&["echo", "one"], &env_area).inner
+        ));
+        // least recently used entry is evicted
+        process.spawn_with_env(&["echo", "two"], &env);
+        assert!(!Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&["echo", "one"], &env).inner
+        ));
+        // different query is only not cached if the command references it
+        let env_query = PreviewEnv {
+            query: "query".to_owned(),
+            ..Default::default()
+        };
+        let one = process.spawn_with_env(&["echo", "one"], &env);
+        assert!(Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&["echo", "one"], &env_query).inner
+        ));
+        let query = ["sh", "-c", "echo $SWEEP_QUERY"];
+        let one = process.spawn_with_env(&query, &env);
+        assert!(!Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&query, &env_query).inner
+        ));
+        Ok(())
+    }
+
+    // paused clock is only advanced once all tasks are idle, so the spawner has
+    // processed all messages by the time sleep returns
+    #[tokio::test(start_paused = true)]
+    async fn test_process_cache_cancel() -> Result<(), anyhow::Error> {
+        let options: ProcessOptions = "debounce=200".parse()?;
+        assert_eq!(options.debounce, Duration::from_millis(200));
+        assert_eq!(ProcessOptions::default().debounce, Duration::ZERO);
+        let process = Process::new_with_options(None, options, TerminalWaker::new(|| Ok(())));
+        let env = PreviewEnv::default();
+        let one = process.spawn_with_env(&["echo", "one"], &env);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // cached output cancels pending request of the previous item
+        let two = process.spawn_with_env(&["echo", "two"], &env);
+        assert!(Arc::ptr_eq(
+            &one.inner,
+            &process.spawn_with_env(&["echo", "one"], &env).inner
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!Arc::ptr_eq(
+            &two.inner,
+            &process.spawn_with_env(&["echo", "two"], &env).inner
+        ));
+        Ok(())
+    }
 }
@@ -63,9 +63,13 @@ __all__ = [
     "sweep",
     "Sweep",
     "SweepBind",
+    "SweepCursorChanged",
     "SweepEvent",
+    "SweepMarksChanged",
+    "SweepQueryChanged",
     "SweepSelect",
     "SweepSize",
+    "SweepStateChanged",
     "SweepWindow",
     "Text",
     "View",
@@ -111,6 +115,42 @@ class SweepSelect[I]:
     items: list[I]
 
 
+@dataclass
+class SweepQueryChanged:
+    """Event generated on query change, requires subscription"""
+
+    uid: WindowId
+    query: str
+
+
+@dataclass
+class SweepCursorChanged[I]:
+    """Event generated when item under the cursor changes, requires subscription"""
+
+    uid: WindowId
+    item: I | None
+
+
+@dataclass
+class SweepMarksChanged[I]:
+    """Event generated when marked items change, requires subscription"""
+
+    uid: WindowId
+    items: list[I]
+
+
+@dataclass
+class SweepStateChanged:
+    """Event generated when scorer, keep order, theme or layout of the window changes
+
+    Only generated for the events passed to `subscribe`
+    """
+
+    uid: WindowId
+    name: Literal["scorer", "keep_order", "theme", "layout"]
+    value: Any
+
+
 class SweepBind(NamedTuple):
     """Event generated on bound key press"""
 
@@ -245,6 +285,7 @@ class Candidate:
     preview: list[Any] | None = None
     preview_flex: float = 0.0
     hotkey: str | None = None
+    key: str | None = None
 
     def to_candidate(self) -> Candidate:
         return self
@@ -321,6 +362,11 @@ class Candidate:
         self.hotkey = hotkey
         return self
 
+    def key_set(self, key: str) -> Candidate:
+        """Assign stable key used by `items_upsert` and `items_remove`"""
+        self.key = key
+        return self
+
     def tag[V](self, value: V) -> CandidateTagged[V]:
         return CandidateTagged(value, self)
 
@@ -343,6 +389,8 @@ class Candidate:
             attrs.append(f"preview_flex={self.preview_flex}")
         if self.hotkey is not None:
             attrs.append(f"hotkey={self.hotkey}")
+        if self.key is not None:
+            attrs.append(f"key={self.key}")
         return f'Candidate({", ".join(attrs)})'
 
     def to_json(self) -> dict[str, Any]:
@@ -362,6 +410,8 @@ class Candidate:
             obj["preview_flex"] = self.preview_flex
         if self.hotkey is not None:
             obj["hotkey"] = self.hotkey
+        if self.key is not None:
+            obj["key"] = self.key
         return obj
 
     @staticmethod
@@ -391,6 +441,7 @@ class Candidate:
         preview = fields_from_json(obj.pop("preview", None))
         preview_flex = obj.pop("preview_flex", None) or 0.0
         hotkey = obj.pop("hotkey", None)
+        key = obj.pop("key", None)
         return Candidate(
             target=target,
             extra=obj or None,
@@ -400,6 +451,7 @@ class Candidate:
             preview=preview,
             preview_flex=preview_flex,
             hotkey=hotkey,
+            key=None if key is None else str(key),
         )
 
 
@@ -412,7 +464,16 @@ class CandidateTagged[V]:
         return self.candidate
 
 
-type SweepEvent[I] = SweepBind | SweepSize | SweepSelect[I] | SweepWindow
+type SweepEvent[I] = (
+    SweepBind
+    | SweepSize
+    | SweepSelect[I]
+    | SweepWindow
+    | SweepQueryChanged
+    | SweepCursorChanged[I]
+    | SweepMarksChanged[I]
+    | SweepStateChanged
+)
 type BindHandler[I] = Callable[[Sweep[I], str], Awaitable[I | None]]
 type FiledResolver = Callable[[int], Awaitable[Field | None]]
 type ViewResolver = Callable[[int], Awaitable[View | None]]
@@ -461,6 +522,11 @@ class SweepArgs(TypedDict, total=False):
     keep_order: bool
     no_match: str | None
     layout: str | None
+    preview_file: str | None
+    preview_window: str | None
+    preview_options: str | None
+    history: str | None
+    vi: bool
     tmp_socket: bool
     field_resolver: FiledResolver | None
     view_resolver: ViewResolver | None
@@ -538,6 +604,7 @@ class Sweep[I]:
         "__peer_iter",
         "__tmp_socket",
         "__items",
+        "__items_lazy",
         "__binds",
         "__field_resolver",
         "__field_resolved",
@@ -564,6 +631,11 @@ class Sweep[I]:
         keep_order: bool = False,
         no_match: str | None = None,
         layout: str | None = None,
+        preview_file: str | None = None,
+        preview_window: str | None = None,
+        preview_options: str | None = None,
+        history: str | None = None,
+        vi: bool = False,
         tmp_socket: bool = False,
         field_resolver: FiledResolver | None = None,
         view_resolver: ViewResolver | None = None,
@@ -595,6 +667,16 @@ class Sweep[I]:
             args.extend(["--layout", layout])
         if preview:
             args.extend(["--preview", preview])
+        if preview_file:
+            args.extend(["--preview-file", preview_file])
+        if preview_window:
+            args.extend(["--preview-window", preview_window])
+        if preview_options:
+            args.extend(["--preview-options", preview_options])
+        if history:
+            args.extend(["--history", history])
+        if vi:
+            args.append("--vi")
         args.extend(["--window-uid", str(window_uid) if window_uid else ""])
         sweep = sweep or ["sweep"]
         self.__args: list[str] = [*sweep, "--rpc", *args]
@@ -605,6 +687,12 @@ class Sweep[I]:
         self.__peer_iter: AsyncIterator[RpcRequest] = aiter(self.__peer)
         self.__size: SweepSize | None = None
         self.__items: defaultdict[WindowId, list[I]] = defaultdict(list)
+        # lazy items with their searchable text by window, dropped when the
+        # window is cleared or closed
+        self.__items_lazy: defaultdict[WindowId, dict[int, tuple[I, str]]] = (
+            defaultdict(dict)
+        )
+        self.__peer.register("items_render", self.__items_render)
         self.__binds: dict[str, BindHandler[I]] = {}
         self.__field_resolver: FiledResolver | None = field_resolver
         self.__field_resolved: set[int] = set()
@@ -661,6 +749,12 @@ class Sweep[I]:
         if isinstance(item, dict):
             items = self.__items[uid or self.__window_uid_current]
             item_dict = cast(dict[str, Any], item)
+            item_lazy: int | None = item_dict.get("lazy")
+            if item_lazy is not None:
+                uid_lazy = uid or self.__window_uid_current
+                items_lazy = self.__items_lazy.get(uid_lazy, {})
+                if item_lazy in items_lazy:
+                    return items_lazy[item_lazy][0]
             item_index: int | None = item_dict.get("_sweep_item_index")
             if item_index is not None and item_index < len(items):
                 return items[item_index]
@@ -700,6 +794,39 @@ class Sweep[I]:
                         item = await handler(self, tag)
                         if item is not None:
                             yield SweepSelect(uid, items=[item])
+                elif event.method == "query_changed":
+                    yield SweepQueryChanged(
+                        uid=event.params["uid"],
+                        query=event.params.get("query", ""),
+                    )
+                elif event.method == "cursor_changed":
+                    uid = event.params["uid"]
+                    item = event.params.get("item")
+                    yield SweepCursorChanged(
+                        uid=uid,
+                        item=None if item is None else self.__item_get(uid, item),
+                    )
+                elif event.method == "marks_changed":
+                    uid = event.params["uid"]
+                    yield SweepMarksChanged(
+                        uid=uid,
+                        items=[
+                            self.__item_get(uid, item)
+                            for item in event.params.get("items", [])
+                        ],
+                    )
+                elif event.method in (
+                    "scorer_changed",
+                    "keep_order_changed",
+                    "theme_changed",
+                    "layout_changed",
+                ):
+                    name = event.method.removesuffix("_changed")
+                    yield SweepStateChanged(
+                        uid=event.params["uid"],
+                        name=name,
+                        value=event.params.get(name),
+                    )
                 elif event.method == "resize":
                     size = SweepSize.from_json(event.params)
                     self.__size = size
@@ -716,6 +843,7 @@ class Sweep[I]:
                         self.__window_uid_current = window.uid_to
                     elif window.type == "closed":
                         self.__items.pop(window.uid_to, None)
+                        self.__items_lazy.pop(window.uid_to, None)
                     yield window
                 elif event.method == "field_missing":
                     ref = event.params.get("ref")
@@ -814,6 +942,48 @@ class Sweep[I]:
         if batch:
             await self.__peer.items_extend(uid=uid, items=batch)
 
+    async def items_extend_lazy(
+        self,
+        items: Iterable[I],
+        haystack: Callable[[I], str] = str,
+        uid: WindowId | None = None,
+    ) -> None:
+        """Extend list with items rendered on demand
+
+        Only searchable text returned by `haystack` is sent, items are converted
+        to `Candidate` when sweep shows them. Searchable text of the candidate
+        (active fields) must be equal to the text returned by `haystack`,
+        otherwise sweep rejects it and shows the text returned by `haystack`.
+        """
+        items = list(items)
+        texts = [haystack(item) for item in items]
+        item_id = await self.__peer.items_extend_lazy(uid=uid, items=texts)
+        items_lazy = self.__items_lazy[uid or self.__window_uid_current]
+        for item, text in zip(items, texts):
+            items_lazy[item_id] = (item, text)
+            item_id += 1
+
+    def __items_render(self, ids: list[int]) -> list[Any]:
+        """Render lazy items requested by sweep"""
+        rendered: list[Any] = []
+        for item_id in ids:
+            item, text = next(
+                (
+                    items_lazy[item_id]
+                    for items_lazy in self.__items_lazy.values()
+                    if item_id in items_lazy
+                ),
+                (None, ""),
+            )
+            if isinstance(item, ToCandidate):
+                item_obj = item.to_candidate()
+                if not isinstance(item_obj, dict):
+                    item_obj = item_obj.to_json()
+                rendered.append(item_obj)
+            else:
+                rendered.append(text)
+        return rendered
+
     async def item_update(
         self,
         index: int,
@@ -837,8 +1007,39 @@ class Sweep[I]:
 
     async def items_clear(self, uid: WindowId | None = None) -> None:
         """Clear list of searchable items"""
+        self.__items_lazy.pop(uid or self.__window_uid_current, None)
         await self.__peer.items_clear(uid=uid)
 
+    async def items_upsert(
+        self,
+        items: Iterable[I],
+        uid: WindowId | None = None,
+    ) -> None:
+        """Replace items with the same key, other items are appended
+
+        Cursor and marks stay attached to the replaced items.
+        """
+        batch: list[I | dict[str, Any]] = []
+        items_cache = self.__items[uid or self.__window_uid_current]
+        for item in items:
+            if isinstance(item, ToCandidate):
+                item_obj = item.to_candidate()
+                if not isinstance(item_obj, dict):
+                    item_obj = item_obj.to_json()
+                # cache is append only, so indices of the kept items stay valid
+                item_obj["_sweep_item_index"] = len(items_cache)
+                batch.append(item_obj)
+            else:
+                batch.append(item)
+            items_cache.append(item)
+        await self.__peer.items_upsert(uid=uid, items=batch)
+
+    async def items_remove(
+        self, keys: Iterable[str | int], uid: WindowId | None = None
+    ) -> None:
+        """Remove items by their keys, unknown keys are ignored"""
+        await self.__peer.items_remove(uid=uid, keys=[str(key) for key in keys])
+
     async def items_current(self, uid: WindowId | None = None) -> I | None:
         """Get currently selected item if any"""
         return self.__item_get(uid, await self.__peer.items_current(uid=uid))
@@ -848,6 +1049,33 @@ class Sweep[I]:
         items = await self.__peer.items_marked(uid)
         return [self.__item_get(uid, item) for item in items]
 
+    async def marks_set(
+        self, indices: Iterable[int], uid: WindowId | None = None
+    ) -> None:
+        """Mark items by their indices, other items are un-marked"""
+        await self.__peer.marks_set(uid=uid, indices=list(indices))
+
+    async def marks_toggle(
+        self, indices: Iterable[int], uid: WindowId | None = None
+    ) -> None:
+        """Toggle marks of items by their indices"""
+        await self.__peer.marks_toggle(uid=uid, indices=list(indices))
+
+    async def marks_clear(self, uid: WindowId | None = None) -> None:
+        """Un-mark all items"""
+        await self.__peer.marks_clear(uid=uid)
+
+    async def marks_policy_set(
+        self,
+        limit: int | None = None,
+        keep_on_clear: bool = True,
+        uid: WindowId | None = None,
+    ) -> None:
+        """Set limit of marked items and whether they are kept by `items_clear`"""
+        await self.__peer.marks_policy_set(
+            uid=uid, limit=limit, keep_on_clear=keep_on_clear
+        )
+
     async def cursor_set(self, position: int, uid: WindowId | None = None) -> None:
         """Set cursor to specified position"""
         await self.__peer.cursor_set(uid=uid, position=position)
@@ -861,6 +1089,30 @@ class Sweep[I]:
         query: str = await self.__peer.query_get(uid=uid)
         return query
 
+    async def action_run(
+        self, name: str, args: Any = None, uid: WindowId | None = None
+    ) -> None:
+        """Run action by its name as listed in help, same as pressing bound key"""
+        await self.__peer.action_run(uid=uid, name=name, args=args)
+
+    async def subscribe(
+        self,
+        events: Iterable[
+            Literal[
+                "query_changed",
+                "cursor_changed",
+                "marks_changed",
+                "scorer_changed",
+                "keep_order_changed",
+                "theme_changed",
+                "layout_changed",
+            ]
+        ],
+        uid: WindowId | None = None,
+    ) -> None:
+        """Subscribe to the window state change events, empty list unsubscribes"""
+        await self.__peer.subscribe(uid=uid, events=list(events))
+
     async def prompt_set(
         self,
         prompt: str | None = None,
@@ -884,6 +1136,57 @@ class Sweep[I]:
         """Whether to show preview associated with the current item"""
         await self.__peer.preview_set(uid=uid, value=value)
 
+    async def preview_layout_set(
+        self,
+        layout: str | None,
+        uid: WindowId | None = None,
+    ) -> None:
+        """Set placement of the large preview `position(,size)`"""
+        await self.__peer.preview_layout_set(uid=uid, layout=layout)
+
+    async def scorer_set(
+        self, name: str | None = None, uid: WindowId | None = None
+    ) -> None:
+        """Switch scorer by name, next scorer is chosen if name is not specified"""
+        await self.__peer.scorer_set(uid=uid, name=name)
+
+    async def keep_order(
+        self, value: bool | None = None, uid: WindowId | None = None
+    ) -> None:
+        """Whether to keep order of the items, toggles if value is not specified"""
+        await self.__peer.keep_order(uid=uid, value=value)
+
+    async def theme_set(self, theme: str, uid: WindowId | None = None) -> None:
+        """Set theme, same format as `--theme` argument"""
+        await self.__peer.theme_set(uid=uid, theme=theme)
+
+    async def theme_get(self, uid: WindowId | None = None) -> dict[str, Any]:
+        """Get current theme"""
+        theme: dict[str, Any] = await self.__peer.theme_get(uid=uid)
+        return theme
+
+    async def layout_set(
+        self, layout: str | None = None, uid: WindowId | None = None
+    ) -> None:
+        """Set window layout, same format as `--layout` argument"""
+        await self.__peer.layout_set(uid=uid, layout=layout)
+
+    async def theme_reload(self) -> None:
+        """Reload themes loaded from theme files for all windows"""
+        await self.__peer.theme_reload()
+
+    async def discover(self) -> dict[str, Any]:
+        """OpenRPC-like description of all methods supported by sweep"""
+        return await self.__peer.call("rpc.discover")
+
+    async def history_set(
+        self,
+        path: str | None,
+        uid: WindowId | None = None,
+    ) -> None:
+        """Set file used to store history of submitted queries"""
+        await self.__peer.history_set(uid=uid, path=path)
+
     async def footer_set(
         self,
         footer: View | None,
@@ -901,25 +1204,31 @@ class Sweep[I]:
     async def bind(
         self,
         key: str,
-        tag: str,
+        tag: str | list[str],
         desc: str = "",
         handler: BindHandler[I] | None = None,
         uid: WindowId | None = None,
+        mode: Literal["insert", "normal"] | None = None,
     ) -> None:
         """Assign new key binding
 
         Arguments:
             - `key` chord combination that triggers the bind
-            - `tag` unique bind identifier if it is empty bind is removed
+            - `tag` unique bind identifier if it is empty bind is removed, list
+               of tags (actions) is executed in order on a single key press
             - `description` of the bind shown in sweep help
             - `handler` callback if it no specified `SweepBind` event is generated
-               otherwise, it called on key press
+               otherwise, it called on key press, not supported for a list of tags
+            - `mode` key map of the binding, `normal` binds key in vi normal mode
         """
-        if tag and handler:
-            self.__binds[tag] = handler
-        else:
-            self.__binds.pop(tag, None)
-        await self.__peer.bind(uid=uid, key=key, tag=tag, desc=desc)
+        if isinstance(tag, str):
+            if tag and handler:
+                self.__binds[tag] = handler
+            else:
+                self.__binds.pop(tag, None)
+        elif handler is not None:
+            raise ValueError("handler can not be used with a list of tags")
+        await self.__peer.bind(uid=uid, key=key, tag=tag, desc=desc, mode=mode)
 
     async def window_switch(self, uid: WindowId, close: bool = False) -> bool:
         """Push new empty state
@@ -1218,7 +1527,13 @@ class RpcPeer:
 
         self.__requests[id] = future
         self.__submit_message(RpcRequest(method, params, id))
-        return await future
+        try:
+            return await future
+        except CancelledError:
+            # let the other peer abort the handler of the request
+            if self.__requests.pop(id, None) is not None and not self.__is_terminated:
+                self.notify("$/cancelRequest", id=id)
+            raise
 
     def __getattr__(self, method: str) -> Callable[..., Any]:
         """Convenient way to call remote methods"""